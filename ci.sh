@@ -4,3 +4,6 @@ set -euxo pipefail
 
 cargo build --features hal-02
 cargo build --features hal-1
+cargo build --features hal-1,rp2040-pio
+cargo test --features hal-mock
+cargo test --features hal-mock,dump
@@ -0,0 +1,172 @@
+//! Output awaiting the show durations instead of blocking on them.
+//!
+//! Most of a frame is spent showing rows, so awaiting these delays lets an async
+//! executor (e.g. Embassy) run other tasks for most of the refresh time. Shifting
+//! is still blocking, as its pulses are far shorter than any context switch.
+//!
+//! The delay is abstracted by [`AsyncDelay`], which `embedded-hal-async` delays
+//! implement in one line:
+//!
+//! ```ignore
+//! struct Delay<D>(D);
+//!
+//! impl<D: embedded_hal_async::delay::DelayNs> AsyncDelay for Delay<D> {
+//!     async fn delay_us(&mut self, us: u32) {
+//!         self.0.delay_us(us).await
+//!     }
+//! }
+//! ```
+
+use core::future::Future;
+
+use crate::{pins::*, pipeline::DisplayPipeline, DelayProvider, Error, FrameStorage, Hub75};
+
+/// An asynchronous microsecond delay.
+pub trait AsyncDelay {
+    /// Complete after the provided number of microseconds.
+    fn delay_us(&mut self, us: u32) -> impl Future<Output = ()>;
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Output the framebuffer to the display, awaiting `async_delay` while rows are
+    /// shown.
+    ///
+    /// `delay` times the clock and latch pulses (see
+    /// [`NoDelay`](crate::NoDelay) to omit them), and the latch guard.
+    ///
+    /// Does nothing while the display is [blanked](Self::blank). S-PWM driver chips
+    /// are not supported, and fail with
+    /// [`ConfigError::UnsupportedChipMode`](crate::ConfigError::UnsupportedChipMode).
+    ///
+    /// *This function is time-sensitive and should be awaited as often as possible.*
+    pub async fn output_async<Delay: DelayProvider, Async: AsyncDelay>(
+        &mut self,
+        delay: &mut Delay,
+        async_delay: &mut Async,
+    ) -> Result<(), Error<E>> {
+        self.check_pwm_mode()?;
+
+        self.await_frame(delay, async_delay)
+            .await
+            .map_err(Error::Pins)
+    }
+
+    /// Output the framebuffer, see [`output_async`](Self::output_async).
+    async fn await_frame<Delay: DelayProvider, Async: AsyncDelay>(
+        &mut self,
+        delay: &mut Delay,
+        async_delay: &mut Async,
+    ) -> Result<(), E> {
+        if self.blanked {
+            return Ok(());
+        }
+
+        self.limit_power();
+
+        for row in self.scan_order {
+            for mask in self.ftc.masks() {
+                self.shift_row(delay, row, mask)?;
+
+                if mask == self.ftc.masks().start {
+                    self.row_pins.set_row(&self.row_map[row as usize])?;
+                }
+
+                if self.latch_guard_us > 0 {
+                    delay.delay_us(self.latch_guard_us);
+                }
+
+                self.data_pins.latch(delay)?;
+                self.data_pins.enable_output()?;
+                async_delay.delay_us(self.show_duration(row, &mask)).await;
+                self.data_pins.disable_output()?;
+            }
+        }
+
+        self.complete_frame();
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use super::*;
+    use crate::{hal::mock::DelayUs, test_utils::*};
+
+    /// A delay recording into the log, pending once before completing.
+    struct YieldingDelay<'a>(&'a Log);
+
+    impl AsyncDelay for YieldingDelay<'_> {
+        async fn delay_us(&mut self, us: u32) {
+            let mut pending = true;
+
+            core::future::poll_fn(|_| {
+                if core::mem::take(&mut pending) {
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            })
+            .await;
+
+            ProbeDelay(self.0).delay_us(us);
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        let mut polls = 1;
+
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return (output, polls),
+                Poll::Pending => polls += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn matches_blocking_output() {
+        let blocking = Log::default();
+        let awaited = Log::default();
+
+        display::<2>(&blocking)
+            .output(&mut ProbeDelay(&blocking))
+            .unwrap();
+
+        let mut display = display::<2>(&awaited);
+        let (result, polls) =
+            block_on(display.output_async(&mut ProbeDelay(&awaited), &mut YieldingDelay(&awaited)));
+
+        result.unwrap();
+
+        assert_eq!(*awaited.borrow(), *blocking.borrow());
+        // every show duration yields once
+        assert_eq!(polls, 16 * 2 + 1);
+    }
+}
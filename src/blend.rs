@@ -0,0 +1,91 @@
+//! Blending of drawn pixels onto the framebuffer.
+
+/// How drawn pixels are combined with the framebuffer content.
+///
+/// Blending is done in the stored (gamma corrected) space: the drawn color is
+/// gamma corrected first and then combined with the stored value. Under
+/// [`Add`](Self::Add), this makes black source pixels exact no-ops, so sprites
+/// with black backgrounds composite for free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BlendMode {
+    /// Overwrite the stored pixel.
+    #[default]
+    Replace,
+    /// Add to the stored pixel, saturating each channel.
+    Add,
+    /// Keep the brighter value of each channel.
+    Max,
+    /// Composite over the stored pixel with an opacity, where 255 is opaque.
+    ///
+    /// E.g. drawing black at half opacity darkens what is below it, for soft
+    /// shadows or translucent overlays on top of the existing content.
+    Alpha(u8),
+}
+
+impl BlendMode {
+    /// Combine a (gamma corrected) source color with the stored color.
+    pub(crate) fn apply(&self, stored: (u8, u8, u8), source: (u8, u8, u8)) -> (u8, u8, u8) {
+        match self {
+            Self::Replace => source,
+            Self::Add => (
+                stored.0.saturating_add(source.0),
+                stored.1.saturating_add(source.1),
+                stored.2.saturating_add(source.2),
+            ),
+            Self::Max => (
+                stored.0.max(source.0),
+                stored.1.max(source.1),
+                stored.2.max(source.2),
+            ),
+            Self::Alpha(alpha) => {
+                let mix = |stored: u8, source: u8| {
+                    ((source as u16 * *alpha as u16 + stored as u16 * (255 - *alpha as u16) + 127)
+                        / 255) as u8
+                };
+
+                (
+                    mix(stored.0, source.0),
+                    mix(stored.1, source.1),
+                    mix(stored.2, source.2),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_saturates() {
+        assert_eq!(
+            BlendMode::Add.apply((200, 10, 255), (100, 20, 1)),
+            (255, 30, 255)
+        );
+    }
+
+    #[test]
+    fn add_black_is_noop() {
+        assert_eq!(BlendMode::Add.apply((12, 34, 56), (0, 0, 0)), (12, 34, 56));
+    }
+
+    #[test]
+    fn max() {
+        assert_eq!(
+            BlendMode::Max.apply((200, 10, 0), (100, 20, 0)),
+            (200, 20, 0)
+        );
+    }
+
+    #[test]
+    fn alpha() {
+        let stored = (200, 10, 0);
+        let source = (0, 110, 255);
+
+        assert_eq!(BlendMode::Alpha(0).apply(stored, source), stored);
+        assert_eq!(BlendMode::Alpha(255).apply(stored, source), source);
+        assert_eq!(BlendMode::Alpha(128).apply(stored, source), (100, 60, 128));
+    }
+}
@@ -0,0 +1,507 @@
+//! Multiple buffering, drawing into a back buffer while the display shows a
+//! complete front buffer.
+
+#[cfg(target_has_atomic = "8")]
+use core::{
+    borrow::{Borrow, BorrowMut},
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{
+    gamma::Gamma, pins::*, pipeline::DisplayPipeline, scan::Progress, DelayProvider, Error,
+    FrameBuffer, FrameStorage, Hub75,
+};
+
+/// A display drawn to through a back buffer, which is swapped with the shown
+/// framebuffer at frame boundaries.
+///
+/// With resumable output (e.g. [`output_step`](Self::output_step)), draws to the
+/// display itself become visible mid-frame, tearing animations. Drawing to the
+/// back buffer instead, and [`swap`](Self::swap)ping once a frame is complete,
+/// only ever shows complete frames.
+///
+/// After a swap, the back buffer holds the previously shown frame.
+pub struct DoubleBuffered<
+    'a,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    display:
+        &'a mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    back: &'a mut FrameBuffer<WIDTH, HEIGHT>,
+    swap_pending: bool,
+}
+
+impl<
+        'a,
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > DoubleBuffered<'a, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    pub fn new(
+        display: &'a mut Hub75<
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS,
+            ColorPins,
+            RowPins,
+            DataPins,
+            Frame,
+            Pipeline,
+        >,
+        back: &'a mut FrameBuffer<WIDTH, HEIGHT>,
+    ) -> Self {
+        back.convert(display.selected_pipeline());
+
+        Self {
+            display,
+            back,
+            swap_pending: false,
+        }
+    }
+
+    /// Show the back buffer from the next frame boundary on.
+    ///
+    /// Draws until then are still included in the swapped frame.
+    pub fn swap(&mut self) {
+        self.swap_pending = true;
+    }
+
+    /// Whether a requested swap has not yet taken effect.
+    pub fn is_swap_pending(&self) -> bool {
+        self.swap_pending
+    }
+
+    /// Output a frame (see [`Hub75::output`]), swapping before it if requested.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<u32, E> {
+        self.apply_swap();
+
+        self.display.output(delay)
+    }
+
+    /// Output the next (row, bit plane) pair (see [`Hub75::output_step`]), swapping
+    /// before it if requested and it starts a frame.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output_step<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+    ) -> Result<Progress, Error<E>> {
+        self.apply_swap();
+
+        self.display.output_step(delay)
+    }
+
+    /// The display, e.g. for its settings.
+    ///
+    /// *Draws to it are replaced by the next swap.*
+    pub fn display(
+        &mut self,
+    ) -> &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        self.display
+    }
+
+    /// Swap the buffers if requested and the display is at a frame boundary.
+    fn apply_swap(&mut self) {
+        if !self.display.at_frame_start() {
+            return;
+        }
+
+        if core::mem::take(&mut self.swap_pending) {
+            // in case the pipeline changed since the back buffer was drawn
+            self.back.convert(self.display.selected_pipeline());
+            core::mem::swap(&mut *self.display.frame, self.back);
+            // the shown frame was drawn through the display
+            self.back.pipeline = self.display.selected_pipeline();
+            self.display.mark_changed(true);
+        }
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Dimensions
+    for DoubleBuffered<'_, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > DrawTarget
+    for DoubleBuffered<'_, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.back.convert(self.display.selected_pipeline());
+        self.back.draw_iter(pixels)
+    }
+}
+
+/// The middle slot of a [`TripleBuffer`] holds a frame not yet taken by the
+/// consumer.
+#[cfg(target_has_atomic = "8")]
+const FRESH: u8 = 1 << 2;
+
+/// Three framebuffers passing complete frames from a producer to a consumer (e.g.
+/// a refresh interrupt), without either ever waiting for the other.
+///
+/// The producer draws into its back buffer and
+/// [`publish`](Producer::publish)es it, exchanging it with the middle buffer. The
+/// consumer [`take`](Consumer::take)s the middle buffer if a newer frame was
+/// published, exchanging it with its front buffer. A producer faster than the
+/// consumer overwrites unconsumed frames (only the newest is shown), and a slower
+/// one leaves the consumer showing its last frame.
+///
+/// The consumer is the framebuffer storage of a display (see
+/// [`Hub75::new_with_consumer`]), which scans directly out of the front buffer, so
+/// this takes only one more buffer of RAM than [`DoubleBuffered`].
+///
+/// The buffer handed back to the producer holds an older frame, so each frame
+/// should be drawn completely.
+///
+/// *Only available on targets with atomic compare-and-swap.*
+#[cfg(target_has_atomic = "8")]
+pub struct TripleBuffer<const WIDTH: usize, const HEIGHT: usize> {
+    frames: [UnsafeCell<FrameBuffer<WIDTH, HEIGHT>>; 3],
+    /// The index of the middle buffer, and whether it is [`FRESH`].
+    middle: AtomicU8,
+}
+
+// SAFETY: every buffer is owned by exactly one of the producer, the consumer, or the
+// middle slot at any time, with ownership exchanged atomically with acquire/release
+// ordering.
+#[cfg(target_has_atomic = "8")]
+unsafe impl<const WIDTH: usize, const HEIGHT: usize> Sync for TripleBuffer<WIDTH, HEIGHT> {}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> TripleBuffer<WIDTH, HEIGHT> {
+    /// Create three all black framebuffers, which can be placed in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            frames: [
+                UnsafeCell::new(FrameBuffer::new()),
+                UnsafeCell::new(FrameBuffer::new()),
+                UnsafeCell::new(FrameBuffer::new()),
+            ],
+            middle: AtomicU8::new(1),
+        }
+    }
+
+    /// Split into the producer and consumer halves, discarding any published
+    /// frame.
+    pub fn split(&mut self) -> (Producer<'_, WIDTH, HEIGHT>, Consumer<'_, WIDTH, HEIGHT>) {
+        *self.middle.get_mut() = 1;
+
+        let shared = &*self;
+
+        (Producer { shared, back: 0 }, Consumer { shared, front: 2 })
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> Default for TripleBuffer<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The drawing half of a [`TripleBuffer`].
+#[cfg(target_has_atomic = "8")]
+pub struct Producer<'a, const WIDTH: usize, const HEIGHT: usize> {
+    shared: &'a TripleBuffer<WIDTH, HEIGHT>,
+    back: u8,
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> Producer<'_, WIDTH, HEIGHT> {
+    /// Hand the drawn frame to the consumer, continuing with another buffer.
+    pub fn publish(&mut self) {
+        let middle = self.shared.middle.swap(self.back | FRESH, Ordering::AcqRel);
+
+        self.back = middle & !FRESH;
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> Dimensions for Producer<'_, WIDTH, HEIGHT> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Producer<'_, WIDTH, HEIGHT> {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // SAFETY: the back buffer is owned by the producer
+        unsafe { &mut *self.shared.frames[self.back as usize].get() }.draw_iter(pixels)
+    }
+}
+
+/// The output half of a [`TripleBuffer`].
+#[cfg(target_has_atomic = "8")]
+pub struct Consumer<'a, const WIDTH: usize, const HEIGHT: usize> {
+    shared: &'a TripleBuffer<WIDTH, HEIGHT>,
+    front: u8,
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> Consumer<'_, WIDTH, HEIGHT> {
+    /// Take the newest published frame into the front buffer, returning whether
+    /// there was one.
+    pub fn take(&mut self) -> bool {
+        if self.shared.middle.load(Ordering::Acquire) & FRESH == 0 {
+            return false;
+        }
+
+        // only the consumer clears the fresh flag, so the middle is still fresh
+        let middle = self.shared.middle.swap(self.front, Ordering::AcqRel);
+
+        self.front = middle & !FRESH;
+
+        true
+    }
+
+    /// The front buffer, i.e. the last taken frame.
+    pub fn frame(&self) -> &FrameBuffer<WIDTH, HEIGHT> {
+        // SAFETY: the front buffer is owned by the consumer
+        unsafe { &*self.shared.frames[self.front as usize].get() }
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> Borrow<FrameBuffer<WIDTH, HEIGHT>>
+    for Consumer<'_, WIDTH, HEIGHT>
+{
+    fn borrow(&self) -> &FrameBuffer<WIDTH, HEIGHT> {
+        self.frame()
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> BorrowMut<FrameBuffer<WIDTH, HEIGHT>>
+    for Consumer<'_, WIDTH, HEIGHT>
+{
+    fn borrow_mut(&mut self) -> &mut FrameBuffer<WIDTH, HEIGHT> {
+        // SAFETY: the front buffer is owned by the consumer
+        unsafe { &mut *self.shared.frames[self.front as usize].get() }
+    }
+}
+
+/// The display scans directly out of the front buffer.
+#[cfg(target_has_atomic = "8")]
+impl<const WIDTH: usize, const HEIGHT: usize> FrameStorage<WIDTH, HEIGHT>
+    for Consumer<'_, WIDTH, HEIGHT>
+{
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<
+        'a,
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Consumer<'a, WIDTH, HEIGHT>>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new Hub75x display instance like [`new`](Hub75::new), outputting
+    /// the frames published to a [`TripleBuffer`] straight from its front buffer.
+    ///
+    /// Draws to the display itself are replaced by the next
+    /// [taken](Self::take_published) frame.
+    pub fn new_with_consumer(
+        consumer: Consumer<'a, WIDTH, HEIGHT>,
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        Self::with_ratio(consumer, color_pins, row_pins, data_pins, on_ratio)
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<
+        'a,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Pipeline: DisplayPipeline,
+    >
+    Hub75<
+        WIDTH,
+        HEIGHT,
+        SCAN,
+        BITS,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Consumer<'a, WIDTH, HEIGHT>,
+        Pipeline,
+    >
+{
+    /// [`Take`](Consumer::take) the newest published frame, if there is one,
+    /// returning whether there was.
+    ///
+    /// The frame is shown from the next output on, so this should be called
+    /// between frames (e.g. on [`Progress::FrameComplete`] of resumable output).
+    pub fn take_published(&mut self) -> bool {
+        if !self.frame.0.take() {
+            return false;
+        }
+
+        // in case the frame was drawn through another pipeline
+        let pipeline = self.selected_pipeline();
+
+        self.frame.convert(pipeline);
+        self.mark_changed(true);
+
+        true
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use embedded_graphics::{pixelcolor::RgbColor, Drawable};
+
+    use super::*;
+    use crate::{test_utils::*, Hub75_64_32_2};
+
+    #[test]
+    fn swap_at_frame_boundary() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut back = FrameBuffer::new();
+        let mut buffered = DoubleBuffered::new(&mut display, &mut back);
+
+        Pixel(Point::new(0, 0), Rgb565::RED)
+            .draw(&mut buffered)
+            .unwrap();
+        buffered.output_step(&mut ProbeDelay(&log)).unwrap();
+        buffered.swap();
+
+        // mid-frame
+        buffered.output_step(&mut ProbeDelay(&log)).unwrap();
+
+        assert!(buffered.is_swap_pending());
+        assert_eq!(buffered.display().frame.data[0][0], (0, 0, 0));
+
+        while buffered.output_step(&mut ProbeDelay(&log)).unwrap() != Progress::FrameComplete {}
+
+        buffered.output_step(&mut ProbeDelay(&log)).unwrap();
+
+        assert!(!buffered.is_swap_pending());
+        assert_eq!(buffered.display().frame.data[0][0], (255, 0, 0));
+        assert_eq!(back.data[0][0], (0, 0, 0));
+    }
+
+    #[test]
+    fn triple_buffer_newest_frame() {
+        let log = Log::default();
+        let mut buffers = TripleBuffer::new();
+        let (mut producer, consumer) = buffers.split();
+        let mut display = Hub75_64_32_2::<1, _, _, _, _>::new_with_consumer(
+            consumer,
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        assert!(!display.take_published());
+
+        for color in [Rgb565::RED, Rgb565::GREEN] {
+            producer.clear(color).unwrap();
+            producer.publish();
+        }
+
+        // not yet published
+        producer.clear(Rgb565::BLUE).unwrap();
+
+        assert!(display.take_published());
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(display.frame().data[0][0], (0, 255, 0));
+        assert!(!display.take_published());
+
+        producer.publish();
+
+        assert!(display.take_published());
+        assert_eq!(display.frame().data[0][0], (0, 0, 255));
+    }
+}
@@ -0,0 +1,587 @@
+//! Configuration of panel driver chips which require it before showing anything.
+
+use crate::{
+    fmt, hal::PinState, pins::*, pipeline::DisplayPipeline, scale_color, ConfigError,
+    DelayProvider, Error, FrameStorage, Hub75,
+};
+
+/// How the driver chips of a panel generate their PWM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipMode {
+    /// Plain shift register chips (e.g. ICN2037, FM6126A): every bit plane is
+    /// shifted, latched, and shown for its duration through the output enable pin.
+    #[default]
+    Pwm,
+    /// S-PWM chips (e.g. ICN2053, ICN2065) with their own frame memory: the whole
+    /// frame is shifted as 16 bit grayscale values and latched once, and the chips
+    /// generate the PWM from `gclk_per_row` grayscale clock pulses per scan row.
+    ///
+    /// The data pins must provide a grayscale clock (see
+    /// [`IsDataPins::pulse_gclk`]), and the chips must be configured first (see
+    /// [`Hub75::init_icn2053`]).
+    SPwm { gclk_per_row: u16 },
+}
+
+/// Shift a 16 bit configuration word into every driver chip of a row, repeated
+/// across `width` columns (most significant bit first) on all color pins, with the
+/// latch held for the last `latch_clocks` clocks.
+///
+/// The number of clocks during which the latch is held selects the register.
+pub(crate) fn write_register<E, ColorPins, DataPins, Delay>(
+    color_pins: &mut ColorPins,
+    data_pins: &mut DataPins,
+    delay: &mut Delay,
+    width: usize,
+    word: u16,
+    latch_clocks: usize,
+) -> Result<(), E>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Delay: DelayProvider,
+{
+    for column in 0..width {
+        let color = if (word >> (15 - column % 16)) & 0x1 == 1 {
+            (0xff, 0xff, 0xff)
+        } else {
+            (0, 0, 0)
+        };
+
+        color_pins.set_colors::<1>(&color, &color, &0)?;
+
+        if column + latch_clocks == width {
+            data_pins.set_latch(PinState::High)?;
+        }
+
+        data_pins.shift(delay)?;
+    }
+
+    data_pins.set_latch(PinState::Low)
+}
+
+/// Shift `latch_clocks` clocks of zeroes with the latch held, which S-PWM chips
+/// interpret as a command.
+fn command<E, ColorPins, DataPins, Delay>(
+    color_pins: &mut ColorPins,
+    data_pins: &mut DataPins,
+    delay: &mut Delay,
+    latch_clocks: usize,
+) -> Result<(), E>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Delay: DelayProvider,
+{
+    write_register(color_pins, data_pins, delay, latch_clocks, 0, latch_clocks)
+}
+
+/// The ICN2053 latch clocks of a vertical sync, showing the shifted frame.
+const ICN2053_VSYNC: usize = 3;
+/// The ICN2053 latch clocks of the command unlocking a register write.
+const ICN2053_PRE_ACTIVE: usize = 14;
+/// The ICN2053 latch clocks of the four configuration register writes.
+const ICN2053_REGISTERS: [usize; 4] = [4, 6, 8, 10];
+
+/// The FM6126A (and FM6127) register 11 value: all outputs enabled.
+const FM6126A_REG11: u16 = 0b0111_1111_1111_1111;
+/// The FM6126A (and FM6127) register 12 value: output enable polarity.
+const FM6126A_REG12: u16 = 0b0000_0000_0100_0000;
+
+/// The MBI5124 latch clocks of the command unlocking a configuration write.
+const MBI5124_ENABLE_CONFIG: usize = 14;
+/// The MBI5124 latch clocks of a configuration write.
+const MBI5124_WRITE_CONFIG: usize = 11;
+/// The MBI5124 configuration bits of the current gain.
+const MBI5124_GAIN_MASK: u16 = 0b0000_0000_0011_1111;
+
+/// The initialization sequence of a family of panel driver chips.
+///
+/// The sequence is:
+/// 1. [`preamble_clocks`](Self::preamble_clocks) clocks of zeroes.
+/// 2. Every [`config_words`](Self::config_words) entry, each preceded by the
+///    [`unlock_command`](Self::unlock_command) if any.
+/// 3. [`extra_latches`](Self::extra_latches) plain latches.
+///
+/// Every hook does nothing by default, so chips without configuration (e.g.
+/// ICN2037) need no more than an empty impl, like [`Generic`].
+pub trait ChipInit {
+    /// The number of clocks of zeroes shifted first, e.g. to flush the chips.
+    fn preamble_clocks(&self) -> usize {
+        0
+    }
+
+    /// The latch clocks of a command written before every configuration word,
+    /// e.g. to unlock the configuration registers.
+    fn unlock_command(&self) -> Option<usize> {
+        None
+    }
+
+    /// The configuration words (repeated for every chip of a row), and the latch
+    /// clocks selecting the register written.
+    fn config_words(&self) -> &[(u16, usize)] {
+        &[]
+    }
+
+    /// The number of plain latches after configuration.
+    fn extra_latches(&self) -> usize {
+        0
+    }
+
+    /// How the chips generate their PWM once initialized.
+    fn mode(&self) -> ChipMode {
+        ChipMode::Pwm
+    }
+}
+
+/// Plain shift register chips (e.g. ICN2037, MBI5124 at its power-up defaults)
+/// without configuration.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Generic;
+
+impl ChipInit for Generic {}
+
+/// FM6126A (or FM6127) chips, which stay dark until their configuration registers
+/// are written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fm6126a;
+
+impl ChipInit for Fm6126a {
+    fn config_words(&self) -> &[(u16, usize)] {
+        &[(FM6126A_REG11, 11), (FM6126A_REG12, 12)]
+    }
+}
+
+/// MBI5124 chips with a current gain, scaling the output current (and so the
+/// brightness) of all LEDs in hardware.
+#[derive(Debug, Clone, Copy)]
+pub struct Mbi5124 {
+    config: [(u16, usize); 1],
+}
+
+impl Mbi5124 {
+    /// The gain is 6 bits wide, so it must be at most 63, or this fails with
+    /// [`ConfigError::GainOutOfRange`]. The other configuration bits are written
+    /// as their power-up defaults (zero).
+    pub const fn new(gain: u8) -> Result<Self, ConfigError> {
+        if gain as u16 & !MBI5124_GAIN_MASK != 0 {
+            return Err(ConfigError::GainOutOfRange);
+        }
+
+        Ok(Self {
+            config: [(gain as u16, MBI5124_WRITE_CONFIG)],
+        })
+    }
+}
+
+impl ChipInit for Mbi5124 {
+    fn unlock_command(&self) -> Option<usize> {
+        Some(MBI5124_ENABLE_CONFIG)
+    }
+
+    fn config_words(&self) -> &[(u16, usize)] {
+        &self.config
+    }
+}
+
+/// ICN2053 (or ICN2065) S-PWM chips, driven in [`ChipMode::SPwm`].
+#[derive(Debug, Clone, Copy)]
+pub struct Icn2053 {
+    config: [(u16, usize); 4],
+    gclk_per_row: u16,
+}
+
+impl Icn2053 {
+    /// The four configuration register values depend on the panel (scan rate,
+    /// current, blanking), and are found in its documentation.
+    pub const fn new(registers: [u16; 4], gclk_per_row: u16) -> Self {
+        Self {
+            config: [
+                (registers[0], ICN2053_REGISTERS[0]),
+                (registers[1], ICN2053_REGISTERS[1]),
+                (registers[2], ICN2053_REGISTERS[2]),
+                (registers[3], ICN2053_REGISTERS[3]),
+            ],
+            gclk_per_row,
+        }
+    }
+}
+
+impl ChipInit for Icn2053 {
+    fn unlock_command(&self) -> Option<usize> {
+        Some(ICN2053_PRE_ACTIVE)
+    }
+
+    fn config_words(&self) -> &[(u16, usize)] {
+        &self.config
+    }
+
+    fn mode(&self) -> ChipMode {
+        ChipMode::SPwm {
+            gclk_per_row: self.gclk_per_row,
+        }
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new Hub75x display instance like [`new`](Self::new), and
+    /// initialize its driver chips.
+    pub fn new_with_chip<Chip: ChipInit, Delay: DelayProvider>(
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+        chip: &Chip,
+        delay: &mut Delay,
+    ) -> Result<Self, E> {
+        let mut display = Self::new(color_pins, row_pins, data_pins, on_ratio);
+
+        display.init_chip(chip, delay)?;
+
+        Ok(display)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Run the initialization sequence of the driver chips, and switch to their
+    /// [`ChipMode`].
+    ///
+    /// Chips losing their configuration on power loss must be initialized again
+    /// after the panel is powered up.
+    pub fn init_chip<Chip: ChipInit, Delay: DelayProvider>(
+        &mut self,
+        chip: &Chip,
+        delay: &mut Delay,
+    ) -> Result<(), E> {
+        fmt::trace!("init driver chips");
+
+        self.data_pins.disable_output()?;
+
+        for _ in 0..chip.preamble_clocks() {
+            self.color_pins
+                .set_colors::<1>(&(0, 0, 0), &(0, 0, 0), &0)?;
+            self.data_pins.shift(delay)?;
+        }
+
+        for (word, latch_clocks) in chip.config_words() {
+            if let Some(unlock) = chip.unlock_command() {
+                command(&mut self.color_pins, &mut self.data_pins, delay, unlock)?;
+            }
+
+            write_register(
+                &mut self.color_pins,
+                &mut self.data_pins,
+                delay,
+                WIDTH,
+                *word,
+                *latch_clocks,
+            )?;
+        }
+
+        for _ in 0..chip.extra_latches() {
+            self.data_pins.latch(delay)?;
+        }
+
+        self.set_chip_mode(chip.mode());
+
+        Ok(())
+    }
+
+    /// Configure FM6126A (or FM6127) driver chips, see [`Fm6126a`].
+    ///
+    /// This must be called once after power-up, before the first output.
+    pub fn init_fm6126a<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.init_chip(&Fm6126a, delay)
+    }
+
+    /// Set the current gain of MBI5124 driver chips, see [`Mbi5124`].
+    ///
+    /// Unlike a lower `on_ratio`, this dims the display without shortening the
+    /// shown bit planes.
+    ///
+    /// `gain` must be at most 63, or nothing is written and this fails with
+    /// [`ConfigError::GainOutOfRange`].
+    pub fn set_mbi5124_gain<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        gain: u8,
+    ) -> Result<(), Error<E>> {
+        let chip = Mbi5124::new(gain)?;
+
+        self.init_chip(&chip, delay).map_err(Error::Pins)
+    }
+
+    /// Configure ICN2053 (or ICN2065) driver chips, see [`Icn2053`], and switch to
+    /// [`ChipMode::SPwm`].
+    pub fn init_icn2053<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        registers: [u16; 4],
+        gclk_per_row: u16,
+    ) -> Result<(), E> {
+        self.init_chip(&Icn2053::new(registers, gclk_per_row), delay)
+    }
+
+    /// Shift the whole framebuffer into the frame memory of S-PWM driver chips, show
+    /// it, and scan all rows once with the grayscale clock.
+    ///
+    /// Every chip drives 16 columns. For every row and column of a chip, the 16 bit
+    /// values of all chips are shifted and then latched together.
+    pub(crate) fn output_spwm<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        gclk_per_row: u16,
+    ) -> Result<(), E> {
+        let chips = WIDTH.div_ceil(16);
+
+        for row in 0..SCAN {
+            let (upper_row, lower_row) = (&self.frame.data[row], &self.frame.data[row + SCAN]);
+
+            for channel in 0..16 {
+                for chip in 0..chips {
+                    let column = chip * 16 + channel;
+                    let (upper, lower) = match (upper_row.get(column), lower_row.get(column)) {
+                        (Some(upper), Some(lower)) => (
+                            scale_color(upper, &self.channel_scale),
+                            scale_color(lower, &self.channel_scale),
+                        ),
+                        _ => ((0, 0, 0), (0, 0, 0)),
+                    };
+
+                    for bit in (0..16).rev() {
+                        // expand the 8 bit values to 16 bits, and select the bit
+                        // as the most significant bit of a 1 bit color
+                        let plane = |value: u8| {
+                            if ((value as u16 * 0x101) >> bit) & 0x1 == 1 {
+                                0xff
+                            } else {
+                                0
+                            }
+                        };
+
+                        self.color_pins.set_colors::<1>(
+                            &(plane(upper.0), plane(upper.1), plane(upper.2)),
+                            &(plane(lower.0), plane(lower.1), plane(lower.2)),
+                            &0,
+                        )?;
+
+                        // the data latch is held for the last clock
+                        let last = chip + 1 == chips && bit == 0;
+
+                        if last {
+                            self.data_pins.set_latch(PinState::High)?;
+                        }
+
+                        self.data_pins.shift(delay)?;
+
+                        if last {
+                            self.data_pins.set_latch(PinState::Low)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        command(
+            &mut self.color_pins,
+            &mut self.data_pins,
+            delay,
+            ICN2053_VSYNC,
+        )?;
+
+        self.data_pins.enable_output()?;
+
+        for row in 0..SCAN {
+            self.row_pins.set_row(&self.row_map[row])?;
+
+            for _ in 0..gclk_per_row {
+                self.data_pins.pulse_gclk()?;
+            }
+        }
+
+        self.data_pins.disable_output()
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{hal::mock::PinState::*, test_utils::*, Hub75_64_32_2};
+
+    #[test]
+    fn fm6126a_sequence() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.init_fm6126a(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 2 * 64);
+        // register 11: every 16th column low, register 12: only bit 9 high
+        assert_eq!(shifted[16], [Low; 6]);
+        assert_eq!(shifted[17], [High; 6]);
+        assert_eq!(shifted[64 + 9], [High; 6]);
+        assert_eq!(shifted[64 + 10], [Low; 6]);
+
+        // the latch is held for the last 11 and 12 clocks respectively
+        let mut latched = false;
+        let mut held = std::vec::Vec::new();
+
+        for event in log.borrow().iter() {
+            match event {
+                Event::Pin(PinId::Lat, state) => {
+                    if *state == High {
+                        held.push(0);
+                    }
+
+                    latched = *state == High;
+                }
+                Event::Pin(PinId::Clk, High) if latched => *held.last_mut().unwrap() += 1,
+                _ => {}
+            }
+        }
+
+        assert_eq!(held, [11, 12]);
+    }
+
+    #[test]
+    fn mbi5124_gain() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        assert_eq!(
+            display.set_mbi5124_gain(&mut ProbeDelay(&log), 0b1110_0101),
+            Err(Error::Config(ConfigError::GainOutOfRange))
+        );
+        assert!(log.borrow().is_empty());
+
+        display
+            .set_mbi5124_gain(&mut ProbeDelay(&log), 0b10_0101)
+            .unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 14 + 64);
+
+        // the unlocking command is all zeroes, followed by the gain repeated per chip
+        assert!(shifted[..14 + 10].iter().all(|states| *states == [Low; 6]));
+        assert_eq!(
+            shifted[14 + 10..14 + 16]
+                .iter()
+                .map(|states| states[0])
+                .collect::<std::vec::Vec<_>>(),
+            [High, Low, Low, High, Low, High]
+        );
+    }
+
+    #[test]
+    fn custom_chip() {
+        struct Custom;
+
+        impl ChipInit for Custom {
+            fn preamble_clocks(&self) -> usize {
+                3
+            }
+
+            fn config_words(&self) -> &[(u16, usize)] {
+                &[(0xffff, 5)]
+            }
+
+            fn extra_latches(&self) -> usize {
+                2
+            }
+        }
+
+        let log = Log::default();
+        let _display = Hub75_64_32_2::<1, _, _, _>::new_with_chip(
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+            &Custom,
+            &mut ProbeDelay(&log),
+        )
+        .unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 3 + 64);
+        assert!(shifted[..3].iter().all(|states| *states == [Low; 6]));
+        assert!(shifted[3..].iter().all(|states| *states == [High; 6]));
+        // the configuration write, and the extra latches
+        assert_eq!(latched_rows(&log).len(), 3);
+    }
+
+    #[test]
+    fn spwm_frame() {
+        let log = Log::default();
+        let mut display = Hub75_64_32_2::<1, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            gclk_data_pins(&log),
+            0.5,
+        );
+
+        display
+            .init_icn2053(&mut ProbeDelay(&log), [0; 4], 8)
+            .unwrap();
+        display.set_pixel_raw(1, 0, (0xff, 0, 0)).unwrap();
+        log.borrow_mut().clear();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        // 16 bits of 64 columns for each row, and the vertical sync
+        assert_eq!(shifted.len(), 16 * 64 * 16 + 3);
+        // the second channel of the first chip of the first row
+        assert!(shifted[..64].iter().all(|states| states[0] == Low));
+        assert!(shifted[64..80].iter().all(|states| states[0] == High));
+        assert!(shifted[80..].iter().all(|states| states[0] == Low));
+
+        let gclk = log
+            .borrow()
+            .iter()
+            .filter(|event| **event == Event::Pin(PinId::Gclk, High))
+            .count();
+
+        assert_eq!(gclk, 16 * 8);
+        // one data latch per row and channel, and the vertical sync
+        assert_eq!(latched_rows(&log).len(), 16 * 16 + 1);
+    }
+}
@@ -0,0 +1,389 @@
+//! Integer color utilities operating directly on the framebuffer.
+//!
+//! These bypass [`Rgb565`] quantization, so gradients are smooth at high color
+//! depths.
+//!
+//! [`Rgb565`]: embedded_graphics::pixelcolor::Rgb565
+
+use embedded_graphics::{
+    geometry::Dimensions,
+    pixelcolor::{PixelColor, Rgb888},
+    primitives::Rectangle,
+};
+
+use crate::{pins::*, pipeline::DisplayPipeline, update, FrameStorage, Hub75};
+
+/// The axis along which a gradient progresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Axis {
+    /// From left to right.
+    Horizontal,
+    /// From top to bottom.
+    Vertical,
+}
+
+/// Convert a hue, saturation, value triple to a linear RGB color.
+///
+/// The hue wraps around the color wheel over the full `u8` range.
+pub const fn hsv_to_rgb(h: u8, s: u8, v: u8) -> (u8, u8, u8) {
+    if s == 0 {
+        return (v, v, v);
+    }
+
+    let (h, s, v) = (h as u16, s as u16, v as u16);
+
+    let region = h / 43;
+    let remainder = (h - region * 43) * 6;
+
+    let p = ((v * (255 - s)) >> 8) as u8;
+    let q = ((v * (255 - ((s * remainder) >> 8))) >> 8) as u8;
+    let t = ((v * (255 - ((s * (255 - remainder)) >> 8))) >> 8) as u8;
+    let v = v as u8;
+
+    match region {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    }
+}
+
+/// A hue, saturation, value color, e.g. for rainbow effects.
+///
+/// It can be drawn through [`Hub75::colored`], and written with
+/// [`write_colors`](Hub75::write_colors).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsv {
+    /// The hue, wrapping around the color wheel over the full `u8` range.
+    pub hue: u8,
+    pub saturation: u8,
+    pub value: u8,
+}
+
+impl Hsv {
+    pub const fn new(hue: u8, saturation: u8, value: u8) -> Self {
+        Self {
+            hue,
+            saturation,
+            value,
+        }
+    }
+}
+
+impl PixelColor for Hsv {
+    type Raw = ();
+}
+
+impl From<Hsv> for (u8, u8, u8) {
+    fn from(color: Hsv) -> Self {
+        hsv_to_rgb(color.hue, color.saturation, color.value)
+    }
+}
+
+impl From<Hsv> for Rgb888 {
+    fn from(color: Hsv) -> Self {
+        let (r, g, b) = color.into();
+
+        Rgb888::new(r, g, b)
+    }
+}
+
+/// The channel scales of blackbody whites from 1000K to 10000K in 500K steps,
+/// relative to 6500K (D65, the white point of sRGB).
+const BLACKBODY: [(u8, u8, u8); 19] = [
+    (255, 57, 0),
+    (255, 112, 0),
+    (255, 140, 18),
+    (255, 165, 73),
+    (255, 184, 108),
+    (255, 201, 138),
+    (255, 214, 164),
+    (255, 224, 187),
+    (255, 233, 208),
+    (255, 242, 226),
+    (255, 249, 241),
+    (255, 255, 255),
+    (245, 249, 255),
+    (235, 244, 255),
+    (227, 239, 255),
+    (220, 235, 255),
+    (214, 230, 255),
+    (208, 227, 255),
+    (204, 224, 255),
+];
+
+/// The approximate channel scale (see [`Hub75::set_color_temperature`]) shifting
+/// white to the color of a blackbody at `kelvin`.
+///
+/// 6500K is unity, lower temperatures are warmer (reducing blue and green) and
+/// higher ones cooler (reducing red). Temperatures are clamped to 1000-10000K.
+pub const fn kelvin_scale(kelvin: u16) -> (u8, u8, u8) {
+    let kelvin = if kelvin < 1000 {
+        1000
+    } else if kelvin > 10000 {
+        10000
+    } else {
+        kelvin
+    };
+    let offset = (kelvin - 1000) as u32;
+    let i = (offset / 500) as usize;
+
+    if i + 1 == BLACKBODY.len() {
+        return BLACKBODY[i];
+    }
+
+    gradient_step(BLACKBODY[i], BLACKBODY[i + 1], offset % 500, 501)
+}
+
+/// The linear interpolation of step `i` of `n` from `from` to `to`.
+///
+/// Step `0` is exactly `from` and step `n - 1` is exactly `to`.
+const fn gradient_step(from: (u8, u8, u8), to: (u8, u8, u8), i: u32, n: u32) -> (u8, u8, u8) {
+    const fn lerp(from: u8, to: u8, i: u32, n: u32) -> u8 {
+        if n <= 1 {
+            return from;
+        }
+
+        let from = from as i32;
+        let to = to as i32;
+
+        (from + (to - from) * i as i32 / (n as i32 - 1)) as u8
+    }
+
+    (
+        lerp(from.0, to.0, i, n),
+        lerp(from.1, to.1, i, n),
+        lerp(from.2, to.2, i, n),
+    )
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Fill a rectangle with a gradient from one linear color to another.
+    ///
+    /// The interpolation is done in linear space and the result is gamma corrected.
+    /// The parts of the rectangle outside of the display are clipped, but the
+    /// gradient still spans the whole rectangle.
+    pub fn fill_gradient(
+        &mut self,
+        rect: Rectangle,
+        from: (u8, u8, u8),
+        to: (u8, u8, u8),
+        direction: Axis,
+    ) {
+        let area = rect.intersection(&self.bounding_box());
+
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
+        };
+
+        let mut changed = false;
+
+        for y in area.top_left.y..=bottom_right.y {
+            for x in area.top_left.x..=bottom_right.x {
+                let (i, n) = match direction {
+                    Axis::Horizontal => (x - rect.top_left.x, rect.size.width),
+                    Axis::Vertical => (y - rect.top_left.y, rect.size.height),
+                };
+
+                if let Some(pixel) = self.frame.pixel_mut(x as usize, y as usize) {
+                    changed |= update(
+                        pixel,
+                        self.pipeline.correct(&gradient_step(from, to, i as u32, n)),
+                    );
+                }
+            }
+        }
+
+        self.mark_changed(changed);
+    }
+
+    /// Write linear colors in row-major order, like a strip of addressable LEDs.
+    ///
+    /// Colors are anything convertible into an RGB tuple, such as [`Hsv`] or
+    /// `smart_leds::RGB8`, so effects ported from addressable LED projects work
+    /// without conversion glue. Colors are gamma corrected, and colors past the
+    /// end of the display are ignored.
+    pub fn write_colors<C: Into<(u8, u8, u8)>>(&mut self, colors: impl IntoIterator<Item = C>) {
+        let pipeline = self.pipeline;
+        let mut changed = false;
+
+        for (pixel, color) in self.frame.pixels_mut().zip(colors) {
+            changed |= update(pixel, pipeline.correct(&color.into()));
+        }
+
+        self.mark_changed(changed);
+    }
+
+    /// Fill the display with a horizontal rainbow spanning its width, offset by
+    /// `phase`.
+    ///
+    /// Incrementing `phase` every frame scrolls the rainbow.
+    pub fn rainbow_fill(&mut self, phase: u8) {
+        let mut changed = false;
+
+        for (x, color) in (0..WIDTH).map(|x| {
+            let hue = (x * 256 / WIDTH) as u8;
+
+            (
+                x,
+                self.pipeline
+                    .correct(&hsv_to_rgb(phase.wrapping_add(hue), 255, 255)),
+            )
+        }) {
+            for row in self.frame.data.iter_mut() {
+                changed |= update(&mut row[x], color);
+            }
+        }
+
+        self.mark_changed(changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamma::Gamma;
+
+    #[test]
+    fn hsv_primaries() {
+        assert_eq!(hsv_to_rgb(0, 255, 255), (255, 0, 0));
+        assert_eq!(hsv_to_rgb(0, 0, 200), (200, 200, 200));
+        assert_eq!(hsv_to_rgb(85, 255, 255).1, 255);
+        assert_eq!(hsv_to_rgb(171, 255, 255).2, 255);
+        assert_eq!(hsv_to_rgb(128, 255, 0), (0, 0, 0));
+    }
+
+    #[test]
+    fn gradient_endpoints() {
+        let from = (10, 200, 0);
+        let to = (250, 0, 255);
+
+        for n in [2, 3, 32, 64] {
+            assert_eq!(gradient_step(from, to, 0, n), from);
+            assert_eq!(gradient_step(from, to, n - 1, n), to);
+        }
+
+        assert_eq!(gradient_step(from, to, 0, 1), from);
+    }
+
+    #[test]
+    fn kelvin_scales() {
+        assert_eq!(kelvin_scale(6500), (255, 255, 255));
+        assert_eq!(kelvin_scale(0), kelvin_scale(1000));
+        assert_eq!(kelvin_scale(u16::MAX), (204, 224, 255));
+        assert_eq!(kelvin_scale(2750), (255, 174, 90));
+
+        for kelvin in (1000..6500).step_by(100) {
+            let (warm, warmer) = (kelvin_scale(kelvin + 100), kelvin_scale(kelvin));
+
+            assert!(warmer.1 <= warm.1 && warmer.2 <= warm.2);
+        }
+    }
+
+    #[test]
+    fn gradient_monotonic() {
+        let from = (10, 200, 0);
+        let to = (250, 0, 255);
+        let n = 64;
+
+        for i in 1..n {
+            let previous = Gamma::POWER.correct(&gradient_step(from, to, i - 1, n));
+            let current = Gamma::POWER.correct(&gradient_step(from, to, i, n));
+
+            assert!(current.0 >= previous.0);
+            assert!(current.1 <= previous.1);
+            assert!(current.2 >= previous.2);
+        }
+    }
+
+    #[cfg(feature = "hal-mock")]
+    #[test]
+    fn led_strip_colors() {
+        use crate::test_utils::*;
+        use embedded_graphics::{geometry::Point, Drawable, Pixel};
+
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let red = Gamma::POWER.correct(&(255, 0, 0));
+
+        display.write_colors([(255, 0, 0), (0, 0, 0)]);
+        display.write_colors((0..3).map(|i| Hsv::new(i * 85, 255, 255)).skip(1));
+        Pixel(Point::new(5, 0), Hsv::new(0, 255, 255))
+            .draw(&mut display.colored())
+            .unwrap();
+
+        assert_eq!(
+            display.frame.data[0][..3],
+            [(0, 255, 0), (0, 0, 255), (0, 0, 0)]
+        );
+        assert_eq!(display.frame.data[0][5], red);
+    }
+
+    #[cfg(feature = "hal-mock")]
+    #[test]
+    fn clipped_gradient() {
+        use crate::test_utils::*;
+        use embedded_graphics::{
+            draw_target::DrawTarget,
+            geometry::{Point, Size},
+            pixelcolor::{Rgb565, RgbColor},
+        };
+
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let from = (0, 0, 0);
+        let to = (255, 0, 0);
+        let background = (0, 0, 255);
+
+        display.set_gamma(Gamma::LINEAR);
+        display.clear(Rgb565::BLUE).unwrap();
+
+        // overhangs the left and bottom edges
+        display.fill_gradient(
+            Rectangle::new(Point::new(-2, 30), Size::new(6, 4)),
+            from,
+            to,
+            Axis::Horizontal,
+        );
+
+        for x in 0..4 {
+            let step = gradient_step(from, to, x as u32 + 2, 6);
+
+            assert_eq!(display.frame.data[30][x], step);
+            assert_eq!(display.frame.data[31][x], step);
+        }
+        assert_eq!(display.frame.data[29][0], background);
+        assert_eq!(display.frame.data[30][4], background);
+
+        // overhangs the top and right edges
+        display.fill_gradient(
+            Rectangle::new(Point::new(62, -1), Size::new(4, 4)),
+            from,
+            to,
+            Axis::Vertical,
+        );
+
+        for y in 0..3 {
+            let step = gradient_step(from, to, y as u32 + 1, 4);
+
+            assert_eq!(display.frame.data[y][62..], [step; 2]);
+        }
+        assert_eq!(display.frame.data[3][63], background);
+        assert_eq!(display.frame.data[0][61], background);
+    }
+}
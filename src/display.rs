@@ -0,0 +1,215 @@
+//! A common interface of all display drivers.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{draw_target::DrawTarget, pixelcolor::Rgb565};
+
+use crate::{
+    pins::*,
+    pipeline::DisplayPipeline,
+    planes::{BitPlanes, Hub75Planes},
+    quad::Hub75Quad,
+    single::Hub75Single,
+    DelayProvider, FrameBuffer, FrameStorage, Hub75,
+};
+
+/// Represents a display driver, regardless of its size and pins.
+///
+/// This allows application and library code to be written generically over the
+/// display:
+///
+/// ```
+/// # use hub75_remastered::{DelayProvider, Hub75Display};
+/// fn refresh<D: Hub75Display>(display: &mut D, delay: &mut impl DelayProvider) {
+///     display.output(delay).ok();
+/// }
+/// ```
+pub trait Hub75Display: DrawTarget<Color = Rgb565, Error = Infallible> {
+    /// The framebuffer of the display.
+    type Frame;
+    /// The error of the pins.
+    type OutputError;
+
+    /// Output the framebuffer to the display.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::OutputError>;
+
+    /// Set the framebuffer to all black.
+    fn wipe(&mut self);
+
+    /// The framebuffer of the display.
+    fn frame(&self) -> &Self::Frame;
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75Display
+    for Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    type Frame = FrameBuffer<WIDTH, HEIGHT>;
+    type OutputError = E;
+
+    fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        Hub75::output(self, delay).map(|_idle| ())
+    }
+
+    fn wipe(&mut self) {
+        Hub75::wipe(self)
+    }
+
+    fn frame(&self) -> &Self::Frame {
+        Hub75::frame(self)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Display for Hub75Quad<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsQuadColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    type Frame = FrameBuffer<WIDTH, HEIGHT>;
+    type OutputError = E;
+
+    fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        Hub75Quad::output(self, delay)
+    }
+
+    fn wipe(&mut self) {
+        Hub75Quad::wipe(self)
+    }
+
+    fn frame(&self) -> &Self::Frame {
+        Hub75Quad::frame(self)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Display for Hub75Single<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    type Frame = FrameBuffer<WIDTH, HEIGHT>;
+    type OutputError = E;
+
+    fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        Hub75Single::output(self, delay)
+    }
+
+    fn wipe(&mut self) {
+        Hub75Single::wipe(self)
+    }
+
+    fn frame(&self) -> &Self::Frame {
+        Hub75Single::frame(self)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Display for Hub75Planes<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    type Frame = BitPlanes<WIDTH, SCAN>;
+    type OutputError = E;
+
+    fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        Hub75Planes::output(self, delay)
+    }
+
+    fn wipe(&mut self) {
+        Hub75Planes::wipe(self)
+    }
+
+    fn frame(&self) -> &Self::Frame {
+        Hub75Planes::frame(self)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{test_utils::*, Hub75_32_32_2};
+    use embedded_graphics::{
+        pixelcolor::RgbColor,
+        primitives::{Primitive, PrimitiveStyle},
+        Drawable,
+    };
+
+    /// Fill the display, output one frame, and wipe it.
+    fn flash<D: Hub75Display>(display: &mut D, delay: &mut impl DelayProvider) {
+        let area = display.bounding_box();
+
+        area.into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+            .draw(display)
+            .unwrap();
+        display.output(delay).ok();
+        display.wipe();
+    }
+
+    #[test]
+    fn generic_over_size() {
+        let log = Log::default();
+        let mut small = Hub75_32_32_2::<1, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        flash(&mut small, &mut ProbeDelay(&log));
+        assert_eq!(shifted_colors(&log).len(), 32 * 16);
+        assert_eq!(Hub75Display::frame(&small), &FrameBuffer::new());
+
+        let log = Log::default();
+        let mut large = display::<1>(&log);
+
+        flash(&mut large, &mut ProbeDelay(&log));
+        assert_eq!(shifted_colors(&log).len(), 64 * 16);
+    }
+}
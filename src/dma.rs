@@ -0,0 +1,333 @@
+//! Output of rendered rows through DMA (or any other background transfer).
+//!
+//! A [`DmaBackend`] transfers rows rendered as bitstreams (see [`stream`]), which
+//! shift and latch the columns, while the display selects rows and shows them
+//! with its row and data pins as usual.
+
+use crate::{
+    field_rows, pins::*, pipeline::DisplayPipeline, sink, stream, DelayProvider, Error,
+    FrameStorage, Hub75,
+};
+
+/// Represents a transfer of rendered rows to the color, clock, and latch pins of
+/// a display, e.g. by DMA to a parallel GPIO port, from a buffer of `LEN`
+/// samples.
+pub trait DmaBackend<const LEN: usize> {
+    type Error;
+
+    /// The buffer the next transfer is rendered into.
+    ///
+    /// This is only called once the previous transfer is complete, so a single
+    /// buffer suffices.
+    fn buffer(&mut self) -> &mut [u8; LEN];
+
+    /// Start transferring the first `len` samples of the buffer.
+    fn start(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    /// Whether the last started transfer is complete.
+    fn is_complete(&mut self) -> bool;
+
+    /// Block until the last started transfer is complete.
+    fn wait(&mut self) {
+        while !self.is_complete() {}
+    }
+}
+
+/// A backend replaying the samples with color and data pins, completing every
+/// transfer before returning from `start`.
+///
+/// This is the bit-banged equivalent of a DMA transfer, e.g. to validate the
+/// integration of a backend. `LEN` must be at least [`stream::row_len`] of the
+/// width of the display, which [`Hub75::output_dma`] asserts at compile time.
+pub struct PinBackend<ColorPins, DataPins, Delay, const LEN: usize> {
+    color_pins: ColorPins,
+    data_pins: DataPins,
+    delay: Delay,
+    buffer: [u8; LEN],
+}
+
+impl<ColorPins, DataPins, Delay, const LEN: usize> PinBackend<ColorPins, DataPins, Delay, LEN> {
+    pub fn new(color_pins: ColorPins, data_pins: DataPins, delay: Delay) -> Self {
+        Self {
+            color_pins,
+            data_pins,
+            delay,
+            buffer: [0; LEN],
+        }
+    }
+
+    /// Release the underlying pins and delay.
+    pub fn release(self) -> (ColorPins, DataPins, Delay) {
+        (self.color_pins, self.data_pins, self.delay)
+    }
+}
+
+impl<E, ColorPins, DataPins, Delay, const LEN: usize> DmaBackend<LEN>
+    for PinBackend<ColorPins, DataPins, Delay, LEN>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Delay: DelayProvider,
+{
+    type Error = E;
+
+    fn buffer(&mut self) -> &mut [u8; LEN] {
+        &mut self.buffer
+    }
+
+    fn start(&mut self, len: usize) -> Result<(), E> {
+        for sample in &self.buffer[..len] {
+            if sample & stream::CLK != 0 {
+                // the colors were presented by the preceding sample
+                self.data_pins.shift(&mut self.delay)?;
+            } else if sample & stream::LAT != 0 {
+                self.data_pins.latch(&mut self.delay)?;
+            } else {
+                let (upper, lower) = sink::unpack(*sample);
+
+                self.color_pins.set_colors::<1>(&upper, &lower, &0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_complete(&mut self) -> bool {
+        true
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Output the framebuffer to the display, transferring the rows through the
+    /// provided backend instead of shifting them with the color and data pins.
+    ///
+    /// The row pins and the output enable of the data pins are still used (see
+    /// [`OutputEnablePin`] for data pins driving only the latter). The transfer of
+    /// each (row, bit plane) pair is started while the previous one is shown, and
+    /// only latched once that show is over.
+    ///
+    /// The buffer of the backend must hold at least [`stream::row_len`] of `WIDTH`
+    /// samples, which is asserted at compile time.
+    ///
+    /// Like [`output`](Self::output), this does nothing while the display is
+    /// [blanked](Self::blank), only outputs every other row while
+    /// [interlaced](Self::set_interlaced), and idles for the rest of the
+    /// [minimum frame interval](Self::set_min_frame_interval_us). S-PWM driver
+    /// chips are not supported, and fail with
+    /// [`ConfigError::UnsupportedChipMode`](crate::ConfigError::UnsupportedChipMode).
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output_dma<Backend, Delay, const LEN: usize>(
+        &mut self,
+        backend: &mut Backend,
+        delay: &mut Delay,
+    ) -> Result<(), Error<E>>
+    where
+        Backend: DmaBackend<LEN, Error = E>,
+        Delay: DelayProvider,
+    {
+        const {
+            assert!(
+                LEN >= stream::row_len(WIDTH),
+                "the DMA buffer is too small for a row"
+            )
+        };
+
+        self.check_pwm_mode()?;
+
+        self.transfer_frame(backend, delay).map_err(Error::Pins)
+    }
+
+    /// Output the framebuffer through `backend`, see [`output_dma`](Self::output_dma).
+    fn transfer_frame<Backend, Delay, const LEN: usize>(
+        &mut self,
+        backend: &mut Backend,
+        delay: &mut Delay,
+    ) -> Result<(), E>
+    where
+        Backend: DmaBackend<LEN, Error = E>,
+        Delay: DelayProvider,
+    {
+        if self.blanked {
+            return Ok(());
+        }
+
+        let field = self.field();
+
+        self.begin_field(field);
+
+        let masks = self.ftc.masks();
+        let mut pairs = field_rows(self.scan_order, field)
+            .flat_map(|row| masks.clone().map(move |mask| (row, mask)));
+        let mut selected_row = None;
+        let mut next = pairs.next();
+
+        if let Some((row, mask)) = next {
+            self.start_shift(backend, row, mask)?;
+        }
+
+        while let Some((row, mask)) = next {
+            backend.wait();
+
+            if self.latch_guard_us > 0 {
+                delay.delay_us(self.latch_guard_us);
+            }
+
+            if selected_row != Some(row) {
+                self.data_pins.wait_shown();
+                self.row_pins.set_row(&self.row_map[row as usize])?;
+                selected_row = Some(row);
+            }
+
+            backend.buffer()[..2].copy_from_slice(&stream::LATCH);
+            backend.start(2)?;
+            backend.wait();
+
+            next = pairs.next();
+
+            // shift the next pair while this one is shown
+            if let Some((row, mask)) = next {
+                self.start_shift(backend, row, mask)?;
+            }
+
+            self.data_pins.show(delay, self.show_duration(row, &mask))?;
+        }
+
+        let idle = self.idle_us(field);
+
+        self.complete_field(field);
+
+        if idle > 0 {
+            delay.delay_us(idle);
+        }
+
+        Ok(())
+    }
+
+    /// Render the shift of the provided bit plane of a row pair into the buffer of
+    /// `backend`, and start transferring it.
+    fn start_shift<Backend: DmaBackend<LEN, Error = E>, const LEN: usize>(
+        &mut self,
+        backend: &mut Backend,
+        row: u8,
+        mask: u8,
+    ) -> Result<(), E> {
+        let packed = self.serialize_row(row, mask);
+
+        stream::render_shift(&packed, backend.buffer());
+        backend.start(2 * WIDTH)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{hal::PinState, test_utils::*, Hub75_64_32_2};
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    #[test]
+    fn pin_backend_matches_output() {
+        let reference = Log::default();
+        let log = Log::default();
+        let mut expected = display::<2>(&reference);
+        let mut display = Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            OutputEnablePin(data_pins(&log).2),
+            0.5,
+        );
+        let mut backend = PinBackend::<_, _, _, { stream::row_len(64) }>::new(
+            color_pins(&log),
+            data_pins(&log),
+            ProbeDelay(&log),
+        );
+
+        for (point, color) in [((3, 9), Rgb565::GREEN), ((63, 31), Rgb565::WHITE)] {
+            Pixel(Point::from(point), color)
+                .draw(&mut expected)
+                .unwrap();
+            Pixel(Point::from(point), color).draw(&mut display).unwrap();
+        }
+
+        expected.output(&mut ProbeDelay(&reference)).unwrap();
+        display
+            .output_dma(&mut backend, &mut ProbeDelay(&log))
+            .unwrap();
+
+        assert_eq!(shifted_colors(&log), shifted_colors(&reference));
+        assert_eq!(latched_rows(&log), latched_rows(&reference));
+    }
+
+    #[test]
+    fn shift_while_showing() {
+        let log = Log::default();
+        let mut display = Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            OutputEnablePin(data_pins(&log).2),
+            0.5,
+        );
+        let mut backend = PinBackend::<_, _, _, { stream::row_len(64) }>::new(
+            color_pins(&log),
+            data_pins(&log),
+            ProbeDelay(&log),
+        );
+
+        display
+            .output_dma(&mut backend, &mut ProbeDelay(&log))
+            .unwrap();
+
+        // the clock edges between each latch and the following show
+        let mut shifted = Vec::new();
+        let mut latched = false;
+
+        for event in log.borrow().iter() {
+            match event {
+                Event::Pin(PinId::Lat, PinState::High) => {
+                    shifted.push(0);
+                    latched = true;
+                }
+                Event::Pin(PinId::Oe, PinState::Low) => latched = false,
+                Event::Pin(PinId::Clk, PinState::High) if latched => {
+                    if let Some(count) = shifted.last_mut() {
+                        *count += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // the next pair is shifted before the latched one is shown, except after
+        // the last pair
+        assert_eq!(shifted.len(), 16 * 2);
+        assert!(shifted[..shifted.len() - 1]
+            .iter()
+            .all(|count| *count == 64));
+        assert_eq!(shifted.last(), Some(&0));
+    }
+}
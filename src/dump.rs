@@ -0,0 +1,289 @@
+//! Capture of the framebuffer as a PPM image, for debugging.
+//!
+//! The image is written incrementally into a [`ByteSink`], so it can be streamed
+//! over RTT or a UART without allocating.
+
+use core::fmt::Write;
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameStorage, Hub75};
+
+/// An error while dumping the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DumpError {
+    /// The sink has no room for more data.
+    Full,
+    /// The sink failed, or cannot represent the data.
+    Sink,
+}
+
+/// Represents a destination for the bytes of a dump.
+pub trait ByteSink {
+    /// Write all of the provided bytes.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), DumpError>;
+}
+
+/// A sink writing into a user-provided buffer.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// The bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl ByteSink for SliceWriter<'_> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), DumpError> {
+        let end = self.len + bytes.len();
+
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(DumpError::Full)?
+            .copy_from_slice(bytes);
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+/// A sink writing into a [`core::fmt::Write`] implementor.
+///
+/// *Only text ([`PpmFormat::Ascii`]) dumps can be written.*
+pub struct FmtWriter<W: Write>(pub W);
+
+impl<W: Write> ByteSink for FmtWriter<W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), DumpError> {
+        let text = core::str::from_utf8(bytes).map_err(|_| DumpError::Sink)?;
+
+        self.0.write_str(text).map_err(|_| DumpError::Sink)
+    }
+}
+
+/// The PPM variant to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PpmFormat {
+    /// Plain (P3), with decimal values.
+    Ascii,
+    /// Raw (P6), with binary values.
+    Binary,
+}
+
+/// Which values to dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DumpValues {
+    /// The stored (gamma corrected) values, as shifted out.
+    Stored,
+    /// The linear values, as drawn (approximately, since gamma correction is lossy).
+    Linear,
+}
+
+/// Write a value in decimal followed by a separator.
+fn write_decimal<S: ByteSink>(sink: &mut S, value: u16, separator: u8) -> Result<(), DumpError> {
+    let mut digits = [0; 6];
+    let mut start = digits.len() - 1;
+
+    digits[start] = separator;
+
+    let mut value = value;
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    sink.write_bytes(&digits[start..])
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Write the framebuffer as a PPM image.
+    pub fn write_ppm<S: ByteSink>(
+        &self,
+        sink: &mut S,
+        format: PpmFormat,
+        values: DumpValues,
+    ) -> Result<(), DumpError> {
+        sink.write_bytes(match format {
+            PpmFormat::Ascii => b"P3\n",
+            PpmFormat::Binary => b"P6\n",
+        })?;
+        write_decimal(sink, WIDTH as u16, b' ')?;
+        write_decimal(sink, HEIGHT as u16, b'\n')?;
+        write_decimal(sink, 255, b'\n')?;
+
+        for row in &self.frame.data {
+            for pixel in row {
+                let channels = match values {
+                    DumpValues::Stored => [pixel.0, pixel.1, pixel.2],
+                    DumpValues::Linear => {
+                        let (r, g, b) = self.pipeline.linearize(pixel);
+
+                        [r, g, b]
+                    }
+                };
+
+                match format {
+                    PpmFormat::Ascii => {
+                        for channel in channels {
+                            write_decimal(sink, channel as u16, b' ')?;
+                        }
+                    }
+                    PpmFormat::Binary => sink.write_bytes(&channels)?,
+                }
+            }
+
+            if format == PpmFormat::Ascii {
+                sink.write_bytes(b"\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{test_utils::*, GAMMA8};
+
+    /// Parse a PPM image into its width, height, and pixels.
+    fn parse(data: &[u8]) -> (usize, usize, Vec<(u8, u8, u8)>) {
+        let mut rest = data;
+        let mut token = || {
+            let start = rest.iter().position(|b| !b.is_ascii_whitespace()).unwrap();
+            let len = rest[start..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            let token = &rest[start..start + len];
+
+            rest = &rest[start + len..];
+
+            core::str::from_utf8(token).unwrap()
+        };
+
+        let magic = token();
+        let width: usize = token().parse().unwrap();
+        let height: usize = token().parse().unwrap();
+        assert_eq!(token(), "255");
+
+        let channels: Vec<u8> = if magic == "P3" {
+            (0..width * height * 3)
+                .map(|_| token().parse().unwrap())
+                .collect()
+        } else {
+            assert_eq!(magic, "P6");
+            rest[1..].to_vec()
+        };
+
+        let pixels = channels.chunks(3).map(|c| (c[0], c[1], c[2])).collect();
+
+        (width, height, pixels)
+    }
+
+    fn pattern<const BITS: u8>(display: &mut ProbeDisplay<'_, BITS>) {
+        for y in 0..32 {
+            for x in 0..64 {
+                display
+                    .set_pixel_raw(x, y, (x as u8 * 4, y as u8 * 8, (x ^ y) as u8))
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        pattern(&mut display);
+
+        for format in [PpmFormat::Ascii, PpmFormat::Binary] {
+            let mut buf = [0; 64 * 32 * 12 + 64];
+            let mut writer = SliceWriter::new(&mut buf);
+
+            display
+                .write_ppm(&mut writer, format, DumpValues::Stored)
+                .unwrap();
+
+            let (width, height, pixels) = parse(writer.written());
+
+            assert_eq!((width, height), (64, 32));
+            assert_eq!(pixels.len(), 64 * 32);
+
+            for (i, pixel) in pixels.iter().enumerate() {
+                let (x, y) = (i % 64, i / 64);
+
+                assert_eq!(*pixel, (x as u8 * 4, y as u8 * 8, (x ^ y) as u8));
+            }
+        }
+    }
+
+    #[test]
+    fn linear_values() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        display.set_pixel_raw(0, 0, (GAMMA8[200], 255, 0)).unwrap();
+
+        let mut buf = [0; 64 * 32 * 3 + 64];
+        let mut writer = SliceWriter::new(&mut buf);
+
+        display
+            .write_ppm(&mut writer, PpmFormat::Binary, DumpValues::Linear)
+            .unwrap();
+
+        let (_, _, pixels) = parse(writer.written());
+        let restored = pixels[0];
+
+        assert_eq!(GAMMA8[restored.0 as usize], GAMMA8[200]);
+        assert_eq!(restored.1, 255);
+        assert_eq!(restored.2, 0);
+    }
+
+    #[test]
+    fn full_buffer() {
+        let log = Log::default();
+        let display = display::<8>(&log);
+
+        let mut buf = [0; 100];
+
+        assert_eq!(
+            display.write_ppm(
+                &mut SliceWriter::new(&mut buf),
+                PpmFormat::Binary,
+                DumpValues::Stored
+            ),
+            Err(DumpError::Full)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! A refresh loop for Embassy (or any other cooperative async executor).
+//!
+//! The display is shared between the refresh task and the drawing side in a
+//! [`RefCell`], which is sound since tasks of one executor never run concurrently.
+//! The refresh task shows one (row, bit plane) pair at a time, like an
+//! [`IsrStepper`], and awaits its show duration with the display lit and not
+//! borrowed, so other tasks (including drawing) run for most of the refresh time:
+//!
+//! ```ignore
+//! static DISPLAY: StaticCell<RefCell<Display>> = StaticCell::new();
+//!
+//! #[embassy_executor::task]
+//! async fn refresh(display: &'static RefCell<Display>, mut delay: Delay) {
+//!     let error = refresh_task(display, &mut delay, &mut FrameDelay).await;
+//!     // ...
+//! }
+//!
+//! // drawing side
+//! Text::new("hi", Point::new(1, 8), style).draw(&mut *display.borrow_mut())?;
+//! ```
+//!
+//! The show durations are awaited with an [`AsyncDelay`], which
+//! `embassy_time::Timer` implements in one line:
+//!
+//! ```ignore
+//! struct FrameDelay;
+//!
+//! impl hub75_remastered::async_output::AsyncDelay for FrameDelay {
+//!     async fn delay_us(&mut self, us: u32) {
+//!         embassy_time::Timer::after_micros(us as u64).await
+//!     }
+//! }
+//! ```
+//!
+//! The frame rate is configured on the display with
+//! [`set_min_frame_interval_us`](Hub75::set_min_frame_interval_us), whose idle
+//! time is awaited as well.
+
+use core::cell::RefCell;
+
+use crate::{
+    async_output::AsyncDelay, pins::*, pipeline::DisplayPipeline, scan::IsrStepper, DelayProvider,
+    Error, FrameStorage, Hub75,
+};
+
+/// Refresh `display` forever, awaiting `async_delay` while each (row, bit plane)
+/// pair is shown.
+///
+/// The display is only borrowed to latch a pair and shift the next one (timed by
+/// `delay`), never across an await point. Once a frame is complete, the rest of
+/// the [minimum frame interval](Hub75::set_min_frame_interval_us) is awaited with
+/// the display dark. Returns only if the output fails, e.g. with
+/// [`ConfigError::UnsupportedChipMode`](crate::ConfigError::UnsupportedChipMode)
+/// for S-PWM driver chips.
+///
+/// *The display must not be borrowed across an await point of the drawing side.*
+pub async fn refresh_task<
+    E,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame,
+    Pipeline,
+    Delay,
+    Async,
+>(
+    display: &RefCell<
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    >,
+    delay: &mut Delay,
+    async_delay: &mut Async,
+) -> Error<E>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+    Delay: DelayProvider,
+    Async: AsyncDelay,
+{
+    let mut stepper = IsrStepper::new();
+
+    loop {
+        let tick = match stepper.tick(&mut display.borrow_mut(), delay) {
+            Ok(tick) => tick,
+            Err(error) => return error,
+        };
+
+        async_delay.delay_us(tick.show_us).await;
+
+        if !tick.frame_complete {
+            continue;
+        }
+
+        let idle = {
+            let mut display = display.borrow_mut();
+            let idle = display.idle_us(display.field());
+
+            if idle > 0 {
+                if let Err(error) = display.data_pins.disable_output() {
+                    return Error::Pins(error);
+                }
+            }
+
+            idle
+        };
+
+        if idle > 0 {
+            async_delay.delay_us(idle).await;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use core::{
+        cell::Cell,
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::{hal::mock::DelayUs, test_utils::*};
+
+    /// A delay recording into the log, whose completions are released by the test.
+    struct ManualDelay<'a>(&'a Log, &'a Cell<u32>);
+
+    impl AsyncDelay for ManualDelay<'_> {
+        async fn delay_us(&mut self, us: u32) {
+            core::future::poll_fn(|_| {
+                if self.1.get() > 0 {
+                    self.1.set(self.1.get() - 1);
+
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            ProbeDelay(self.0).delay_us(us);
+        }
+    }
+
+    #[test]
+    fn yields_while_shown() {
+        let log = Log::default();
+        let shown = Log::default();
+        let display = RefCell::new(display::<1>(&log));
+        let released = Cell::new(0);
+        let mut delay = ProbeDelay(&log);
+        let mut async_delay = ManualDelay(&shown, &released);
+        let mut task = pin!(refresh_task(&display, &mut delay, &mut async_delay));
+        let mut context = Context::from_waker(Waker::noop());
+
+        assert!(task.as_mut().poll(&mut context).is_pending());
+        // the first pair is shown, and the display is free while it is
+        assert_eq!(latched_rows(&log).len(), 1);
+        assert_eq!(
+            log.borrow()
+                .iter()
+                .rfind(|event| matches!(event, Event::Pin(PinId::Oe, _))),
+            Some(&Event::Pin(PinId::Oe, crate::hal::PinState::Low))
+        );
+        assert!(display.try_borrow_mut().is_ok());
+
+        Pixel(Point::new(0, 0), Rgb565::RED)
+            .draw(&mut *display.borrow_mut())
+            .unwrap();
+        released.set(16);
+
+        assert!(task.as_mut().poll(&mut context).is_pending());
+        assert_eq!(latched_rows(&log).len(), 17);
+        assert_eq!(shown.borrow().len(), 16);
+        // the next frame shows the drawn pixel
+        assert_eq!(shifted_colors(&log)[64 * 16][0], crate::hal::PinState::High);
+    }
+}
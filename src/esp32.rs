@@ -0,0 +1,271 @@
+//! A reference [`DmaBackend`] for the ESP32's I2S parallel mode (or the LCD_CAM
+//! peripheral of the ESP32-S3).
+//!
+//! The peripheral streams rendered rows (see [`stream`](crate::stream)) in 8 bit
+//! parallel mode to the consecutive R1, G1, B1, R2, G2, B2, CLK, and LAT pins, one
+//! sample per byte, clocked at the desired shift rate. Row selection and output
+//! enable timing are left to the row and data pins of the display.
+//!
+//! The peripheral setup (pin mapping, clock, DMA descriptors) is application
+//! specific, so this module only provides the buffer handling; the peripheral is
+//! abstracted by [`ParallelBus`].
+//!
+//! Alternatively, on parts with dedicated GPIO (e.g. ESP32-C3 and ESP32-S3),
+//! [`BundleColors`] and [`BundleData`] drive the pins through a dedicated GPIO
+//! bundle, which sets several pins per CPU cycle.
+
+use core::convert::Infallible;
+
+use crate::{
+    dma::DmaBackend,
+    hal::{OutputPin, PinState},
+    pins::{IsColorBus, IsDataPins},
+    stream::{CLK, LAT},
+    DelayProvider,
+};
+
+/// An 8 bit parallel output peripheral with DMA.
+///
+/// For `esp-hal`, implement this by starting a DMA transfer of the I2S (or
+/// LCD_CAM) peripheral from the provided samples.
+pub trait ParallelBus {
+    /// Start streaming the samples.
+    ///
+    /// The samples are owned by the backend, which keeps them in place and
+    /// unmodified until [`is_idle`](Self::is_idle) returns `true`.
+    fn start(&mut self, samples: &[u8]);
+
+    /// Whether the last started stream is complete.
+    fn is_idle(&mut self) -> bool;
+}
+
+/// A DMA backend streaming rendered rows through an 8 bit parallel peripheral.
+///
+/// The I2S peripheral of the classic ESP32 outputs the samples of each 16 bit half
+/// of a FIFO word in swapped order, which `swap_pairs` compensates for. The
+/// LCD_CAM peripheral of the ESP32-S3 does not need it.
+///
+/// `LEN` must be at least [`stream::row_len`](crate::stream::row_len) of the width
+/// of the display, plus one if `swap_pairs` is set.
+pub struct I2sDmaBackend<Bus, const LEN: usize> {
+    bus: Bus,
+    swap_pairs: bool,
+    buffer: [u8; LEN],
+}
+
+impl<Bus: ParallelBus, const LEN: usize> I2sDmaBackend<Bus, LEN> {
+    pub fn new(bus: Bus, swap_pairs: bool) -> Self {
+        Self {
+            bus,
+            swap_pairs,
+            buffer: [0; LEN],
+        }
+    }
+
+    /// Release the underlying peripheral.
+    pub fn release(self) -> Bus {
+        self.bus
+    }
+}
+
+impl<Bus: ParallelBus, const LEN: usize> DmaBackend<LEN> for I2sDmaBackend<Bus, LEN> {
+    type Error = Infallible;
+
+    fn buffer(&mut self) -> &mut [u8; LEN] {
+        &mut self.buffer
+    }
+
+    fn start(&mut self, len: usize) -> Result<(), Self::Error> {
+        let len = if self.swap_pairs {
+            // pad to whole pairs with a sample holding the clock and latch low
+            let padded = len.next_multiple_of(2);
+
+            self.buffer[len..padded].fill(0);
+
+            for pair in self.buffer[..padded].chunks_exact_mut(2) {
+                pair.swap(0, 1);
+            }
+
+            padded
+        } else {
+            len
+        };
+
+        self.bus.start(&self.buffer[..len]);
+
+        Ok(())
+    }
+
+    fn is_complete(&mut self) -> bool {
+        self.bus.is_idle()
+    }
+}
+
+/// A bundle of 8 dedicated GPIO output channels, holding R1, G1, B1, R2, G2, B2,
+/// CLK, and LAT from the first channel up (the bits of a rendered
+/// [`stream`](crate::stream) sample).
+///
+/// For `esp-hal`, implement this for the output bundle by forwarding to its masked
+/// write. Since dedicated GPIO are per core registers, the implementor is usually
+/// a cheap handle, shared by [`BundleColors`] and [`BundleData`].
+pub trait DedicatedGpio {
+    /// Drive the channels set in `mask` to the corresponding bits of `value`.
+    fn write(&mut self, mask: u8, value: u8);
+}
+
+/// The color channels of a dedicated GPIO bundle, written at once.
+///
+/// Use as [`ColorBus`](crate::pins::ColorBus)`(BundleColors(bundle))`.
+pub struct BundleColors<Bundle>(pub Bundle);
+
+impl<Bundle: DedicatedGpio> IsColorBus for BundleColors<Bundle> {
+    type Error = Infallible;
+
+    fn write(&mut self, packed: u8) -> Result<(), Self::Error> {
+        self.0.write(!(CLK | LAT), packed);
+
+        Ok(())
+    }
+}
+
+/// The clock and latch channels of a dedicated GPIO bundle, and a regular output
+/// enable pin.
+///
+/// Like [`Undelayed`](crate::pins::Undelayed) data pins, the pulses are not delayed,
+/// as a bundle write takes a single CPU cycle. *At high CPU clocks, the pulses
+/// may become too short for the panel.*
+pub struct BundleData<Bundle, Output> {
+    bundle: Bundle,
+    output: Output,
+}
+
+impl<Bundle, Output> BundleData<Bundle, Output> {
+    pub fn new(bundle: Bundle, output: Output) -> Self {
+        Self { bundle, output }
+    }
+
+    /// Release the underlying bundle and pin.
+    pub fn release(self) -> (Bundle, Output) {
+        (self.bundle, self.output)
+    }
+}
+
+impl<Bundle: DedicatedGpio, Output: OutputPin> IsDataPins for BundleData<Bundle, Output> {
+    type Error = Output::Error;
+
+    fn shift<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        self.bundle.write(CLK, CLK);
+        self.bundle.write(CLK, 0);
+
+        Ok(())
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::Error> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        _delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), Self::Error> {
+        let level = if active == PinState::High { LAT } else { 0 };
+
+        self.bundle.write(LAT, level);
+        self.bundle.write(LAT, LAT ^ level);
+
+        Ok(())
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), Self::Error> {
+        self.bundle
+            .write(LAT, if state == PinState::High { LAT } else { 0 });
+
+        Ok(())
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        self.output.set_low()
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        self.output.set_high()
+    }
+
+    fn show<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        crate::pins::show_held(self, delay, duration)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::sink;
+
+    struct Recorder(Vec<u8>);
+
+    impl ParallelBus for Recorder {
+        fn start(&mut self, samples: &[u8]) {
+            self.0.extend_from_slice(samples);
+        }
+
+        fn is_idle(&mut self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn bundle_writes() {
+        struct Bundle<'a>(&'a core::cell::RefCell<Vec<(u8, u8)>>);
+
+        impl DedicatedGpio for Bundle<'_> {
+            fn write(&mut self, mask: u8, value: u8) {
+                self.0.borrow_mut().push((mask, value));
+            }
+        }
+
+        let writes = core::cell::RefCell::new(Vec::new());
+        let mut colors = BundleColors(Bundle(&writes));
+        let mut data = BundleData::new(Bundle(&writes), crate::mock::Pin(|_| {}));
+
+        colors.write(sink::R1 | sink::B2).unwrap();
+        data.shift(&mut crate::mock::Delay(|_| {})).unwrap();
+        data.set_latch(PinState::High).unwrap();
+
+        assert_eq!(
+            *writes.borrow(),
+            [
+                (0x3f, sink::R1 | sink::B2),
+                (CLK, CLK),
+                (CLK, 0),
+                (LAT, LAT)
+            ]
+        );
+    }
+
+    #[test]
+    fn swapped_pairs() {
+        let mut backend = I2sDmaBackend::<_, 4>::new(Recorder(Vec::new()), true);
+
+        backend.buffer()[..3].copy_from_slice(&[1, 1 | CLK, LAT]);
+        backend.start(3).unwrap();
+
+        assert_eq!(backend.release().0, [1 | CLK, 1, 0, LAT]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Brightness transitions, advanced by the output at every frame boundary.
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameStorage, Hub75};
+
+/// A running transition of the global brightness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct Fade {
+    from: u8,
+    to: u8,
+    frames: u16,
+    elapsed: u16,
+}
+
+impl Fade {
+    /// Advance by a frame, returning the brightness to show and whether the fade
+    /// is complete.
+    fn step(&mut self) -> (u8, bool) {
+        self.elapsed += 1;
+
+        let (from, to) = (self.from as i32, self.to as i32);
+        let brightness = from + (to - from) * self.elapsed as i32 / self.frames as i32;
+
+        (brightness as u8, self.elapsed == self.frames)
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Ramp the [global brightness](Self::set_brightness) linearly to `brightness`
+    /// over the next `frames` frames, so power-on, power-off and scene changes
+    /// don't pop.
+    ///
+    /// The brightness is stepped at the end of every frame, by any output method,
+    /// so no further calls are needed. A fade over zero frames (or a call to
+    /// [`set_brightness`](Self::set_brightness)) takes effect immediately.
+    pub fn fade_to(&mut self, brightness: u8, frames: u16) {
+        if frames == 0 {
+            self.set_brightness(brightness);
+
+            return;
+        }
+
+        self.fade = Some(Fade {
+            from: self.brightness(),
+            to: brightness,
+            frames,
+            elapsed: 0,
+        });
+    }
+
+    /// Fade to full brightness, see [`fade_to`](Self::fade_to).
+    pub fn fade_in(&mut self, frames: u16) {
+        self.fade_to(255, frames);
+    }
+
+    /// Fade to black, see [`fade_to`](Self::fade_to).
+    pub fn fade_out(&mut self, frames: u16) {
+        self.fade_to(0, frames);
+    }
+
+    /// Whether a fade is still in progress.
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Step the fade in progress (if any) at the end of a frame.
+    pub(crate) fn step_fade(&mut self) {
+        if let Some(fade) = &mut self.fade {
+            let (brightness, done) = fade.step();
+
+            self.ftc.brightness = brightness;
+
+            if done {
+                self.fade = None;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use crate::test_utils::*;
+
+    #[test]
+    fn fade_over_frames() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+
+        display.fade_out(4);
+
+        for brightness in [192, 128, 64, 0] {
+            assert!(display.is_fading());
+
+            display.output(&mut ProbeDelay(&log)).unwrap();
+
+            assert_eq!(display.brightness(), brightness);
+        }
+
+        assert!(!display.is_fading());
+
+        display.fade_in(2);
+        display.output_step(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(display.brightness(), 0);
+
+        display.set_brightness(200);
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(display.brightness(), 200);
+        assert!(!display.is_fading());
+
+        display.fade_to(100, 0);
+
+        assert_eq!(display.brightness(), 100);
+    }
+}
@@ -0,0 +1,280 @@
+//! The framebuffer holding the (gamma corrected) pixel values of a display.
+
+use core::{
+    borrow::BorrowMut,
+    convert::Infallible,
+    fmt::{self, Debug},
+    ops::{Deref, DerefMut},
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{
+    pins::*,
+    pipeline::{ColorPipeline, DisplayPipeline, Selected},
+    update, Hub75,
+};
+
+/// The pixel values of a `WIDTH` by `HEIGHT` display, in row-major order.
+///
+/// Stored values are gamma corrected, i.e. proportional to the light output.
+///
+/// Drawn colors are converted by the [pipeline](crate::pipeline) of the display
+/// the framebuffer was last loaded into (e.g. from a
+/// [`FrameQueue`](crate::queue::FrameQueue)), or [`Gamma::POWER`](crate::gamma::Gamma::POWER)
+/// before that. Frames drawn with another pipeline are converted when loaded,
+/// which is slightly lossy, so e.g. the first frames of a queue may band a little
+/// after [`set_gamma`](Hub75::set_gamma).
+#[derive(Clone)]
+pub struct FrameBuffer<const WIDTH: usize, const HEIGHT: usize> {
+    pub(crate) data: [[(u8, u8, u8); WIDTH]; HEIGHT],
+    /// The conversion of drawn colors to stored values.
+    pub(crate) pipeline: Selected,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> FrameBuffer<WIDTH, HEIGHT> {
+    /// Create an all black framebuffer.
+    pub const fn new() -> Self {
+        Self {
+            data: [[(0, 0, 0); WIDTH]; HEIGHT],
+            pipeline: Selected::DEFAULT,
+        }
+    }
+
+    /// Convert the stored values to `pipeline`, which then converts subsequent
+    /// draws.
+    pub(crate) fn convert(&mut self, pipeline: Selected) {
+        if self.pipeline.same(&pipeline) {
+            return;
+        }
+
+        let previous = core::mem::replace(&mut self.pipeline, pipeline);
+
+        for pixel in self.pixels_mut() {
+            *pixel = pipeline.correct(&previous.linearize(pixel));
+        }
+    }
+
+    /// Get the entry of the provided pixel, if it is in bounds.
+    pub(crate) fn pixel(&self, x: usize, y: usize) -> Option<&(u8, u8, u8)> {
+        self.data.get(y)?.get(x)
+    }
+
+    /// Get the entry of the provided pixel, if it is in bounds.
+    pub(crate) fn pixel_mut(&mut self, x: usize, y: usize) -> Option<&mut (u8, u8, u8)> {
+        self.data.get_mut(y)?.get_mut(x)
+    }
+
+    /// All entries, in row-major order.
+    pub(crate) fn pixels(&self) -> impl Iterator<Item = &(u8, u8, u8)> {
+        self.data.iter().flatten()
+    }
+
+    /// All entries, in row-major order.
+    pub(crate) fn pixels_mut(&mut self) -> impl Iterator<Item = &mut (u8, u8, u8)> {
+        self.data.iter_mut().flatten()
+    }
+}
+
+/// The storage of a display's framebuffer: either embedded in it (a
+/// [`FrameBuffer`]), provided by the application (a `&'static mut`
+/// [`FrameBuffer`], see [`Hub75::new_with_buffer`]), or the front buffer of a
+/// [`TripleBuffer`](crate::buffer::TripleBuffer) (see
+/// [`Hub75::new_with_consumer`]).
+pub trait FrameStorage<const WIDTH: usize, const HEIGHT: usize>:
+    BorrowMut<FrameBuffer<WIDTH, HEIGHT>>
+{
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> FrameStorage<WIDTH, HEIGHT>
+    for FrameBuffer<WIDTH, HEIGHT>
+{
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> FrameStorage<WIDTH, HEIGHT>
+    for &'static mut FrameBuffer<WIDTH, HEIGHT>
+{
+}
+
+/// The framebuffer of a display, dereferencing to it however it is stored.
+pub(crate) struct Storage<const WIDTH: usize, const HEIGHT: usize, Frame>(pub(crate) Frame);
+
+impl<const WIDTH: usize, const HEIGHT: usize, Frame: FrameStorage<WIDTH, HEIGHT>> Deref
+    for Storage<WIDTH, HEIGHT, Frame>
+{
+    type Target = FrameBuffer<WIDTH, HEIGHT>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.borrow()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, Frame: FrameStorage<WIDTH, HEIGHT>> DerefMut
+    for Storage<WIDTH, HEIGHT, Frame>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.borrow_mut()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Debug for FrameBuffer<WIDTH, HEIGHT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameBuffer")
+            .field("data", &self.data)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Framebuffers are equal if they store the same values, however they were
+/// converted.
+impl<const WIDTH: usize, const HEIGHT: usize> PartialEq for FrameBuffer<WIDTH, HEIGHT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Eq for FrameBuffer<WIDTH, HEIGHT> {}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for FrameBuffer<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Dimensions for FrameBuffer<WIDTH, HEIGHT> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for FrameBuffer<WIDTH, HEIGHT> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let pipeline = self.pipeline;
+
+        for Pixel(coord, color) in pixels {
+            if coord.x >= 0 && coord.y >= 0 {
+                if let Some(pixel) = self.pixel_mut(coord.x as usize, coord.y as usize) {
+                    *pixel = pipeline.correct_rgb565(color);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Interpolate from one stored value to another, `t` in `0..=255`.
+const fn lerp(from: u8, to: u8, t: u8) -> u8 {
+    let from = from as i32;
+    let to = to as i32;
+
+    (from + (to - from) * t as i32 / 255) as u8
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// The framebuffer of the display.
+    pub fn frame(&self) -> &FrameBuffer<WIDTH, HEIGHT> {
+        &self.frame
+    }
+
+    /// Write one step of a crossfade from one framebuffer to another into the
+    /// display, with `t` progressing from 0 (exactly `from`) to 255 (exactly `to`).
+    ///
+    /// Since stored values are proportional to the light output, interpolating them
+    /// directly crossfades in linear light, so mid-fade frames do not look too
+    /// dark.
+    pub fn crossfade_step(
+        &mut self,
+        from: &FrameBuffer<WIDTH, HEIGHT>,
+        to: &FrameBuffer<WIDTH, HEIGHT>,
+        t: u8,
+    ) {
+        let mut changed = false;
+
+        for (pixel, (from, to)) in self.frame.pixels_mut().zip(from.pixels().zip(to.pixels())) {
+            changed |= update(
+                pixel,
+                (
+                    lerp(from.0, to.0, t),
+                    lerp(from.1, to.1, t),
+                    lerp(from.2, to.2, t),
+                ),
+            );
+        }
+
+        self.mark_changed(changed);
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use embedded_graphics::{pixelcolor::RgbColor, Drawable};
+
+    #[test]
+    fn crossfade_endpoints() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        let mut from = FrameBuffer::<64, 32>::new();
+        let mut to = FrameBuffer::<64, 32>::new();
+
+        Pixel(Point::new(0, 0), Rgb565::WHITE)
+            .draw(&mut from)
+            .unwrap();
+        Pixel(Point::new(1, 20), Rgb565::RED)
+            .draw(&mut from)
+            .unwrap();
+        Pixel(Point::new(1, 20), Rgb565::BLUE)
+            .draw(&mut to)
+            .unwrap();
+        Pixel(Point::new(63, 31), Rgb565::GREEN)
+            .draw(&mut to)
+            .unwrap();
+
+        display.crossfade_step(&from, &to, 0);
+        assert_eq!(display.frame(), &from);
+
+        display.crossfade_step(&from, &to, 255);
+        assert_eq!(display.frame(), &to);
+    }
+
+    #[test]
+    fn crossfade_linear_light() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        let mut from = FrameBuffer::<64, 32>::new();
+
+        Pixel(Point::zero(), Rgb565::WHITE).draw(&mut from).unwrap();
+
+        display.crossfade_step(&from, &FrameBuffer::<64, 32>::new(), 128);
+
+        // half of the light, not half of the perceptual value
+        assert_eq!(display.frame().pixel(0, 0), Some(&(127, 127, 127)));
+    }
+}
@@ -0,0 +1,193 @@
+//! Gamma correction, mapping drawn (linear) colors to stored values proportional
+//! to the light output.
+
+use crate::{pins::*, FrameStorage, Hub75, GAMMA8};
+
+/// The identity table, leaving values as drawn.
+const IDENTITY: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        table[i] = i as u8;
+        i += 1;
+    }
+
+    table
+};
+
+/// The CIE 1931 lightness curve, mapping linear values to perceived lightness.
+///
+/// L* = 100 * value / 255, and the luminance Y = L* / 903.3 below L* = 8 and
+/// ((L* + 16) / 116)^3 above, scaled to 0-255.
+const CIE1931: [u8; 256] = [
+    0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 3, 4,
+    4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 8, 9, 9, 9, 10, 10, 10, 10,
+    11, 11, 11, 12, 12, 12, 13, 13, 13, 14, 14, 15, 15, 15, 16, 16, 17, 17, 17, 18, 18, 19, 19, 20,
+    20, 21, 21, 22, 22, 23, 23, 24, 24, 25, 25, 26, 26, 27, 28, 28, 29, 29, 30, 31, 31, 32, 32, 33,
+    34, 34, 35, 36, 37, 37, 38, 39, 39, 40, 41, 42, 43, 43, 44, 45, 46, 47, 47, 48, 49, 50, 51, 52,
+    53, 54, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 70, 71, 72, 73, 74, 75, 76,
+    77, 79, 80, 81, 82, 83, 85, 86, 87, 88, 90, 91, 92, 94, 95, 96, 98, 99, 100, 102, 103, 105,
+    106, 108, 109, 110, 112, 113, 115, 116, 118, 120, 121, 123, 124, 126, 128, 129, 131, 132, 134,
+    136, 138, 139, 141, 143, 145, 146, 148, 150, 152, 154, 155, 157, 159, 161, 163, 165, 167, 169,
+    171, 173, 175, 177, 179, 181, 183, 185, 187, 189, 191, 193, 196, 198, 200, 202, 204, 207, 209,
+    211, 214, 216, 218, 220, 223, 225, 228, 230, 232, 235, 237, 240, 242, 245, 247, 250, 252, 255,
+];
+
+/// The lookup tables correcting each color channel.
+///
+/// Tables map a linear 8-bit channel value to the stored value, and should be
+/// monotonic (for [`linearize`](Self::linearize)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gamma {
+    r: &'static [u8; 256],
+    g: &'static [u8; 256],
+    b: &'static [u8; 256],
+}
+
+impl Gamma {
+    /// The built-in power law curve (the default).
+    pub const POWER: Self = Self::uniform(&GAMMA8);
+
+    /// The CIE 1931 lightness curve, which is perceptually linear.
+    ///
+    /// Compared to [`POWER`](Self::POWER), it rises sooner and in finer steps in
+    /// the low range, so fades and gradients band less.
+    pub const CIE1931: Self = Self::uniform(&CIE1931);
+
+    /// No correction, e.g. for content which is already corrected by the
+    /// application's own color management.
+    pub const LINEAR: Self = Self::uniform(&IDENTITY);
+
+    /// Correct every channel with the same table.
+    pub const fn uniform(table: &'static [u8; 256]) -> Self {
+        Self::new(table, table, table)
+    }
+
+    /// Correct each channel with its own table.
+    pub const fn new(r: &'static [u8; 256], g: &'static [u8; 256], b: &'static [u8; 256]) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Gamma correct a linear 8-bit color.
+    pub const fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        (
+            self.r[color.0 as usize],
+            self.g[color.1 as usize],
+            self.b[color.2 as usize],
+        )
+    }
+
+    /// Whether both use the very same tables, which is cheaper than comparing their
+    /// contents.
+    pub(crate) fn same_tables(&self, other: &Self) -> bool {
+        core::ptr::eq(self.r, other.r)
+            && core::ptr::eq(self.g, other.g)
+            && core::ptr::eq(self.b, other.b)
+    }
+
+    /// Approximately invert gamma correction, since it is lossy.
+    pub fn linearize(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        fn channel(table: &[u8; 256], value: u8) -> u8 {
+            table.partition_point(|corrected| *corrected < value) as u8
+        }
+
+        (
+            channel(self.r, color.0),
+            channel(self.g, color.1),
+            channel(self.b, color.2),
+        )
+    }
+}
+
+impl Default for Gamma {
+    fn default() -> Self {
+        Self::POWER
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame>
+{
+    /// Set the gamma correction applied to drawn colors.
+    ///
+    /// The framebuffer stores corrected values, so this only affects subsequent
+    /// draws (and [palettes](crate::palette::Indexed::set_palette) set
+    /// afterwards). Standalone [`FrameBuffer`](crate::FrameBuffer)s (e.g. of a
+    /// [`FrameQueue`](crate::queue::FrameQueue)) are converted to it when loaded.
+    pub fn set_gamma(&mut self, gamma: Gamma) {
+        self.pipeline = gamma;
+    }
+
+    /// The gamma correction applied to drawn colors.
+    pub fn gamma(&self) -> Gamma {
+        self.pipeline
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use embedded_graphics::{geometry::Point, pixelcolor::Rgb565, Drawable, Pixel};
+
+    #[test]
+    fn custom_tables() {
+        static INVERTED: [u8; 256] = {
+            let mut table = [0; 256];
+            let mut i = 0;
+
+            while i < 256 {
+                table[i] = 255 - i as u8;
+                i += 1;
+            }
+
+            table
+        };
+
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        assert_eq!(display.gamma(), Gamma::POWER);
+
+        display.set_gamma(Gamma::LINEAR);
+        Pixel(Point::new(0, 0), Rgb565::new(15, 31, 0))
+            .draw(&mut display)
+            .unwrap();
+        display.set_gamma(Gamma::new(&IDENTITY, &INVERTED, &GAMMA8));
+        Pixel(Point::new(1, 0), Rgb565::new(15, 31, 15))
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.frame.pixel(0, 0), Some(&(127, 127, 0)));
+        assert_eq!(display.frame.pixel(1, 0), Some(&(127, 128, GAMMA8[127])));
+        assert_eq!(
+            Gamma::POWER.linearize(&Gamma::POWER.correct(&(0, 128, 255))),
+            (0, 128, 255)
+        );
+    }
+
+    #[test]
+    fn cie1931_low_range() {
+        let levels = |table: &[u8; 256]| {
+            table[..64]
+                .windows(2)
+                .filter(|pair| pair[0] != pair[1])
+                .count()
+        };
+
+        assert!(CIE1931.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!((CIE1931[0], CIE1931[255]), (0, 255));
+        assert!(levels(&CIE1931) > levels(&GAMMA8));
+        assert!(CIE1931.iter().position(|v| *v > 0) < GAMMA8.iter().position(|v| *v > 0));
+    }
+}
@@ -0,0 +1,96 @@
+//! A host backend without `embedded-hal`, for testing.
+//!
+//! Pins and delays are plain closures (or function pointers), so the framebuffer,
+//! color correction, and output logic can be exercised without target hardware.
+
+use core::convert::Infallible;
+
+/// The state of an output pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinState {
+    Low,
+    High,
+}
+
+impl core::ops::Not for PinState {
+    type Output = PinState;
+
+    fn not(self) -> Self::Output {
+        match self {
+            PinState::Low => PinState::High,
+            PinState::High => PinState::Low,
+        }
+    }
+}
+
+/// A single digital output pin.
+pub trait OutputPin {
+    type Error;
+
+    /// Drive the pin low.
+    fn set_low(&mut self) -> Result<(), Self::Error>;
+
+    /// Drive the pin high.
+    fn set_high(&mut self) -> Result<(), Self::Error>;
+
+    /// Drive the pin to the provided state.
+    fn set_state(&mut self, state: PinState) -> Result<(), Self::Error> {
+        match state {
+            PinState::Low => self.set_low(),
+            PinState::High => self.set_high(),
+        }
+    }
+}
+
+/// Microsecond delays.
+pub trait DelayUs {
+    /// Pause for the provided number of microseconds.
+    fn delay_us(&mut self, us: u32);
+}
+
+/// A blocking SPI bus writing bytes.
+pub trait SpiWrite {
+    type Error;
+
+    /// Write the bytes, returning once they are shifted out.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// A PWM channel with an integer duty cycle.
+pub trait PwmChannel {
+    type Error;
+
+    /// The duty cycle corresponding to always high.
+    fn max_duty(&self) -> u16;
+
+    /// Set the duty cycle, from 0 (always low) to [`max_duty`](Self::max_duty).
+    fn set_duty(&mut self, duty: u16) -> Result<(), Self::Error>;
+}
+
+/// An output pin invoking a closure on every state change.
+pub struct Pin<F: FnMut(PinState)>(pub F);
+
+impl<F: FnMut(PinState)> OutputPin for Pin<F> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        (self.0)(PinState::Low);
+
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        (self.0)(PinState::High);
+
+        Ok(())
+    }
+}
+
+/// A delay invoking a closure instead of pausing.
+pub struct Delay<F: FnMut(u32)>(pub F);
+
+impl<F: FnMut(u32)> DelayUs for Delay<F> {
+    fn delay_us(&mut self, us: u32) {
+        (self.0)(us);
+    }
+}
@@ -0,0 +1,235 @@
+//! Selection of exactly one `embedded-hal` backend.
+
+#[cfg(any(
+    all(feature = "hal-02", feature = "hal-1"),
+    all(feature = "hal-02", feature = "hal-mock"),
+    all(feature = "hal-1", feature = "hal-mock"),
+))]
+compile_error!("Only one of the `hal-02`, `hal-1`, and `hal-mock` features may be enabled.");
+
+#[cfg(not(any(feature = "hal-02", feature = "hal-1", feature = "hal-mock")))]
+compile_error!(
+    "One of the `hal-02`, `hal-1`, or `hal-mock` features must be enabled to select the `embedded-hal` version."
+);
+
+// The backends are selected by precedence so a misconfiguration only reports the
+// errors above.
+
+#[cfg(feature = "hal-1")]
+mod backend {
+    pub use embedded_hal_1::{
+        delay::DelayNs,
+        digital::{OutputPin, PinState},
+    };
+
+    /// A provider of the delays needed to drive the display.
+    pub trait DelayProvider: DelayNs {}
+
+    impl<T: DelayNs> DelayProvider for T {}
+
+    /// A delay provider returning immediately.
+    pub struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    /// A PWM channel with an integer duty cycle.
+    pub trait PwmChannel {
+        type Error;
+
+        /// The duty cycle corresponding to always high.
+        fn max_duty(&self) -> u16;
+
+        /// Set the duty cycle, from 0 (always low) to [`max_duty`](Self::max_duty).
+        fn set_duty(&mut self, duty: u16) -> Result<(), Self::Error>;
+    }
+
+    impl<T: embedded_hal_1::pwm::SetDutyCycle> PwmChannel for T {
+        type Error = T::Error;
+
+        fn max_duty(&self) -> u16 {
+            self.max_duty_cycle()
+        }
+
+        fn set_duty(&mut self, duty: u16) -> Result<(), Self::Error> {
+            self.set_duty_cycle(duty)
+        }
+    }
+
+    impl<P: OutputPin> embedded_hal_1::digital::ErrorType for super::CachedPin<P> {
+        type Error = P::Error;
+    }
+
+    impl<P: OutputPin> OutputPin for super::CachedPin<P> {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::Low, |pin| pin.set_low())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::High, |pin| pin.set_high())
+        }
+    }
+
+    /// A blocking SPI bus writing bytes.
+    pub trait SpiWrite {
+        type Error;
+
+        /// Write the bytes, returning once they are shifted out.
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+    }
+
+    impl<T: embedded_hal_1::spi::SpiBus<u8>> SpiWrite for T {
+        type Error = T::Error;
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            embedded_hal_1::spi::SpiBus::write(self, bytes)?;
+            self.flush()
+        }
+    }
+}
+
+#[cfg(all(feature = "hal-02", not(feature = "hal-1")))]
+mod backend {
+    pub use embedded_hal_02::{
+        blocking::delay::DelayUs,
+        digital::v2::{OutputPin, PinState},
+    };
+
+    /// A provider of the delays needed to drive the display.
+    pub trait DelayProvider: DelayUs<u32> {}
+
+    impl<T: DelayUs<u32>> DelayProvider for T {}
+
+    /// A delay provider returning immediately.
+    pub struct NoDelay;
+
+    impl DelayUs<u32> for NoDelay {
+        fn delay_us(&mut self, _us: u32) {}
+    }
+
+    /// A PWM channel with an integer duty cycle.
+    pub trait PwmChannel {
+        type Error;
+
+        /// The duty cycle corresponding to always high.
+        fn max_duty(&self) -> u16;
+
+        /// Set the duty cycle, from 0 (always low) to [`max_duty`](Self::max_duty).
+        fn set_duty(&mut self, duty: u16) -> Result<(), Self::Error>;
+    }
+
+    impl<T: embedded_hal_02::PwmPin<Duty = u16>> PwmChannel for T {
+        type Error = core::convert::Infallible;
+
+        fn max_duty(&self) -> u16 {
+            self.get_max_duty()
+        }
+
+        fn set_duty(&mut self, duty: u16) -> Result<(), Self::Error> {
+            embedded_hal_02::PwmPin::set_duty(self, duty);
+
+            Ok(())
+        }
+    }
+
+    impl<P: OutputPin> OutputPin for super::CachedPin<P> {
+        type Error = P::Error;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::Low, |pin| pin.set_low())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::High, |pin| pin.set_high())
+        }
+    }
+
+    /// A blocking SPI bus writing bytes.
+    pub trait SpiWrite {
+        type Error;
+
+        /// Write the bytes, returning once they are shifted out.
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+    }
+
+    impl<T: embedded_hal_02::blocking::spi::Write<u8>> SpiWrite for T {
+        type Error = T::Error;
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            embedded_hal_02::blocking::spi::Write::write(self, bytes)
+        }
+    }
+}
+
+#[cfg(not(any(feature = "hal-1", feature = "hal-02")))]
+pub mod mock;
+
+#[cfg(not(any(feature = "hal-1", feature = "hal-02")))]
+mod backend {
+    pub use super::mock::{DelayUs, OutputPin, PinState, PwmChannel, SpiWrite};
+
+    /// A provider of the delays needed to drive the display.
+    pub trait DelayProvider: DelayUs {}
+
+    impl<T: DelayUs> DelayProvider for T {}
+
+    /// A delay provider returning immediately.
+    pub struct NoDelay;
+
+    impl DelayUs for NoDelay {
+        fn delay_us(&mut self, _us: u32) {}
+    }
+
+    impl<P: OutputPin> OutputPin for super::CachedPin<P> {
+        type Error = P::Error;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::Low, |pin| pin.set_low())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.write(PinState::High, |pin| pin.set_high())
+        }
+    }
+}
+
+pub use backend::*;
+
+/// An output pin skipping writes of the state it was last driven to.
+///
+/// Adjacent pixels mostly share their color bits, so wrapping the color pins
+/// (e.g. `(CachedPin::new(r1), ...)`) saves most of their GPIO writes on typical
+/// content. This only pays off when a GPIO write is slower than the comparison,
+/// and the pin must not be driven by anything else while wrapped.
+pub struct CachedPin<P> {
+    pin: P,
+    state: Option<PinState>,
+}
+
+impl<P> CachedPin<P> {
+    /// Wrap the pin, whose state is unknown until the first write.
+    pub fn new(pin: P) -> Self {
+        Self { pin, state: None }
+    }
+
+    /// Release the underlying pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+
+    fn write<E>(
+        &mut self,
+        state: PinState,
+        set: impl FnOnce(&mut P) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.state != Some(state) {
+            // forget the state if the write fails, as it may have taken effect
+            self.state = None;
+            set(&mut self.pin)?;
+            self.state = Some(state);
+        }
+
+        Ok(())
+    }
+}
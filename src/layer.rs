@@ -0,0 +1,185 @@
+//! An overlay layer composited over the framebuffer at output time.
+//!
+//! The framebuffer acts as the background. Content which changes on its own
+//! schedule (e.g. the digits of a clock or a score) can be drawn to an overlay
+//! instead, so it can be updated or cleared without redrawing the background.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Size},
+    pixelcolor::{Rgb888, RgbColor},
+    Pixel,
+};
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameStorage, Hub75};
+
+/// A layer of [`Rgb888`] pixels shown on top of the framebuffer, see
+/// [`Hub75::set_overlay`].
+///
+/// Pixels of the transparent color (black by default) show the framebuffer
+/// below them. The layer stores colors as drawn, they are gamma corrected by the
+/// display while shifting data out.
+pub struct Layer<const WIDTH: usize, const HEIGHT: usize> {
+    data: [[(u8, u8, u8); WIDTH]; HEIGHT],
+    transparent: Option<(u8, u8, u8)>,
+    visible: bool,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Layer<WIDTH, HEIGHT> {
+    /// A visible, fully transparent layer.
+    pub const fn new() -> Self {
+        Self {
+            data: [[(0, 0, 0); WIDTH]; HEIGHT],
+            transparent: Some((0, 0, 0)),
+            visible: true,
+        }
+    }
+
+    /// Show or hide the layer, without modifying its content.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Whether the layer is shown.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Set the color which shows the framebuffer below it, or `None` for an
+    /// opaque layer.
+    pub fn set_transparent(&mut self, color: Option<Rgb888>) {
+        self.transparent = color.map(|color| (color.r(), color.g(), color.b()));
+    }
+
+    /// The shown (not yet gamma corrected) color of a pixel, if it covers the
+    /// framebuffer.
+    pub(crate) fn pixel(&self, x: usize, y: usize) -> Option<(u8, u8, u8)> {
+        let color = self.data[y][x];
+
+        (self.visible && self.transparent != Some(color)).then_some(color)
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for Layer<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> OriginDimensions for Layer<WIDTH, HEIGHT> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Layer<WIDTH, HEIGHT> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if let Some(pixel) = usize::try_from(coord.y)
+                .ok()
+                .and_then(|y| self.data.get_mut(y))
+                .zip(usize::try_from(coord.x).ok())
+                .and_then(|(row, x)| row.get_mut(x))
+            {
+                *pixel = (color.r(), color.g(), color.b());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.data = [[(color.r(), color.g(), color.b()); WIDTH]; HEIGHT];
+
+        Ok(())
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Composite a layer over the framebuffer while shifting data out, returning
+    /// the previous one.
+    ///
+    /// The layer is borrowed rather than copied to avoid doubling the memory of
+    /// the display, and is drawn to through [`overlay`](Self::overlay). The
+    /// framebuffer content is not modified, so dumps and the
+    /// [power estimate](Self::estimated_current_ma) only cover the background.
+    /// S-PWM driver chips do not show the overlay.
+    pub fn set_overlay(
+        &mut self,
+        layer: Option<&'static mut Layer<WIDTH, HEIGHT>>,
+    ) -> Option<&'static mut Layer<WIDTH, HEIGHT>> {
+        self.mark_rows_dirty();
+
+        core::mem::replace(&mut self.overlay, layer)
+    }
+
+    /// The overlay, to draw to or to change its visibility.
+    ///
+    /// Every row is marked dirty, since the overlay may be modified.
+    pub fn overlay(&mut self) -> Option<&mut Layer<WIDTH, HEIGHT>> {
+        self.mark_rows_dirty();
+
+        self.overlay.as_deref_mut()
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{
+        sink::{B1, G1, R1},
+        test_utils::*,
+    };
+    use embedded_graphics::{geometry::Point, primitives::Rectangle};
+    use std::boxed::Box;
+
+    #[test]
+    fn overlay_composited() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let layer = Box::leak(Box::new(Layer::new()));
+
+        display.rgb888().clear(Rgb888::BLUE).unwrap();
+        layer
+            .fill_solid(
+                &Rectangle::new(Point::zero(), Size::new(2, 1)),
+                Rgb888::YELLOW,
+            )
+            .unwrap();
+
+        assert!(display.set_overlay(Some(layer)).is_none());
+
+        // the upper half of the row pair
+        let shown =
+            |display: &ProbeDisplay<8>, x: usize| display.serialize_row(0, 7)[x] & (R1 | G1 | B1);
+
+        assert_eq!(shown(&display, 0), R1 | G1);
+        assert_eq!(shown(&display, 1), R1 | G1);
+        assert_eq!(shown(&display, 2), B1);
+
+        display.overlay().unwrap().set_visible(false);
+
+        assert_eq!(shown(&display, 0), B1);
+    }
+}
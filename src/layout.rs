@@ -0,0 +1,542 @@
+//! Mapping of logical display coordinates onto panels of a shift chain.
+//!
+//! A chain of `n` panels is driven as a single display `n` times as wide, e.g.
+//! `Hub75<128, 32, 16, ...>` for two 64x32 panels, with the columns of the panel
+//! farthest from the driver shifted first. A [`Stacking`] is a
+//! [`PanelMapping`](crate::remap::PanelMapping) onto this display.
+
+use embedded_graphics::geometry::{Point, Size};
+
+use crate::remap::{Half, PanelMapping, ShiftLocation};
+
+/// The width of a single panel.
+const PANEL_WIDTH: u32 = 64;
+/// The height of a single panel.
+const PANEL_HEIGHT: u32 = 32;
+
+/// A pixel location on a shift chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChainLocation {
+    /// The position of the panel in the chain, starting with the panel closest to
+    /// the driver.
+    pub panel: u8,
+    /// The panel-local column.
+    pub x: u8,
+    /// The panel-local row.
+    pub y: u8,
+}
+
+/// The physical arrangement of the panels of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Stacking {
+    /// Panels side by side, all upright, with the driver connected to the
+    /// rightmost panel:
+    ///
+    /// ```text
+    /// (0, 0)
+    ///    +----------------+----------------+
+    ///    |    panel 1     |    panel 0     |  <- driver
+    ///    +----------------+----------------+
+    ///                                  (127, 31)
+    /// ```
+    ///
+    /// This is the native layout of a chain, so the mapping is the identity.
+    Horizontal { panels: u8 },
+    /// Panels stacked vertically, with every other panel rotated 180° (standard
+    /// serpentine mounting).
+    ///
+    /// The first panel of the chain is at the top:
+    ///
+    /// ```text
+    /// (0, 0)
+    ///    +----------------+
+    ///    |    panel 0     |  upright
+    ///    +----------------+
+    ///    |    panel 1     |  rotated 180°
+    ///    +----------------+
+    ///    |    panel 2     |  upright
+    ///    +----------------+
+    ///                  (63, 95)
+    /// ```
+    ///
+    /// Panel 1 is rotated 180°, so its local (0, 0) is at logical (63, 63).
+    VerticalSerpentine { panels: u8 },
+    /// Rows of panels wired in a serpentine, with every other row rotated 180°.
+    ///
+    /// The chain starts at the top right, runs right to left along the top row, then
+    /// left to right along the second (rotated) row, and so on:
+    ///
+    /// ```text
+    /// (0, 0)
+    ///    +----------------+----------------+
+    ///    |    panel 1     |    panel 0     |  <- driver, upright
+    ///    +----------------+----------------+
+    ///    |    panel 2     |    panel 3     |  rotated 180°
+    ///    +----------------+----------------+
+    ///                                  (127, 63)
+    /// ```
+    ///
+    /// `Horizontal` and `VerticalSerpentine` are the special cases of a single row
+    /// and a single column.
+    Grid { columns: u8, rows: u8 },
+}
+
+impl Stacking {
+    /// The logical size of the arrangement.
+    pub const fn size(&self) -> Size {
+        match self {
+            Self::Horizontal { panels } => Size::new(PANEL_WIDTH * *panels as u32, PANEL_HEIGHT),
+            Self::VerticalSerpentine { panels } => {
+                Size::new(PANEL_WIDTH, PANEL_HEIGHT * *panels as u32)
+            }
+            Self::Grid { columns, rows } => {
+                Size::new(PANEL_WIDTH * *columns as u32, PANEL_HEIGHT * *rows as u32)
+            }
+        }
+    }
+
+    /// The arrangement as a grid of `(columns, rows)` panels.
+    const fn grid(&self) -> (u8, u8) {
+        match self {
+            Self::Horizontal { panels } => (*panels, 1),
+            Self::VerticalSerpentine { panels } => (1, *panels),
+            Self::Grid { columns, rows } => (*columns, *rows),
+        }
+    }
+
+    /// The number of panels in the chain.
+    pub const fn panels(&self) -> u8 {
+        let (columns, rows) = self.grid();
+
+        columns * rows
+    }
+
+    /// Map a logical point to its location on the chain, if it is in bounds.
+    pub fn locate(&self, point: Point) -> Option<ChainLocation> {
+        let size = self.size();
+
+        if point.x < 0
+            || point.y < 0
+            || point.x as u32 >= size.width
+            || point.y as u32 >= size.height
+        {
+            return None;
+        }
+
+        let (x, y) = (point.x as u32, point.y as u32);
+        let (columns, _) = self.grid();
+        let (column, row) = (x / PANEL_WIDTH, y / PANEL_HEIGHT);
+        let (x, y) = (x % PANEL_WIDTH, y % PANEL_HEIGHT);
+
+        // even rows run right to left upright, odd rows left to right rotated
+        let (index, x, y) = if row % 2 == 0 {
+            (columns as u32 - 1 - column, x, y)
+        } else {
+            (column, PANEL_WIDTH - 1 - x, PANEL_HEIGHT - 1 - y)
+        };
+
+        Some(ChainLocation {
+            panel: (row * columns as u32 + index) as u8,
+            x: x as u8,
+            y: y as u8,
+        })
+    }
+}
+
+impl ChainLocation {
+    /// The location in the shift geometry of a chain of `panels` panels.
+    const fn shift_location(&self, panels: u8) -> ShiftLocation {
+        let scan = (PANEL_HEIGHT / 2) as u8;
+
+        ShiftLocation {
+            column: (panels - 1 - self.panel) as u16 * PANEL_WIDTH as u16 + self.x as u16,
+            row: self.y % scan,
+            half: if self.y < scan {
+                Half::Upper
+            } else {
+                Half::Lower
+            },
+        }
+    }
+}
+
+impl PanelMapping for Stacking {
+    fn size(&self) -> Size {
+        Stacking::size(self)
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        Some(self.locate(point)?.shift_location(self.panels()))
+    }
+}
+
+/// The mounting of a panel, relative to its place in a [`Stacking`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Orientation {
+    /// Mounted as the stacking expects.
+    #[default]
+    Normal,
+    /// Mounted upside down (e.g. for cable routing).
+    Rotated180,
+}
+
+/// A [`Stacking`] of `PANELS` panels, with each panel's mounting configured
+/// individually.
+///
+/// ```
+/// # use hub75_remastered::layout::*;
+/// // the middle panel of three is mounted upside down
+/// let layout = Oriented::new(
+///     Stacking::Horizontal { panels: 3 },
+///     [Orientation::Normal, Orientation::Rotated180, Orientation::Normal],
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Oriented<const PANELS: usize> {
+    stacking: Stacking,
+    orientations: [Orientation; PANELS],
+}
+
+impl<const PANELS: usize> Oriented<PANELS> {
+    /// Combine a stacking with the orientations of its panels, indexed by their
+    /// position in the chain.
+    ///
+    /// *`PANELS` must match the number of panels of the stacking.*
+    pub const fn new(stacking: Stacking, orientations: [Orientation; PANELS]) -> Self {
+        assert!(stacking.panels() as usize == PANELS);
+
+        Self {
+            stacking,
+            orientations,
+        }
+    }
+
+    /// Map a logical point to its location on the chain, if it is in bounds.
+    pub fn locate(&self, point: Point) -> Option<ChainLocation> {
+        let location = self.stacking.locate(point)?;
+
+        Some(match self.orientations[location.panel as usize] {
+            Orientation::Normal => location,
+            Orientation::Rotated180 => ChainLocation {
+                x: (PANEL_WIDTH - 1) as u8 - location.x,
+                y: (PANEL_HEIGHT - 1) as u8 - location.y,
+                ..location
+            },
+        })
+    }
+}
+
+impl<const PANELS: usize> PanelMapping for Oriented<PANELS> {
+    fn size(&self) -> Size {
+        self.stacking.size()
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        Some(self.locate(point)?.shift_location(self.stacking.panels()))
+    }
+}
+
+/// Panels of differing widths (but equal heights) side by side, with the driver
+/// connected to the rightmost panel.
+///
+/// The widths are indexed by the position in the chain, so the first width is of
+/// the rightmost panel:
+///
+/// ```text
+/// MixedChain::new([64, 32])
+///
+/// (0, 0)
+///    +--------+----------------+
+///    |   1    |    panel 0     |  <- driver
+///    +--------+----------------+
+///                          (95, 31)
+/// ```
+///
+/// The chain is driven as one display of the combined width, e.g.
+/// `Hub75<96, 32, 16, ...>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MixedChain<const PANELS: usize> {
+    widths: [u16; PANELS],
+    orientations: [Orientation; PANELS],
+}
+
+impl<const PANELS: usize> MixedChain<PANELS> {
+    /// Describe a chain of upright panels with the provided widths.
+    pub const fn new(widths: [u16; PANELS]) -> Self {
+        Self {
+            widths,
+            orientations: [Orientation::Normal; PANELS],
+        }
+    }
+
+    /// Set the mounting of each panel, indexed by the position in the chain.
+    pub const fn with_orientations(self, orientations: [Orientation; PANELS]) -> Self {
+        Self {
+            orientations,
+            ..self
+        }
+    }
+
+    /// The combined width of all panels.
+    pub const fn width(&self) -> u32 {
+        let mut width = 0;
+        let mut panel = 0;
+
+        while panel < PANELS {
+            width += self.widths[panel] as u32;
+            panel += 1;
+        }
+
+        width
+    }
+
+    /// Map a logical point to its location on the chain, if it is in bounds.
+    pub fn locate(&self, point: Point) -> Option<ChainLocation> {
+        if point.x < 0
+            || point.y < 0
+            || point.x as u32 >= self.width()
+            || point.y as u32 >= PANEL_HEIGHT
+        {
+            return None;
+        }
+
+        let (mut x, y) = (point.x as u32, point.y as u32);
+
+        // scan from the leftmost (last) panel
+        for panel in (0..PANELS).rev() {
+            let width = self.widths[panel] as u32;
+
+            if x < width {
+                let (x, y) = match self.orientations[panel] {
+                    Orientation::Normal => (x, y),
+                    Orientation::Rotated180 => (width - 1 - x, PANEL_HEIGHT - 1 - y),
+                };
+
+                return Some(ChainLocation {
+                    panel: panel as u8,
+                    x: x as u8,
+                    y: y as u8,
+                });
+            }
+
+            x -= width;
+        }
+
+        None
+    }
+}
+
+impl<const PANELS: usize> PanelMapping for MixedChain<PANELS> {
+    fn size(&self) -> Size {
+        Size::new(self.width(), PANEL_HEIGHT)
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        let location = self.locate(point)?;
+
+        // the panels farther from the driver are shifted first
+        let offset: u16 = self.widths[location.panel as usize + 1..].iter().sum();
+
+        let single = ChainLocation {
+            panel: 0,
+            ..location
+        };
+
+        Some(ShiftLocation {
+            column: offset + location.x as u16,
+            ..single.shift_location(1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remap::Identity;
+
+    #[test]
+    fn vertical_serpentine_size() {
+        assert_eq!(
+            Stacking::VerticalSerpentine { panels: 3 }.size(),
+            Size::new(64, 96)
+        );
+    }
+
+    #[test]
+    fn vertical_serpentine_corners() {
+        let stacking = Stacking::VerticalSerpentine { panels: 2 };
+        let at = |panel, x, y| Some(ChainLocation { panel, x, y });
+
+        // panel 0, upright
+        assert_eq!(stacking.locate(Point::new(0, 0)), at(0, 0, 0));
+        assert_eq!(stacking.locate(Point::new(63, 0)), at(0, 63, 0));
+        assert_eq!(stacking.locate(Point::new(0, 31)), at(0, 0, 31));
+        assert_eq!(stacking.locate(Point::new(63, 31)), at(0, 63, 31));
+
+        // panel 1, rotated
+        assert_eq!(stacking.locate(Point::new(0, 32)), at(1, 63, 31));
+        assert_eq!(stacking.locate(Point::new(63, 32)), at(1, 0, 31));
+        assert_eq!(stacking.locate(Point::new(0, 63)), at(1, 63, 0));
+        assert_eq!(stacking.locate(Point::new(63, 63)), at(1, 0, 0));
+    }
+
+    #[test]
+    fn horizontal_is_native() {
+        let stacking = Stacking::Horizontal { panels: 2 };
+
+        assert_eq!(stacking.size(), Size::new(128, 32));
+        assert_eq!(
+            stacking.locate(Point::new(0, 0)),
+            Some(ChainLocation {
+                panel: 1,
+                x: 0,
+                y: 0
+            })
+        );
+
+        for point in [Point::new(0, 0), Point::new(70, 20), Point::new(127, 31)] {
+            assert_eq!(
+                PanelMapping::map(&stacking, point),
+                PanelMapping::map(&Identity::new(Size::new(128, 32)), point)
+            );
+        }
+    }
+
+    #[test]
+    fn vertical_serpentine_shift_location() {
+        let stacking = Stacking::VerticalSerpentine { panels: 2 };
+
+        // panel 0 is closest to the driver, so it is shifted last
+        assert_eq!(
+            PanelMapping::map(&stacking, Point::new(1, 17)),
+            Some(ShiftLocation {
+                column: 65,
+                row: 1,
+                half: Half::Lower
+            })
+        );
+        // logical (0, 32) is the local (63, 31) of the rotated panel 1
+        assert_eq!(
+            PanelMapping::map(&stacking, Point::new(0, 32)),
+            Some(ShiftLocation {
+                column: 63,
+                row: 15,
+                half: Half::Lower
+            })
+        );
+    }
+
+    #[test]
+    fn grid_corners() {
+        let stacking = Stacking::Grid {
+            columns: 2,
+            rows: 2,
+        };
+        let at = |panel, x, y| Some(ChainLocation { panel, x, y });
+
+        assert_eq!(stacking.size(), Size::new(128, 64));
+        assert_eq!(stacking.panels(), 4);
+
+        // top row, upright, right to left
+        assert_eq!(stacking.locate(Point::new(127, 0)), at(0, 63, 0));
+        assert_eq!(stacking.locate(Point::new(0, 0)), at(1, 0, 0));
+
+        // bottom row, rotated, left to right
+        assert_eq!(stacking.locate(Point::new(0, 32)), at(2, 63, 31));
+        assert_eq!(stacking.locate(Point::new(127, 63)), at(3, 0, 0));
+    }
+
+    #[test]
+    fn grid_generalizes() {
+        for (stacking, grid) in [
+            (
+                Stacking::Horizontal { panels: 3 },
+                Stacking::Grid {
+                    columns: 3,
+                    rows: 1,
+                },
+            ),
+            (
+                Stacking::VerticalSerpentine { panels: 3 },
+                Stacking::Grid {
+                    columns: 1,
+                    rows: 3,
+                },
+            ),
+        ] {
+            assert_eq!(stacking.size(), grid.size());
+
+            for y in 0..grid.size().height as i32 {
+                for x in 0..grid.size().width as i32 {
+                    let point = Point::new(x, y);
+
+                    assert_eq!(stacking.locate(point), grid.locate(point));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oriented_rotates_single_panel() {
+        use Orientation::*;
+
+        let stacking = Stacking::Horizontal { panels: 3 };
+        let oriented = Oriented::new(stacking, [Normal, Rotated180, Normal]);
+        let at = |panel, x, y| Some(ChainLocation { panel, x, y });
+
+        // the middle panel is rotated
+        assert_eq!(oriented.locate(Point::new(64, 0)), at(1, 63, 31));
+        assert_eq!(oriented.locate(Point::new(127, 31)), at(1, 0, 0));
+
+        // the others are untouched
+        for point in [Point::new(0, 0), Point::new(63, 31), Point::new(191, 5)] {
+            assert_eq!(oriented.locate(point), stacking.locate(point));
+        }
+    }
+
+    #[test]
+    fn mixed_chain() {
+        let chain = MixedChain::new([64, 32]);
+        let at = |panel, x, y| Some(ChainLocation { panel, x, y });
+
+        assert_eq!(PanelMapping::size(&chain), Size::new(96, 32));
+        assert_eq!(chain.locate(Point::new(0, 0)), at(1, 0, 0));
+        assert_eq!(chain.locate(Point::new(31, 31)), at(1, 31, 31));
+        assert_eq!(chain.locate(Point::new(32, 0)), at(0, 0, 0));
+        assert_eq!(chain.locate(Point::new(96, 0)), None);
+
+        // upright panels side by side are the native layout
+        for point in [Point::new(0, 0), Point::new(40, 20), Point::new(95, 31)] {
+            assert_eq!(
+                PanelMapping::map(&chain, point),
+                PanelMapping::map(&Identity::new(Size::new(96, 32)), point)
+            );
+        }
+
+        let chain = chain.with_orientations([Orientation::Normal, Orientation::Rotated180]);
+
+        assert_eq!(chain.locate(Point::new(0, 0)), at(1, 31, 31));
+        assert_eq!(
+            PanelMapping::map(&chain, Point::new(0, 0)),
+            Some(ShiftLocation {
+                column: 31,
+                row: 15,
+                half: Half::Lower
+            })
+        );
+    }
+
+    #[test]
+    fn vertical_serpentine_out_of_bounds() {
+        let stacking = Stacking::VerticalSerpentine { panels: 2 };
+
+        assert_eq!(stacking.locate(Point::new(-1, 0)), None);
+        assert_eq!(stacking.locate(Point::new(64, 0)), None);
+        assert_eq!(stacking.locate(Point::new(0, 64)), None);
+    }
+}
@@ -2,212 +2,3031 @@
 
 #![no_std]
 
+mod fade;
 mod fmt;
+mod hal;
 
+#[doc(hidden)]
+pub mod macros;
+
+#[cfg(not(any(feature = "hal-1", feature = "hal-02")))]
+pub use hal::mock;
+
+#[cfg(feature = "async")]
+pub mod async_output;
+pub mod blend;
+pub mod buffer;
+pub mod chip;
+pub mod color;
+pub mod display;
+pub mod dma;
+#[cfg(feature = "dump")]
+pub mod dump;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "esp32")]
+pub mod esp32;
+pub mod frame;
+pub mod gamma;
+pub mod layer;
+pub mod layout;
+pub mod palette;
 pub mod pins;
+pub mod pipeline;
+pub mod planes;
+pub mod power;
+pub mod quad;
+pub mod queue;
+pub mod remap;
+#[cfg(feature = "rp2040-pio")]
+pub mod rp2040;
+pub mod scan;
+#[cfg(target_has_atomic = "8")]
+pub mod shared;
+pub mod single;
+pub mod sink;
+pub mod spi;
+pub mod split;
+#[cfg(feature = "stm32")]
+pub mod stm32;
+pub mod stream;
+pub mod synced;
+pub mod target;
+#[cfg(all(test, feature = "hal-mock"))]
+mod test_utils;
+use blend::BlendMode;
+use chip::ChipMode;
+pub use display::Hub75Display;
+pub use frame::{FrameBuffer, FrameStorage};
+use gamma::Gamma;
 use pins::*;
+use pipeline::DisplayPipeline;
+use scan::{Cursor, ScanOrder};
+use sink::{PinSink, RowSink};
+use stream::RenderedRows;
+
+pub use hal::{CachedPin, DelayProvider, NoDelay};
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// A pixel access outside of the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutOfBounds;
 
-#[cfg(feature = "hal-02")]
-use embedded_hal_02::blocking::delay::DelayUs;
-#[cfg(feature = "hal-1")]
-use embedded_hal_1::delay::DelayNs;
+/// An invalid runtime configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The number of active bit planes must be in `1..=BITS`.
+    ActiveBitsOutOfRange,
+    /// The output path can't drive the [chip mode](ChipMode) of the display, e.g.
+    /// S-PWM driver chips on a path which doesn't drive their grayscale clock.
+    UnsupportedChipMode,
+    /// The current gain of MBI5124 driver chips must be at most 63.
+    GainOutOfRange,
+}
 
-#[cfg(feature = "hal-02")]
-pub trait DelayProvider: DelayUs<u32> {}
-#[cfg(feature = "hal-1")]
-pub trait DelayProvider: DelayNs {}
+/// An error of the pins (or transport) of an operation, or of a configuration it
+/// can't be performed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The pins (or transport) failed.
+    Pins(E),
+    /// The display is configured in a way the operation doesn't support.
+    Config(ConfigError),
+}
 
-#[cfg(feature = "hal-02")]
-impl<T: DelayUs<u32>> DelayProvider for T {}
-#[cfg(feature = "hal-1")]
-impl<T: DelayNs> DelayProvider for T {}
+impl<E> From<ConfigError> for Error<E> {
+    fn from(error: ConfigError) -> Self {
+        Self::Config(error)
+    }
+}
 
 /// A helper struct for computing the frame time compensation to maintain a constant
 /// brightness across all color depths.
+///
+/// Only the most significant `active_bits` bit planes are shown, with the on-time
+/// of the dropped planes redistributed among the shown ones. All durations are
+/// scaled by `brightness` and the power `limit`, where 255 is unity, and are
+/// never shorter than `min_show_us` unless the display is dark.
 struct FrameTimeCompensation<const BITS: u8> {
     h: u32,
+    active_bits: u8,
+    brightness: u8,
+    limit: u8,
+    min_show_us: u32,
 }
 
 impl<const BITS: u8> FrameTimeCompensation<BITS> {
-    fn new(on_ratio: f64) -> Self {
+    /// Compute the compensation for an on ratio in per-mille, in integer math so it
+    /// can be done in const contexts and without soft-float routines.
+    const fn new(on_ratio_permille: u16) -> Self {
+        assert!(
+            on_ratio_permille < 1000,
+            "on_ratio must be in 0..1000 per-mille"
+        );
+
+        let p = (2 * BITS + 1) as u32;
+        let h = p * on_ratio_permille as u32 / (1000 - on_ratio_permille as u32);
+
+        Self {
+            h,
+            active_bits: BITS,
+            brightness: 255,
+            limit: 255,
+            min_show_us: 0,
+        }
+    }
+
+    /// Compute the compensation for an on ratio in `0..1`, at per-mille resolution.
+    fn from_ratio(on_ratio: f64) -> Self {
         assert!((0f64..1f64).contains(&on_ratio));
 
-        let p = (2 * BITS + 1) as f64;
-        let h = ((p * on_ratio) / (1. - on_ratio)) as u32;
+        let ftc = Self::new(((on_ratio * 1000. + 0.5) as u16).min(999));
 
-        fmt::trace!("FTC H constant: {}", h);
+        fmt::trace!("FTC H constant: {}", ftc.h);
 
-        Self { h }
+        ftc
+    }
+
+    /// Show only the most significant `bits` bit planes.
+    fn set_active_bits(&mut self, bits: u8) -> Result<(), ConfigError> {
+        if !(1..=BITS).contains(&bits) {
+            return Err(ConfigError::ActiveBitsOutOfRange);
+        }
+
+        self.active_bits = bits;
+
+        Ok(())
+    }
+
+    /// The bit planes to be shown.
+    const fn masks(&self) -> Range<u8> {
+        BITS - self.active_bits..BITS
     }
 
     const fn duration(&self, mask: &u8) -> u32 {
-        2u32.pow(*mask as u32) * self.h / (2u32.pow(BITS as u32) - 1)
+        let scale = self.brightness as u64 * self.limit as u64 / 255;
+
+        if scale == 0 {
+            return 0;
+        }
+
+        let duration = ((self.h as u64) << *mask) * scale
+            / (((1u64 << BITS) - (1u64 << (BITS - self.active_bits))) * 255);
+
+        self.clamp(duration)
+    }
+
+    /// Saturate a show duration to `u32` and raise it to the minimum.
+    const fn clamp(&self, duration: u64) -> u32 {
+        let duration = if duration > u32::MAX as u64 {
+            u32::MAX
+        } else {
+            duration as u32
+        };
+
+        if duration < self.min_show_us {
+            self.min_show_us
+        } else {
+            duration
+        }
     }
 }
 
+// Color Correction
+
+// This table remaps linear input values
+// (the numbers we’d like to use; e.g. 127 = half brightness)
+// to nonlinear gamma-corrected output values
+// (numbers producing the desired effect on the LED;
+// e.g. 36 = half brightness).
+pub(crate) const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// The temporal dither offsets, in quarters of a quantization step.
+///
+/// Every pixel cycles through all of them over 4 frames, with neighboring pixels
+/// at different phases so the panel as a whole does not flicker.
+const DITHER: [u16; 4] = [0, 2, 3, 1];
+
+/// Scale each channel of a color by a factor, where 255 is unity.
+const fn scale_color(color: &(u8, u8, u8), scale: &(u8, u8, u8)) -> (u8, u8, u8) {
+    const fn scale_channel(value: u8, scale: u8) -> u8 {
+        ((value as u16 * (scale as u16 + 1)) >> 8) as u8
+    }
+
+    (
+        scale_channel(color.0, scale.0),
+        scale_channel(color.1, scale.1),
+        scale_channel(color.2, scale.2),
+    )
+}
+
+/// Whether a scan row is output in the provided interlaced field (`None` while
+/// not interlaced).
+pub(crate) const fn in_field(row: u8, field: Option<bool>) -> bool {
+    match field {
+        Some(odd) => (row % 2 == 1) == odd,
+        None => true,
+    }
+}
+
+/// The scan rows of `order` which are output in the provided interlaced field.
+pub(crate) fn field_rows<const SCAN: usize>(
+    order: [u8; SCAN],
+    field: Option<bool>,
+) -> impl Iterator<Item = u8> {
+    order.into_iter().filter(move |row| in_field(*row, field))
+}
+
+/// The row map selecting every scan row by its own address.
+const fn identity_row_map<const SCAN: usize>() -> [u8; SCAN] {
+    let mut map = [0; SCAN];
+    let mut row = 0;
+
+    while row < SCAN {
+        map[row] = row as u8;
+        row += 1;
+    }
+
+    map
+}
+
+/// Write a color to a framebuffer entry, returning whether it changed.
+fn update(pixel: &mut (u8, u8, u8), color: (u8, u8, u8)) -> bool {
+    let changed = *pixel != color;
+
+    *pixel = color;
+
+    changed
+}
+
 // Display Drivers
 
-/// A 64x32 display with 2 colors written at a time.
-pub struct Hub75_64_32_2<
+/// A `WIDTH` by `HEIGHT` display with `SCAN` scan rows and 2 colors written at a
+/// time.
+///
+/// Each scan row selects the row pair `row` and `row + HEIGHT / 2`, so `HEIGHT`
+/// must be `2 * SCAN`.
+///
+/// The framebuffer is embedded in the display by default, or provided by the
+/// application as a `&'static mut` [`FrameBuffer`] (see
+/// [`new_with_buffer`](Self::new_with_buffer)).
+pub struct Hub75<
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
     const BITS: u8,
-    UpperColorPins: IsColorPins,
-    LowerColorPins: IsColorPins,
+    ColorPins: IsDualColorPins,
     RowPins: IsRowPins,
     DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
 > {
-    top_data: [[(u8, u8, u8); 64]; 32 / 2],
-    bottom_data: [[(u8, u8, u8); 64]; 32 / 2],
+    frame: frame::Storage<WIDTH, HEIGHT, Frame>,
+    pipeline: Pipeline,
+    cursor: Cursor,
+    blanked: bool,
+    halves_swapped: bool,
+    min_frame_interval_us: u32,
+    color_temperature: (u8, u8, u8),
+    white_balance: (u8, u8, u8),
+    /// The combined color temperature and white balance scale.
+    channel_scale: (u8, u8, u8),
+    dithering: bool,
+    fade: Option<fade::Fade>,
+    row_brightness: [u8; SCAN],
+    brightness_mask: Option<&'static [[u8; WIDTH]; HEIGHT]>,
+    overlay: Option<&'static mut layer::Layer<WIDTH, HEIGHT>>,
+    power_limit: Option<power::PowerLimit>,
+    /// The per channel sums of the framebuffer, and the generation they are of.
+    power_sums: (Option<u32>, [u32; 3]),
+    interlaced: bool,
+    /// The odd rows are output next while interlaced.
+    odd_field: bool,
+    generation: u32,
+    /// The stamp at which each scan row was last marked dirty.
+    dirty_rows: [u32; SCAN],
+    /// The stamp of rows marked dirty now, advanced whenever a consumer marks its
+    /// rendered rows.
+    dirty_stamp: u32,
+    frame_count: u32,
+    vsync: Option<&'static AtomicU32>,
+    blend_mode: BlendMode,
+    latch_guard_us: u32,
+    row_map: [u8; SCAN],
+    scan_order: [u8; SCAN],
+    chip_mode: ChipMode,
     ftc: FrameTimeCompensation<BITS>,
-    upper_color_pins: UpperColorPins,
-    lower_color_pins: LowerColorPins,
+    color_pins: ColorPins,
     row_pins: RowPins,
     data_pins: DataPins,
 }
 
-impl<E, const BITS: u8, UpperColorPins, LowerColorPins, RowPins, DataPins>
-    Hub75_64_32_2<BITS, UpperColorPins, LowerColorPins, RowPins, DataPins>
+/// A 64x32 display with 2 colors written at a time (1/16 scan, A-D row pins).
+pub type Hub75_64_32_2<
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<64, 32>,
+    Pipeline = Gamma,
+> = Hub75<64, 32, 16, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+
+/// A 64x64 display with 2 colors written at a time (1/32 scan, A-E row pins).
+pub type Hub75_64_64_2<
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<64, 64>,
+    Pipeline = Gamma,
+> = Hub75<64, 64, 32, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+
+/// A 128x64 display with 2 colors written at a time (1/32 scan, A-E row pins).
+pub type Hub75_128_64_2<
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<128, 64>,
+    Pipeline = Gamma,
+> = Hub75<128, 64, 32, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+
+/// A 32x32 display with 2 colors written at a time (1/16 scan, A-D row pins).
+pub type Hub75_32_32_2<
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<32, 32>,
+    Pipeline = Gamma,
+> = Hub75<32, 32, 16, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+
+/// A 32x16 display with 2 colors written at a time (1/8 scan, A-C row pins).
+pub type Hub75_32_16_2<
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<32, 16>,
+    Pipeline = Gamma,
+> = Hub75<32, 16, 8, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
 where
-    UpperColorPins: IsColorPins<Error = E>,
-    LowerColorPins: IsColorPins<Error = E>,
+    ColorPins: IsDualColorPins<Error = E>,
     RowPins: IsRowPins<Error = E>,
     DataPins: IsDataPins<Error = E>,
 {
     /// Construct a new Hub75x display instance.
     ///
+    /// `color_pins` is either a pair of upper and lower color pins, or any other
+    /// [`IsDualColorPins`] implementor (such as a six-tuple of color pins).
+    ///
     /// `on_ratio` is a float from 0-1 (exclusive) that configures the proportion
     /// with which the pixel values are held before proceeding to the next row.
     /// This permits control of the observed brightness of the display at the cost
-    /// of refresh rate.
+    /// of refresh rate. It is applied at per-mille resolution.
+    ///
+    /// See [`new_permille`](Self::new_permille) to avoid floating point math.
     pub fn new(
-        upper_color_pins: UpperColorPins,
-        lower_color_pins: LowerColorPins,
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        Self::with_ratio(
+            FrameBuffer::new(),
+            color_pins,
+            row_pins,
+            data_pins,
+            on_ratio,
+        )
+    }
+
+    /// Construct a new Hub75x display instance, with `on_ratio` (see
+    /// [`new`](Self::new)) in per-mille, i.e. from 0-1000 (exclusive).
+    ///
+    /// This is free of floating point math and can be evaluated in const contexts,
+    /// e.g. to place the display in a `static`.
+    pub const fn new_permille(
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: u16,
+    ) -> Self {
+        Self::with_frame(
+            FrameBuffer::new(),
+            color_pins,
+            row_pins,
+            data_pins,
+            FrameTimeCompensation::new(on_ratio),
+        )
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    >
+    Hub75<
+        WIDTH,
+        HEIGHT,
+        SCAN,
+        BITS,
+        ColorPins,
+        RowPins,
+        DataPins,
+        &'static mut FrameBuffer<WIDTH, HEIGHT>,
+    >
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new Hub75x display instance like [`new`](Hub75::new), drawing to
+    /// and outputting from a framebuffer provided by the application.
+    ///
+    /// This leaves the placement of the buffer to the application, e.g. in
+    /// DMA-capable RAM, core-coupled memory or external PSRAM via a `#[link_section]`.
+    /// The content of the buffer is shown as is, and the display only holds a
+    /// reference to it.
+    pub fn new_with_buffer(
+        buffer: &'static mut FrameBuffer<WIDTH, HEIGHT>,
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        Self::with_ratio(buffer, color_pins, row_pins, data_pins, on_ratio)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+{
+    /// Construct a display around the storage of its framebuffer, with `on_ratio`
+    /// as for [`new`](Hub75::new).
+    fn with_ratio(
+        frame: Frame,
+        color_pins: ColorPins,
         row_pins: RowPins,
         data_pins: DataPins,
         on_ratio: f64,
     ) -> Self {
-        let ftc = FrameTimeCompensation::new(on_ratio);
+        let display = Self::with_frame(
+            frame,
+            color_pins,
+            row_pins,
+            data_pins,
+            FrameTimeCompensation::from_ratio(on_ratio),
+        );
+
+        fmt::trace!(
+            "new Hub75 with {}x{} pixels, {} scan rows and {} bits",
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS
+        );
+
+        display
+    }
 
-        fmt::trace!("new Hub75_64_32_2 with {} bits", BITS);
+    /// Construct a display around the storage of its framebuffer.
+    const fn with_frame(
+        frame: Frame,
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        ftc: FrameTimeCompensation<BITS>,
+    ) -> Self {
+        const {
+            assert!(HEIGHT == 2 * SCAN, "HEIGHT must be twice SCAN");
+            assert!(WIDTH <= u16::MAX as usize, "columns are addressed as u16");
+            assert!(SCAN <= u8::MAX as usize, "scan rows are addressed as u8");
+            assert!(BITS >= 1 && BITS <= 8, "BITS must be in 1..=8");
+        };
 
         Self {
-            top_data: [[(0, 0, 0); 64]; 16],
-            bottom_data: [[(0, 0, 0); 64]; 16],
+            frame: frame::Storage(frame),
+            pipeline: Gamma::POWER,
+            cursor: Cursor::new(),
+            blanked: false,
+            halves_swapped: false,
+            min_frame_interval_us: 0,
+            color_temperature: (255, 255, 255),
+            white_balance: (255, 255, 255),
+            channel_scale: (255, 255, 255),
+            dithering: false,
+            fade: None,
+            row_brightness: [255; SCAN],
+            brightness_mask: None,
+            overlay: None,
+            power_limit: None,
+            power_sums: (None, [0; 3]),
+            interlaced: false,
+            odd_field: false,
+            generation: 0,
+            dirty_rows: [0; SCAN],
+            dirty_stamp: 0,
+            frame_count: 0,
+            vsync: None,
+            blend_mode: BlendMode::Replace,
+            latch_guard_us: 1,
+            row_map: identity_row_map(),
+            scan_order: identity_row_map(),
+            chip_mode: ChipMode::Pwm,
             ftc,
-            upper_color_pins,
-            lower_color_pins,
+            color_pins,
             row_pins,
             data_pins,
         }
     }
+}
 
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
     /// Output the framebuffer to the display.
     ///
+    /// If a minimum frame interval is configured, the remaining idle time is spent
+    /// (with the display blanked) at the end of the frame, and returned in
+    /// microseconds.
+    ///
+    /// Does nothing while the display is [blanked](Self::blank). While
+    /// [interlaced](Self::set_interlaced), only every other row is output.
+    ///
     /// *This function is time-sensitive and should be called as often as possible.*
-    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
-        for (i, (upper_row, lower_row)) in self.top_data.iter().zip(&self.bottom_data).enumerate() {
-            self.row_pins.set_row(&(i as u8))?;
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<u32, E> {
+        if self.blanked {
+            return Ok(0);
+        }
 
-            for mask in 0..BITS {
-                for (upper_col, lower_col) in upper_row.iter().zip(lower_row) {
-                    self.upper_color_pins.set_color::<BITS>(upper_col, &mask)?;
-                    self.lower_color_pins.set_color::<BITS>(lower_col, &mask)?;
+        if let ChipMode::SPwm { gclk_per_row } = self.chip_mode {
+            self.output_spwm(delay, gclk_per_row)?;
+            self.complete_frame();
 
-                    self.data_pins.shift(delay)?;
-                }
+            return Ok(0);
+        }
 
-                self.data_pins.latch(delay)?;
+        let mut selected_row = None;
+        let field = self.field();
 
-                self.data_pins.show(delay, self.ftc.duration(&mask))?;
+        self.begin_field(field);
+
+        for row in field_rows(self.scan_order, field) {
+            for mask in self.ftc.masks() {
+                let packed = self.serialize_row(row, mask);
+                let show_ns = self.show_duration(row, &mask).saturating_mul(1000);
+
+                PinSink {
+                    color_pins: &mut self.color_pins,
+                    row_pins: &mut self.row_pins,
+                    data_pins: &mut self.data_pins,
+                    delay,
+                    latch_guard_us: self.latch_guard_us,
+                    row: &mut selected_row,
+                }
+                .send_row(self.row_map[row as usize], mask, &packed, show_ns)?;
             }
         }
 
-        Ok(())
+        let idle = self.idle_us(field);
+
+        self.complete_field(field);
+
+        if idle > 0 {
+            delay.delay_us(idle);
+        }
+
+        Ok(idle)
     }
 
-    /// Set the framebuffer to all black.
-    pub fn wipe(&mut self) {
-        self.top_data = [[(0, 0, 0); 64]; 16];
-        self.bottom_data = [[(0, 0, 0); 64]; 16];
+    /// Shift the provided bit plane of a row pair into the display.
+    fn shift_row<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        row: u8,
+        mask: u8,
+    ) -> Result<(), E> {
+        let packed = self.serialize_row(row, mask);
+
+        sink::shift_packed(&mut self.color_pins, &mut self.data_pins, delay, &packed)
     }
-}
 
-// DrawTarget impl
+    /// Set the framebuffer to all black.
+    pub fn wipe(&mut self) {
+        let changed = self.frame.pixels().any(|pixel| *pixel != (0, 0, 0));
 
-use core::convert::Infallible;
-use embedded_graphics::{
-    draw_target::DrawTarget,
-    geometry::{Dimensions, Point, Size},
-    pixelcolor::{Rgb565, RgbColor},
-    primitives::Rectangle,
-    Pixel,
-};
+        *self.frame = FrameBuffer::new();
 
-impl<
-        const BITS: u8,
-        UpperColorPins: IsColorPins,
-        LowerColorPins: IsColorPins,
-        RowPins: IsRowPins,
-        DataPins: IsDataPins,
-    > Dimensions for Hub75_64_32_2<BITS, UpperColorPins, LowerColorPins, RowPins, DataPins>
-{
-    fn bounding_box(&self) -> Rectangle {
-        Rectangle::new(Point::zero(), Size::new(64, 32))
+        self.mark_changed(changed);
     }
 }
 
 impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
         const BITS: u8,
-        UpperColorPins: IsColorPins,
-        LowerColorPins: IsColorPins,
+        ColorPins: IsDualColorPins,
         RowPins: IsRowPins,
         DataPins: IsDataPins,
-    > DrawTarget for Hub75_64_32_2<BITS, UpperColorPins, LowerColorPins, RowPins, DataPins>
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
 {
-    type Color = Rgb565;
-    type Error = Infallible;
+    /// Exchange the roles of the upper and lower color pins.
+    ///
+    /// Some panels have their halves swapped relative to the R1/R2 data lines, so
+    /// content drawn at the top appears at the bottom. Since this is applied when
+    /// shifting data out, it composes with any transformation applied while
+    /// drawing.
+    pub fn set_halves_swapped(&mut self, swapped: bool) {
+        self.halves_swapped = swapped;
+        self.mark_rows_dirty();
+    }
 
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        // This table remaps linear input values
-        // (the numbers we’d like to use; e.g. 127 = half brightness)
-        // to nonlinear gamma-corrected output values
-        // (numbers producing the desired effect on the LED;
-        // e.g. 36 = half brightness).
-        const GAMMA8: [u8; 256] = [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4,
-            4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11,
-            12, 12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22,
-            22, 23, 24, 24, 25, 25, 26, 27, 27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37,
-            38, 39, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58,
-            59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72, 73, 74, 75, 77, 78, 79, 81, 82, 83, 85,
-            86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104, 105, 107, 109, 110, 112, 114,
-            115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142, 144,
-            146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175, 177, 180,
-            182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
-            223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
-        ];
-
-        for Pixel(coord, color) in pixels {
-            if coord.x >= 0 && coord.x < 64 && coord.y >= 0 && coord.y < 32 {
-                if coord.y < 16 {
-                    self.top_data[coord.y as usize][coord.x as usize] = (
-                        GAMMA8[(color.r() as usize + 1) * 8 - 1],
-                        GAMMA8[(color.g() as usize + 1) * 4 - 1],
-                        GAMMA8[(color.b() as usize + 1) * 8 - 1],
-                    );
-                } else {
-                    self.bottom_data[(coord.y - 16) as usize][coord.x as usize] = (
-                        GAMMA8[(color.r() as usize + 1) * 8 - 1],
-                        GAMMA8[(color.g() as usize + 1) * 4 - 1],
-                        GAMMA8[(color.b() as usize + 1) * 8 - 1],
-                    );
-                }
+    /// Set the minimum interval between the starts of consecutive frames, capping
+    /// the refresh rate.
+    ///
+    /// The frame time is estimated from the deterministic delays of a frame (show
+    /// durations and the pulse widths reported by the data pins, see
+    /// [`IsDataPins::shift_delay_us`]), so as long as the data pins report them
+    /// accurately, the actual frame time can only be longer and the cap is never
+    /// exceeded.
+    ///
+    /// Zero (the default) disables pacing.
+    pub fn set_min_frame_interval_us(&mut self, interval: u32) {
+        self.min_frame_interval_us = interval;
+    }
+
+    /// The estimated duration of one frame in microseconds, excluding pacing.
+    ///
+    /// With the delays of the standard data pins, shifting takes 2µs per column and
+    /// bit plane (other data pins report their own delays), so long chains (large `WIDTH`) quickly dominate the frame time.
+    /// Use this (or [`refresh_rate_hz`](Self::refresh_rate_hz)) to check whether a
+    /// chain is still flicker free, and reduce the active bit planes or use
+    /// [`TimedDataPins`] or [`Undelayed`] data pins otherwise.
+    pub fn frame_time_us(&self) -> u32 {
+        self.ftc
+            .masks()
+            .map(|mask| self.ftc.duration(&mask) + self.plane_overhead_us())
+            .sum::<u32>()
+            * SCAN as u32
+    }
+
+    /// The estimated time (in microseconds) spent shifting and latching one bit plane
+    /// of a row, on top of its show duration.
+    ///
+    /// This only accounts for the delays reported by the data pins (see
+    /// [`IsDataPins::shift_delay_us`]) and the latch guard, not for the time spent
+    /// toggling pins.
+    pub(crate) fn plane_overhead_us(&self) -> u32 {
+        WIDTH as u32 * self.data_pins.shift_delay_us()
+            + self.data_pins.latch_delay_us()
+            + self.latch_guard_us
+    }
+
+    /// The time (in microseconds) the bit plane `mask` of scan row `row` is shown
+    /// for, including its [row brightness](Self::set_row_brightness).
+    pub(crate) fn show_duration(&self, row: u8, mask: &u8) -> u32 {
+        match self.row_brightness[row as usize] {
+            255 => self.ftc.duration(mask),
+            0 => 0,
+            scale => self
+                .ftc
+                .clamp(self.ftc.duration(mask) as u64 * scale as u64 / 255),
+        }
+    }
+
+    /// The estimated refresh rate in Hz, excluding pacing (see
+    /// [`frame_time_us`](Self::frame_time_us)).
+    pub fn refresh_rate_hz(&self) -> u32 {
+        1_000_000 / self.frame_time_us().max(1)
+    }
+
+    /// Set the physical row pair `row` and `row + HEIGHT / 2` at once.
+    ///
+    /// Unless `already_corrected` is set, the linear colors are gamma corrected.
+    ///
+    /// *`row` must be less than `SCAN`.*
+    pub fn set_row_pair(
+        &mut self,
+        row: u8,
+        upper: &[(u8, u8, u8); WIDTH],
+        lower: &[(u8, u8, u8); WIDTH],
+        already_corrected: bool,
+    ) {
+        fmt::assert!((row as usize) < SCAN);
+
+        let row = row as usize;
+        let mut changed = false;
+
+        for (y, colors) in [(row, upper), (row + HEIGHT / 2, lower)] {
+            for (pixel, color) in self.frame.data[y].iter_mut().zip(colors) {
+                changed |= update(
+                    pixel,
+                    if already_corrected {
+                        *color
+                    } else {
+                        self.pipeline.correct(color)
+                    },
+                );
             }
         }
 
+        if changed {
+            self.dirty_rows[row] = self.dirty_stamp;
+        }
+
+        self.mark_rows_changed(changed);
+    }
+
+    /// Get the (gamma corrected) physical row pair `row` and `row + HEIGHT / 2`.
+    ///
+    /// *`row` must be less than `SCAN`.*
+    #[allow(clippy::type_complexity)]
+    pub fn row_pair(&self, row: u8) -> (&[(u8, u8, u8); WIDTH], &[(u8, u8, u8); WIDTH]) {
+        fmt::assert!((row as usize) < SCAN);
+
+        (
+            &self.frame.data[row as usize],
+            &self.frame.data[row as usize + HEIGHT / 2],
+        )
+    }
+
+    /// Scale each color channel of the displayed image, e.g. to shift it warmer by
+    /// reducing blue.
+    ///
+    /// 255 is unity, so `(255, 255, 255)` (the default) disables scaling at no
+    /// cost. The scaling is applied while shifting data out, so the framebuffer
+    /// content is not modified. It is multiplicative with the brightness set by
+    /// `on_ratio` and the active bit planes, which act on the show durations.
+    pub fn set_color_temperature(&mut self, scale: (u8, u8, u8)) {
+        self.color_temperature = scale;
+        self.update_channel_scale();
+    }
+
+    /// Set the color temperature of the displayed white in Kelvin, e.g. to match
+    /// ambient lighting.
+    ///
+    /// This is an approximation (see [`color::kelvin_scale`]), with 6500K
+    /// leaving colors unchanged.
+    pub fn set_color_temperature_kelvin(&mut self, kelvin: u16) {
+        self.set_color_temperature(color::kelvin_scale(kelvin));
+    }
+
+    /// Enable temporal dithering, gaining up to 2 bits of apparent color depth by
+    /// alternating the quantization of each pixel to the shown bit planes over 4
+    /// frames.
+    ///
+    /// This does not increase the show durations, but smooths gradients which band
+    /// at low `BITS` (or [active bits](Self::set_active_bits)). The refresh rate
+    /// should be high enough to hide the 4 frame cycle. Every row is
+    /// [dirty](Self::is_row_dirty) after every frame while dithering.
+    ///
+    /// S-PWM driver chips show 16 bits and are not dithered.
+    pub fn set_dithering(&mut self, dithering: bool) {
+        self.dithering = dithering;
+        self.mark_rows_dirty();
+    }
+
+    /// Set the gain of each color channel, calibrating the white point of the panel
+    /// (e.g. reducing green on green-tinted panels).
+    ///
+    /// 255 is unity (the default). Like the
+    /// [color temperature](Self::set_color_temperature), the gains are applied
+    /// while shifting data out, and both are combined, so the calibration persists
+    /// while the color temperature is adjusted at runtime.
+    pub fn set_white_balance(&mut self, gains: (u8, u8, u8)) {
+        self.white_balance = gains;
+        self.update_channel_scale();
+    }
+
+    /// Combine the color temperature and white balance into the scale applied
+    /// while shifting data out.
+    fn update_channel_scale(&mut self) {
+        self.channel_scale = scale_color(&self.color_temperature, &self.white_balance);
+        self.mark_rows_dirty();
+    }
+
+    /// Set the time (in microseconds) waited between disabling the output and
+    /// latching the next row, 1µs by default.
+    ///
+    /// Latching while the output of the previous bit plane is still (partially)
+    /// enabled can cause a faint flash of the whole row on some panels.
+    ///
+    /// This applies to all pin-driven output paths. Row sinks are responsible for
+    /// their own timing.
+    pub fn set_latch_guard_us(&mut self, guard: u32) {
+        self.latch_guard_us = guard;
+    }
+
+    /// Set how the driver chips of the panel generate their PWM, see [`ChipMode`].
+    pub fn set_chip_mode(&mut self, mode: ChipMode) {
+        self.chip_mode = mode;
+    }
+
+    /// Draw to the display through a [`PanelMapping`](remap::PanelMapping), for
+    /// panels whose wiring does not match their visual layout.
+    ///
+    /// The display itself is sized by the shift geometry of the panel.
+    pub fn mapped<M: remap::PanelMapping>(&mut self, mapping: M) -> remap::Remapped<'_, Self, M> {
+        remap::Remapped::new(self, mapping)
+    }
+
+    /// Set the row address selected for each scan row, for panels whose physical
+    /// row order does not match the binary address.
+    ///
+    /// Scan row `row` (the row pair `row` and `row + HEIGHT / 2` of the framebuffer)
+    /// is shown by selecting address `map[row]`. The default is the identity.
+    pub fn set_row_map(&mut self, map: [u8; SCAN]) {
+        self.row_map = map;
+    }
+
+    /// Set the order in which the scan rows are output.
+    ///
+    /// At low refresh rates, scanning the rows sequentially is visible as a band
+    /// rolling down the display. [`ScanOrder::Interleaved`] spreads consecutive
+    /// rows apart, making it much less apparent.
+    pub fn set_scan_order(&mut self, order: ScanOrder) {
+        self.scan_order = scan::scan_order(order);
+    }
+
+    /// Set the number of bit planes shown, trading color depth for refresh rate and
+    /// power without changing `BITS`.
+    ///
+    /// Only the most significant `bits` planes are shown, and their show durations
+    /// are rescaled so the overall brightness stays approximately constant. The
+    /// framebuffer keeps its full depth, so restoring `BITS` is instant.
+    ///
+    /// `bits` must be in `1..=BITS`.
+    pub fn set_active_bits(&mut self, bits: u8) -> Result<(), ConfigError> {
+        self.ftc.set_active_bits(bits)?;
+        self.mark_rows_dirty();
+
+        // resume at a plane which is still shown
+        let first = self.ftc.masks().start;
+
+        if self.cursor.mask < first {
+            self.cursor.mask = first;
+        }
+
         Ok(())
     }
+
+    /// The number of bit planes shown, see [`set_active_bits`](Self::set_active_bits).
+    pub fn active_bits(&self) -> u8 {
+        self.ftc.active_bits
+    }
+
+    /// Set the minimum time (in microseconds) any bit plane is shown, 0 by default.
+    ///
+    /// At low `on_ratio`s or brightness the show durations of the least
+    /// significant planes truncate to nothing, so those bits have no visible
+    /// effect. Raising the minimum keeps every plane visible at the cost of
+    /// slightly brighter dark tones. Dark planes (brightness or row brightness
+    /// 0) are not affected.
+    pub fn set_min_show_us(&mut self, min: u32) {
+        self.ftc.min_show_us = min;
+    }
+
+    /// The minimum show duration, see [`set_min_show_us`](Self::set_min_show_us).
+    pub fn min_show_us(&self) -> u32 {
+        self.ftc.min_show_us
+    }
+
+    /// Scale the show durations of each scan row (row pair), e.g. to compensate
+    /// rows which are brighter than others due to capacitor droop.
+    ///
+    /// 255 is unity (the default for every row). The scale is indexed by the row
+    /// pair (i.e. `y % SCAN`) and applied on top of the
+    /// [global brightness](Self::set_brightness).
+    pub fn set_row_brightness(&mut self, scale: &[u8; SCAN]) {
+        self.row_brightness = *scale;
+    }
+
+    /// Attenuate each pixel by a mask (indexed `[y][x]`, 255 is unity), e.g. to
+    /// correct vignetting or diffuser hot spots, or to dim status regions which are
+    /// lit around the clock.
+    ///
+    /// The mask is applied while shifting data out, so the framebuffer content is
+    /// not modified and the mask can be swapped at any time. It is borrowed rather
+    /// than copied to avoid doubling the memory of the display. S-PWM driver chips
+    /// are not masked.
+    pub fn set_brightness_mask(&mut self, mask: Option<&'static [[u8; WIDTH]; HEIGHT]>) {
+        self.brightness_mask = mask;
+        self.mark_rows_dirty();
+    }
+
+    /// Output the even and odd rows on alternating calls to
+    /// [`output`](Self::output), for slow MCUs which can't refresh at full rate.
+    ///
+    /// Every call takes half as long, doubling the apparent scan frequency and
+    /// reducing visible flicker, but each row is only lit every other call, halving
+    /// the brightness. A frame is complete (e.g. for the
+    /// [frame count](Self::frame_count)) after both fields, and the
+    /// [minimum frame interval](Self::set_min_frame_interval_us) applies to each
+    /// field.
+    ///
+    /// Resumable output (e.g. [`output_step`](Self::output_step)) scans the fields
+    /// one after the other in the same way.
+    ///
+    /// Interlacing starts with the even rows.
+    pub fn set_interlaced(&mut self, interlaced: bool) {
+        self.interlaced = interlaced;
+        self.odd_field = false;
+        self.cursor.seek(&self.scan_order, self.field());
+    }
+
+    /// Set the global brightness, scaling the show duration of every bit plane.
+    ///
+    /// 255 (the default) shows the durations configured by `on_ratio`, and 0 keeps
+    /// the display dark. Since the framebuffer is untouched, this can be changed
+    /// on the fly, e.g. to dim the display at night. Dimming shortens the frame, so
+    /// the refresh rate rises, but the show durations of the least significant
+    /// planes may truncate to nothing at low brightness.
+    ///
+    /// S-PWM driver chips generate their own PWM and are not dimmed by this, see
+    /// [`chip::Mbi5124`] for a hardware current gain.
+    ///
+    /// This cancels any [fade](Self::fade_to) in progress.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.ftc.brightness = brightness;
+        self.fade = None;
+        self.limit_power();
+    }
+
+    /// The global brightness, see [`set_brightness`](Self::set_brightness).
+    pub fn brightness(&self) -> u8 {
+        self.ftc.brightness
+    }
+
+    /// Set how pixels drawn through the [`DrawTarget`] implementation are combined
+    /// with the framebuffer content.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// A counter which is incremented by every call that modifies the framebuffer.
+    ///
+    /// Pixels are compared before being written, so calls which do not change any
+    /// pixel (e.g. redrawing identical content) leave the counter untouched. This
+    /// lets applications skip re-rendering when nothing changed.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Increment the generation if the framebuffer was changed, marking every row
+    /// dirty.
+    fn mark_changed(&mut self, changed: bool) {
+        if changed {
+            self.mark_rows_dirty();
+        }
+
+        self.mark_rows_changed(changed);
+    }
+
+    /// Increment the generation if the framebuffer was changed, with the changed
+    /// rows already marked dirty.
+    fn mark_rows_changed(&mut self, changed: bool) {
+        if changed {
+            self.generation = self.generation.wrapping_add(1);
+        }
+    }
+
+    /// Mark every row dirty.
+    fn mark_rows_dirty(&mut self) {
+        self.dirty_rows = [self.dirty_stamp; SCAN];
+    }
+
+    /// Whether the scan row `row` (i.e. the row pair `row` and `row + HEIGHT / 2`)
+    /// may have changed since `rendered` was [marked](Self::mark_rendered), either
+    /// in the framebuffer or by a setting affecting its serialization.
+    ///
+    /// This lets output paths which keep rendered rows around (e.g.
+    /// [`render_dirty`](Self::render_dirty)) skip re-rendering static content.
+    /// [`output`](Self::output) and the other paths driving the pins directly
+    /// serialize every row of every frame regardless.
+    ///
+    /// *`row` must be less than `SCAN`.*
+    pub fn is_row_dirty(&self, row: u8, rendered: &RenderedRows) -> bool {
+        self.dirty_rows[row as usize].wrapping_sub(rendered.0) as i32 >= 0
+    }
+
+    /// Mark every row clean for `rendered`, e.g. once the dirty rows were
+    /// rendered. Rows changed afterwards are dirty to it again.
+    pub fn mark_rendered(&mut self, rendered: &mut RenderedRows) {
+        self.dirty_stamp = self.dirty_stamp.wrapping_add(1);
+        *rendered = RenderedRows(self.dirty_stamp);
+    }
+
+    /// The number of frames completely output so far (wrapping), by any output
+    /// method.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Publish the [frame count](Self::frame_count) to `counter` at the end of every
+    /// frame, e.g. for other contexts to wait for the next frame before updating an
+    /// animation.
+    ///
+    /// The counter is only ever stored to, so it may be read from any context
+    /// (including on targets without atomic read-modify-write instructions).
+    pub fn set_vsync_counter(&mut self, counter: Option<&'static AtomicU32>) {
+        self.vsync = counter;
+    }
+
+    /// Count a completely output frame.
+    pub(crate) fn complete_frame(&mut self) {
+        self.frame_count = self.frame_count.wrapping_add(1);
+
+        self.step_fade();
+
+        if self.dithering {
+            // the dither pattern moves on every frame
+            self.mark_rows_dirty();
+        }
+
+        if let Some(vsync) = self.vsync {
+            vsync.store(self.frame_count, Ordering::Release);
+        }
+    }
+
+    /// Output the framebuffer through the provided row sink instead of the pins.
+    ///
+    /// Like [`output`](Self::output), this does nothing while the display is
+    /// [blanked](Self::blank), and only outputs every other row while
+    /// [interlaced](Self::set_interlaced).
+    ///
+    /// S-PWM driver chips (see [`ChipMode::SPwm`]) need a grayscale clock, which
+    /// row sinks don't drive, so this fails with
+    /// [`ConfigError::UnsupportedChipMode`] in that mode.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output_to<Sink: RowSink>(&mut self, sink: &mut Sink) -> Result<(), Error<Sink::Error>> {
+        self.check_pwm_mode()?;
+
+        if self.blanked {
+            return Ok(());
+        }
+
+        let field = self.field();
+
+        self.begin_field(field);
+
+        for row in field_rows(self.scan_order, field) {
+            for mask in self.ftc.masks() {
+                let packed = self.serialize_row(row, mask);
+
+                sink.send_row(
+                    self.row_map[row as usize],
+                    mask,
+                    &packed,
+                    self.show_duration(row, &mask).saturating_mul(1000),
+                )
+                .map_err(Error::Pins)?;
+            }
+        }
+
+        self.complete_field(field);
+
+        Ok(())
+    }
+
+    /// Fail unless the driver chips generate their PWM from the output enable, for
+    /// output paths which don't drive the grayscale clock of S-PWM driver chips.
+    pub(crate) fn check_pwm_mode(&self) -> Result<(), ConfigError> {
+        match self.chip_mode {
+            ChipMode::Pwm => Ok(()),
+            ChipMode::SPwm { .. } => Err(ConfigError::UnsupportedChipMode),
+        }
+    }
+
+    /// The field to output while [interlaced](Self::set_interlaced), `true` for the
+    /// odd rows.
+    pub(crate) fn field(&self) -> Option<bool> {
+        self.interlaced.then_some(self.odd_field)
+    }
+
+    /// The time (in microseconds) left of the
+    /// [minimum frame interval](Self::set_min_frame_interval_us) after outputting
+    /// `field`, to be idled before the next one.
+    pub(crate) fn idle_us(&self, field: Option<bool>) -> u32 {
+        let frame_time = match field {
+            Some(_) => self.frame_time_us() / 2,
+            None => self.frame_time_us(),
+        };
+
+        self.min_frame_interval_us.saturating_sub(frame_time)
+    }
+
+    /// Prepare the output of `field`, limiting the power of a new frame to its
+    /// framebuffer and brightness before any bit plane is shown.
+    pub(crate) fn begin_field(&mut self, field: Option<bool>) {
+        if field != Some(true) {
+            self.limit_power();
+        }
+    }
+
+    /// Switch to the other field, completing the frame once both fields were
+    /// output.
+    pub(crate) fn complete_field(&mut self, field: Option<bool>) {
+        if let Some(odd) = field {
+            self.odd_field = !odd;
+        }
+
+        if field != Some(false) {
+            self.complete_frame();
+        }
+    }
+
+    /// Pack the provided bit plane of a row pair, one byte per column (see
+    /// [`sink`] for the format).
+    ///
+    /// *`row` must be less than `SCAN` and `mask` less than `BITS`.*
+    pub fn serialize_row(&self, row: u8, mask: u8) -> [u8; WIDTH] {
+        let (upper, lower) = (row as usize, row as usize + HEIGHT / 2);
+        let (upper, lower) = if self.halves_swapped {
+            (lower, upper)
+        } else {
+            (upper, lower)
+        };
+        let (upper_row, lower_row) = (&self.frame.data[upper], &self.frame.data[lower]);
+
+        let mut packed = [0; WIDTH];
+
+        if self.channel_scale == (255, 255, 255)
+            && !self.dithering
+            && self.brightness_mask.is_none()
+            && !Pipeline::CUSTOM
+            && !self
+                .overlay
+                .as_ref()
+                .is_some_and(|layer| layer.is_visible())
+        {
+            for (column, (upper, lower)) in packed.iter_mut().zip(upper_row.iter().zip(lower_row)) {
+                *column = sink::pack::<BITS>(upper, lower, mask);
+            }
+        } else {
+            let scale = self.channel_scale;
+            // the quantization step of the shown bit planes
+            let step = if self.dithering {
+                1u16 << (8 - self.ftc.active_bits)
+            } else {
+                0
+            };
+            let adjust = |color: &(u8, u8, u8), x: usize, y: usize| {
+                let color = match self.overlay.as_ref().and_then(|layer| layer.pixel(x, y)) {
+                    Some(shown) => self.pipeline.correct(&shown),
+                    None => *color,
+                };
+                let color = self.pipeline.scale(&color, &scale);
+                let color = match self.brightness_mask {
+                    Some(attenuation) => {
+                        let attenuation = attenuation[y][x];
+
+                        scale_color(&color, &(attenuation, attenuation, attenuation))
+                    }
+                    None => color,
+                };
+                let offset =
+                    ((DITHER[(self.frame_count as usize + x + 2 * y) % 4] * step) >> 2) as u8;
+
+                (
+                    color.0.saturating_add(offset),
+                    color.1.saturating_add(offset),
+                    color.2.saturating_add(offset),
+                )
+            };
+
+            for (x, (column, (upper_color, lower_color))) in packed
+                .iter_mut()
+                .zip(upper_row.iter().zip(lower_row))
+                .enumerate()
+            {
+                *column = sink::pack::<BITS>(
+                    &adjust(upper_color, x, upper),
+                    &adjust(lower_color, x, lower),
+                    mask,
+                );
+            }
+        }
+
+        packed
+    }
+
+    /// Set a pixel to a color as is, without gamma correction or quantization.
+    pub fn set_pixel_raw(&mut self, x: u16, y: u16, rgb: (u8, u8, u8)) -> Result<(), OutOfBounds> {
+        let pixel = self
+            .frame
+            .pixel_mut(x as usize, y as usize)
+            .ok_or(OutOfBounds)?;
+        let changed = update(pixel, rgb);
+
+        if changed {
+            self.dirty_rows[y as usize % SCAN] = self.dirty_stamp;
+        }
+
+        self.mark_rows_changed(changed);
+
+        Ok(())
+    }
+
+    /// Get the stored color of a pixel, as it will be shifted out.
+    pub fn pixel_raw(&self, x: u16, y: u16) -> Result<(u8, u8, u8), OutOfBounds> {
+        self.frame
+            .pixel(x as usize, y as usize)
+            .copied()
+            .ok_or(OutOfBounds)
+    }
+
+    /// Load colors as is, without gamma correction or quantization, in row-major
+    /// order.
+    ///
+    /// If `data` is shorter than the display, only the leading pixels are written.
+    /// If it is longer, nothing is written.
+    pub fn load_raw(&mut self, data: &[(u8, u8, u8)]) -> Result<(), OutOfBounds> {
+        if data.len() > WIDTH * HEIGHT {
+            return Err(OutOfBounds);
+        }
+
+        let mut changed = false;
+
+        for (pixel, rgb) in self.frame.pixels_mut().zip(data) {
+            changed |= update(pixel, *rgb);
+        }
+
+        self.mark_changed(changed);
+
+        Ok(())
+    }
+}
+
+// DrawTarget impl
+
+use core::convert::Infallible;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+    Pixel,
+};
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Dimensions
+    for Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Draw gamma corrected pixels, for draw targets of any color type.
+    pub(crate) fn draw_corrected(
+        &mut self,
+        pixels: impl IntoIterator<Item = (Point, (u8, u8, u8))>,
+    ) {
+        let blend_mode = self.blend_mode;
+        let mut changed = false;
+
+        for (coord, color) in pixels {
+            if coord.x >= 0 && coord.y >= 0 {
+                if let Some(pixel) = self.frame.pixel_mut(coord.x as usize, coord.y as usize) {
+                    if update(pixel, blend_mode.apply(*pixel, color)) {
+                        self.dirty_rows[coord.y as usize % SCAN] = self.dirty_stamp;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        self.mark_rows_changed(changed);
+    }
+
+    /// Fill an area with a gamma corrected color, row slice by row slice.
+    pub(crate) fn fill_solid_corrected(&mut self, area: &Rectangle, color: (u8, u8, u8)) {
+        let area = area.intersection(&self.bounding_box());
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
+        };
+        let rows = area.top_left.y as usize..=bottom_right.y as usize;
+        let columns = area.top_left.x as usize..=bottom_right.x as usize;
+        let blend_mode = self.blend_mode;
+        let mut changed = false;
+
+        for y in rows {
+            let row = &mut self.frame.data[y][columns.clone()];
+            let mut row_changed = false;
+
+            if blend_mode == BlendMode::Replace {
+                row_changed = row.iter().any(|pixel| *pixel != color);
+                row.fill(color);
+            } else {
+                for pixel in row {
+                    row_changed |= update(pixel, blend_mode.apply(*pixel, color));
+                }
+            }
+
+            if row_changed {
+                self.dirty_rows[y % SCAN] = self.dirty_stamp;
+            }
+
+            changed |= row_changed;
+        }
+
+        self.mark_rows_changed(changed);
+    }
+
+    /// Fill an area with gamma corrected colors, without computing each pixel's
+    /// coordinates.
+    pub(crate) fn fill_contiguous_corrected(
+        &mut self,
+        area: &Rectangle,
+        colors: impl IntoIterator<Item = (u8, u8, u8)>,
+    ) {
+        let blend_mode = self.blend_mode;
+        let mut colors = colors.into_iter();
+        let mut changed = false;
+
+        // colors are consumed row by row over the whole area, including the parts
+        // outside of the display
+        for y in area.rows() {
+            let y = usize::try_from(y).ok();
+            let mut row = y.and_then(|y| self.frame.data.get_mut(y));
+            let mut row_changed = false;
+
+            for x in area.columns() {
+                let Some(color) = colors.next() else {
+                    break;
+                };
+
+                if let Some(pixel) = row
+                    .as_deref_mut()
+                    .and_then(|row| row.get_mut(usize::try_from(x).ok()?))
+                {
+                    row_changed |= update(pixel, blend_mode.apply(*pixel, color));
+                }
+            }
+
+            if let Some(y) = y.filter(|_| row_changed) {
+                self.dirty_rows[y % SCAN] = self.dirty_stamp;
+                changed = true;
+            }
+        }
+
+        self.mark_rows_changed(changed);
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > DrawTarget
+    for Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let pipeline = self.pipeline;
+
+        self.draw_corrected(
+            pixels
+                .into_iter()
+                .map(|Pixel(coord, color)| (coord, pipeline.correct_rgb565(color))),
+        );
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_corrected(area, self.pipeline.correct_rgb565(color));
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let pipeline = self.pipeline;
+
+        self.fill_contiguous_corrected(
+            area,
+            colors
+                .into_iter()
+                .map(|color| pipeline.correct_rgb565(color)),
+        );
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid(&self.bounding_box(), color)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{
+        hal::PinState::{High, Low},
+        test_utils::*,
+    };
+    use embedded_graphics::{pixelcolor::RgbColor, primitives::PointsIter, Drawable};
+
+    #[test]
+    fn ftc_durations_double() {
+        let ftc = FrameTimeCompensation::<4>::new(500);
+
+        for mask in 1..4 {
+            let previous = ftc.duration(&(mask - 1));
+            let current = ftc.duration(&mask);
+
+            assert!(current >= previous * 2);
+            assert!(current <= previous * 2 + 1);
+        }
+    }
+
+    #[test]
+    fn const_construction() {
+        const DISPLAY: Hub75_64_32_2<4, NoPins, NoPins, NoPins> =
+            Hub75::new_permille(NoPins, NoPins, NoPins, 900);
+
+        let display = Hub75_64_32_2::<4, _, _, _>::new(NoPins, NoPins, NoPins, 0.9);
+
+        assert_eq!(DISPLAY.ftc.h, display.ftc.h);
+        // (2 * 4 + 1) * 0.9 / 0.1
+        assert_eq!(DISPLAY.ftc.h, 81);
+    }
+
+    #[test]
+    fn ftc_active_bits_preserve_on_time() {
+        let mut ftc = FrameTimeCompensation::<6>::new(900);
+
+        let total = |ftc: &FrameTimeCompensation<6>| {
+            ftc.masks().map(|mask| ftc.duration(&mask)).sum::<u32>()
+        };
+        let full = total(&ftc);
+
+        for bits in 1..=6 {
+            ftc.active_bits = bits;
+
+            assert_eq!(ftc.masks().len(), bits as usize);
+            // each plane's duration truncates by less than 1µs
+            assert!(total(&ftc).abs_diff(full) < 6);
+        }
+    }
+
+    #[test]
+    fn global_brightness() {
+        let log = Log::default();
+        let mut display = display::<4>(&log);
+        let durations = |display: &Hub75<64, 32, 16, 4, _, _, _>| -> [u32; 4] {
+            core::array::from_fn(|mask| display.ftc.duration(&(mask as u8)))
+        };
+        let full = durations(&display);
+
+        assert_eq!(display.brightness(), 255);
+
+        display.set_brightness(51);
+
+        assert_eq!(durations(&display), full.map(|duration| duration / 5));
+
+        display.set_brightness(0);
+
+        assert!(display
+            .ftc
+            .masks()
+            .all(|mask| display.ftc.duration(&mask) == 0));
+    }
+
+    #[test]
+    fn interlaced_fields() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.set_interlaced(true);
+
+        for odd in [0, 1, 0] {
+            log.borrow_mut().clear();
+            display.output(&mut ProbeDelay(&log)).unwrap();
+
+            assert!(latched_rows(&log).into_iter().eq((odd..16).step_by(2)));
+        }
+
+        assert_eq!(display.frame_count(), 1);
+    }
+
+    #[test]
+    fn row_brightness() {
+        let log = Log::default();
+        let mut display = display::<4>(&log);
+        let mut scale = [255; 16];
+
+        scale[0] = 127;
+        scale[15] = 0;
+        display.set_row_brightness(&scale);
+        display.set_latch_guard_us(0);
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let top = display.ftc.duration(&3);
+
+        assert_eq!(display.show_duration(0, &3), top * 127 / 255);
+        assert_eq!(display.show_duration(1, &3), top);
+        assert_eq!(display.show_duration(15, &3), 0);
+
+        // pulse delays are 1µs
+        let expected = (0..16)
+            .flat_map(|row| (0..4).map(move |mask| (row, mask)))
+            .map(|(row, mask)| display.show_duration(row, &mask))
+            .filter(|us| *us > 1);
+        let log = log.borrow();
+        let shown = log.iter().filter_map(|event| match event {
+            Event::Delay(us) if *us > 1 => Some(*us),
+            _ => None,
+        });
+
+        assert!(shown.eq(expected));
+    }
+
+    #[test]
+    fn brightness_mask() {
+        use sink::{B1, B2, G1, G2, R1, R2};
+
+        static MASK: [[u8; 64]; 32] = {
+            let mut mask = [[255; 64]; 32];
+
+            mask[0][1] = 127;
+            mask[16][0] = 0;
+            mask
+        };
+
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        for (x, y) in [(0, 0), (1, 0), (0, 16)] {
+            display.set_pixel_raw(x, y, (255, 255, 255)).unwrap();
+        }
+
+        display.set_brightness_mask(Some(&MASK));
+
+        assert_eq!(display.serialize_row(0, 0)[..2], [R1 | G1 | B1, 0],);
+
+        display.set_brightness_mask(None);
+
+        assert_eq!(
+            display.serialize_row(0, 0)[..2],
+            [R1 | G1 | B1 | R2 | G2 | B2, R1 | G1 | B1],
+        );
+    }
+
+    #[test]
+    fn min_show_duration() {
+        let log = Log::default();
+        let mut display = display::<4>(&log);
+
+        let top = display.ftc.duration(&3);
+
+        display.set_brightness(64);
+
+        assert_eq!(display.ftc.duration(&0), 0);
+
+        display.set_min_show_us(2);
+
+        assert_eq!(display.ftc.duration(&0), 2);
+
+        display.set_brightness(255);
+
+        // longer planes are unaffected
+        assert_eq!(display.ftc.duration(&3), top);
+
+        display.set_brightness(0);
+
+        assert_eq!(display.ftc.duration(&0), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "on_ratio must be in 0..1000")]
+    fn ftc_ratio_out_of_range() {
+        FrameTimeCompensation::<8>::new(1000);
+    }
+
+    #[test]
+    fn borrowed_buffer() {
+        let log = Log::default();
+        let buffer = std::boxed::Box::leak(std::boxed::Box::new(FrameBuffer::new()));
+        let address = buffer as *const FrameBuffer<64, 32>;
+        let mut display = Hub75_64_32_2::<8, _, _, _, _>::new_with_buffer(
+            buffer,
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        Pixel(Point::new(1, 2), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+
+        assert!(core::ptr::eq(display.frame(), address));
+        assert_eq!(display.frame().pixel(1, 2), Some(&(255, 255, 255)));
+        // only a reference to the buffer is held
+        assert!(core::mem::size_of_val(&display) < core::mem::size_of::<FrameBuffer<64, 32>>());
+
+        display.wipe();
+
+        assert!(core::ptr::eq(display.frame(), address));
+        assert_eq!(display.frame().pixel(1, 2), Some(&(0, 0, 0)));
+    }
+
+    /// A row sink recording the row and bit plane of every sent row.
+    #[derive(Default)]
+    struct RecordingSink(std::vec::Vec<(u8, u8)>);
+
+    impl RowSink for RecordingSink {
+        type Error = core::convert::Infallible;
+
+        fn send_row(
+            &mut self,
+            row: u8,
+            mask: u8,
+            _packed: &[u8],
+            _show_ns: u32,
+        ) -> Result<(), Self::Error> {
+            self.0.push((row, mask));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn blanked_sink_output() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+        let mut sink = RecordingSink::default();
+
+        display.blank().unwrap();
+        display.output_to(&mut sink).unwrap();
+
+        assert!(sink.0.is_empty());
+        assert_eq!(display.frame_count(), 0);
+
+        display.unblank();
+        display.output_to(&mut sink).unwrap();
+
+        assert_eq!(sink.0.len(), 16 * 2);
+        assert_eq!(display.frame_count(), 1);
+    }
+
+    #[test]
+    fn interlaced_sink_output() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.set_interlaced(true);
+
+        for odd in [0, 1, 0] {
+            let mut sink = RecordingSink::default();
+
+            display.output_to(&mut sink).unwrap();
+
+            assert!(sink.0.iter().map(|(row, _)| *row).eq((odd..16).step_by(2)));
+        }
+
+        assert_eq!(display.frame_count(), 1);
+    }
+
+    #[test]
+    fn spwm_unsupported_paths() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut sink = RecordingSink::default();
+        let unsupported = Error::Config(ConfigError::UnsupportedChipMode);
+
+        display.set_chip_mode(ChipMode::SPwm { gclk_per_row: 1 });
+
+        assert_eq!(display.output_to(&mut sink), Err(unsupported));
+        assert_eq!(display.output_step(&mut ProbeDelay(&log)), Err(unsupported));
+        assert_eq!(display.output_row(&mut ProbeDelay(&log)), Err(unsupported));
+        assert_eq!(
+            display.output_bounded(&mut ProbeDelay(&log), 1000),
+            Err(unsupported)
+        );
+        assert_eq!(
+            scan::IsrStepper::new().tick(&mut display, &mut ProbeDelay(&log)),
+            Err(unsupported)
+        );
+
+        assert!(sink.0.is_empty());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn frame_pacing() {
+        let plain = Log::default();
+
+        display::<2>(&plain)
+            .output(&mut ProbeDelay(&plain))
+            .unwrap();
+
+        let paced = |interval| {
+            let log = Log::default();
+            let mut display = display::<2>(&log);
+
+            display.set_min_frame_interval_us(interval);
+
+            let idle = display.output(&mut ProbeDelay(&log)).unwrap();
+            let frame_time = display.frame_time_us();
+
+            (idle, frame_time, log.into_inner())
+        };
+
+        // disabled by default
+        let (idle, frame_time, log) = paced(0);
+
+        assert_eq!(idle, 0);
+        assert_eq!(log, *plain.borrow());
+
+        // the remaining time is idled at the end of the frame
+        let (idle, _, log) = paced(frame_time + 500);
+
+        assert_eq!(idle, 500);
+        assert_eq!(log.last(), Some(&Event::Delay(500)));
+        assert_eq!(log[..log.len() - 1], plain.borrow()[..]);
+
+        // frames longer than the interval are not delayed
+        let (idle, _, log) = paced(frame_time / 2);
+
+        assert_eq!(idle, 0);
+        assert_eq!(log, *plain.borrow());
+    }
+
+    #[test]
+    fn active_bits_range() {
+        let log = Log::default();
+        let mut display = display::<4>(&log);
+
+        assert_eq!(
+            display.set_active_bits(0),
+            Err(ConfigError::ActiveBitsOutOfRange)
+        );
+        assert_eq!(
+            display.set_active_bits(5),
+            Err(ConfigError::ActiveBitsOutOfRange)
+        );
+        assert_eq!(display.active_bits(), 4);
+        assert_eq!(display.set_active_bits(2), Ok(()));
+        assert_eq!(display.active_bits(), 2);
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log).len(), 64 * 16 * 2);
+    }
+
+    #[test]
+    fn draw_applies_gamma() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        Pixel(Point::new(3, 2), Rgb565::new(31, 0, 15))
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(5, 20), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(64, 0), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.frame.data[2][3], (255, 0, GAMMA8[127]));
+        assert_eq!(display.frame.data[20][5], (255, 255, 255));
+        assert_eq!(
+            display.frame.data[..16]
+                .iter()
+                .flatten()
+                .filter(|c| **c != (0, 0, 0))
+                .count(),
+            1
+        );
+
+        display.wipe();
+
+        assert!(display.frame.pixels().all(|c| *c == (0, 0, 0)));
+    }
+
+    #[test]
+    fn blend_in_stored_space() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        display.set_blend_mode(BlendMode::Add);
+
+        // half-intensity red is gamma corrected before being added
+        let half = Rgb565::new(15, 0, 0);
+
+        Pixel(Point::zero(), half).draw(&mut display).unwrap();
+        Pixel(Point::zero(), half).draw(&mut display).unwrap();
+        assert_eq!(display.frame.data[0][0], (GAMMA8[127] * 2, 0, 0));
+
+        for _ in 0..16 {
+            Pixel(Point::zero(), half).draw(&mut display).unwrap();
+        }
+        assert_eq!(display.frame.data[0][0], (255, 0, 0));
+
+        let generation = display.generation();
+        Pixel(Point::zero(), Rgb565::BLACK)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.frame.data[0][0], (255, 0, 0));
+        assert_eq!(display.generation(), generation);
+    }
+
+    #[test]
+    fn raw_pixels() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        assert_eq!(display.set_pixel_raw(1, 17, (1, 2, 3)), Ok(()));
+        assert_eq!(display.pixel_raw(1, 17), Ok((1, 2, 3)));
+        assert_eq!(display.frame.data[17][1], (1, 2, 3));
+
+        assert_eq!(display.set_pixel_raw(64, 0, (1, 2, 3)), Err(OutOfBounds));
+        assert_eq!(display.pixel_raw(0, 32), Err(OutOfBounds));
+
+        assert_eq!(display.load_raw(&[(9, 9, 9); 65]), Ok(()));
+        assert_eq!(display.pixel_raw(0, 1), Ok((9, 9, 9)));
+        assert_eq!(display.pixel_raw(1, 1), Ok((0, 0, 0)));
+        assert_eq!(
+            display.load_raw(&[(9, 9, 9); 64 * 32 + 1]),
+            Err(OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn generation() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        display.wipe();
+        assert_eq!(display.generation(), 0);
+
+        Pixel(Point::zero(), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.generation(), 1);
+
+        Pixel(Point::zero(), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(64, 0), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.generation(), 1);
+
+        display.wipe();
+        assert_eq!(display.generation(), 2);
+    }
+
+    #[test]
+    fn row_pair() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        display.set_row_pair(3, &[(127, 0, 255); 64], &[(1, 2, 3); 64], false);
+        assert_eq!(
+            display.frame.pixel_mut(10, 3).copied(),
+            Some((GAMMA8[127], 0, 255))
+        );
+        assert_eq!(display.frame.pixel_mut(10, 19).copied(), Some((0, 0, 0)));
+
+        display.set_row_pair(3, &[(127, 0, 255); 64], &[(1, 2, 3); 64], true);
+        assert_eq!(display.row_pair(3).0[0], (127, 0, 255));
+        assert_eq!(display.row_pair(3).1[63], (1, 2, 3));
+    }
+
+    #[test]
+    fn output_shifts_halves() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        Pixel(Point::zero(), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(0, 16), Rgb565::BLUE)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 64 * 16);
+        assert_eq!(shifted[0], [High, Low, Low, Low, Low, High]);
+        assert_eq!(shifted[1], [Low; 6]);
+    }
+
+    #[test]
+    fn output_64_64() {
+        let log = Log::default();
+        let mut display = Hub75_64_64_2::<1, _, _, _>::new(
+            color_pins(&log),
+            row_pins_e(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        Pixel(Point::new(0, 63), Rgb565::GREEN)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 64 * 32);
+        assert_eq!(shifted[64 * 31], [Low, Low, Low, Low, High, Low]);
+        assert_eq!(
+            log.borrow()
+                .iter()
+                .filter(|event| **event == Event::Pin(PinId::E, High))
+                .count(),
+            16
+        );
+    }
+
+    #[test]
+    fn output_128_64() {
+        let log = Log::default();
+        let mut display = Hub75_128_64_2::<1, _, _, _>::new(
+            color_pins(&log),
+            row_pins_e(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        assert_eq!(display.bounding_box().size, Size::new(128, 64));
+
+        Pixel(Point::new(127, 63), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 128 * 32);
+        assert_eq!(shifted[128 * 32 - 1], [Low, Low, Low, High, Low, Low]);
+    }
+
+    #[test]
+    fn output_32_32() {
+        let log = Log::default();
+        let mut display = Hub75_32_32_2::<1, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        assert_eq!(display.bounding_box().size, Size::new(32, 32));
+
+        Pixel(Point::new(31, 16), Rgb565::BLUE)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(32, 0), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 32 * 16);
+        assert_eq!(shifted[31], [Low, Low, Low, Low, Low, High]);
+        assert!(shifted.iter().filter(|s| s.contains(&High)).count() == 1);
+    }
+
+    #[test]
+    fn output_32_16() {
+        let log = Log::default();
+        let (a, b, c, _) = row_pins(&log);
+        let mut display =
+            Hub75_32_16_2::<1, _, _, _>::new(color_pins(&log), (a, b, c), data_pins(&log), 0.5);
+
+        assert_eq!(display.bounding_box().size, Size::new(32, 16));
+
+        Pixel(Point::new(0, 15), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 32 * 8);
+        assert_eq!(shifted[32 * 7], [Low, Low, Low, High, Low, Low]);
+        assert!(!log
+            .borrow()
+            .iter()
+            .any(|event| matches!(event, Event::Pin(PinId::D, _))));
+    }
+
+    #[test]
+    fn single_color_pins() {
+        let log = Log::default();
+        let ((r1, ..), (r2, ..)) = color_pins(&log);
+        let mut display =
+            Hub75_64_32_2::<1, _, _, _>::new(((r1,), (r2,)), row_pins(&log), data_pins(&log), 0.5);
+
+        Pixel(Point::new(0, 0), Rgb565::GREEN)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted[0], [High, Low, Low, Low, Low, Low]);
+        assert_eq!(shifted[1], [Low; 6]);
+    }
+
+    #[test]
+    fn six_color_pins() {
+        let reference = Log::default();
+        let log = Log::default();
+        let ((r1, g1, b1), (r2, g2, b2)) = color_pins(&log);
+        let mut pairs = display::<3>(&reference);
+        let mut six = Hub75_64_32_2::<3, _, _, _>::new(
+            (r1, g1, b1, r2, g2, b2),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        for (point, color) in [((4, 2), Rgb565::YELLOW), ((60, 30), Rgb565::MAGENTA)] {
+            Pixel(Point::from(point), color).draw(&mut pairs).unwrap();
+            Pixel(Point::from(point), color).draw(&mut six).unwrap();
+        }
+
+        pairs.output(&mut ProbeDelay(&reference)).unwrap();
+        six.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(*log.borrow(), *reference.borrow());
+    }
+
+    #[test]
+    fn color_bus() {
+        let reference = Log::default();
+        let log = Log::default();
+        let ((r1, g1, b1), (r2, g2, b2)) = color_pins(&log);
+        let mut pins = display::<3>(&reference);
+        let mut bus = Hub75_64_32_2::<3, _, _, _>::new(
+            ColorBus((r1, g1, b1, r2, g2, b2)),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        for (point, color) in [((4, 2), Rgb565::YELLOW), ((60, 30), Rgb565::MAGENTA)] {
+            Pixel(Point::from(point), color).draw(&mut pins).unwrap();
+            Pixel(Point::from(point), color).draw(&mut bus).unwrap();
+        }
+
+        pins.output(&mut ProbeDelay(&reference)).unwrap();
+        bus.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log), shifted_colors(&reference));
+    }
+
+    #[test]
+    fn decoder_rows() {
+        let log = Log::default();
+        let (a, b, c, d) = row_pins(&log);
+        let mut display = Hub75_32_16_2::<1, _, _, _>::new(
+            color_pins(&log),
+            DecoderRowPins::new(a, b, c, d),
+            data_pins(&log),
+            0.5,
+        );
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let mut enabled = false;
+
+        for event in log.borrow().iter() {
+            match event {
+                Event::Pin(PinId::D, state) => enabled = *state == Low,
+                Event::Pin(PinId::A | PinId::B | PinId::C, _) => assert!(!enabled),
+                Event::Pin(PinId::Oe, Low) => assert!(enabled),
+                _ => {}
+            }
+        }
+
+        assert_eq!(latched_rows(&log), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn row_map() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut map = identity_row_map::<16>();
+
+        map.swap(0, 3);
+        display.set_row_map(map);
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(latched_rows(&log), map);
+    }
+
+    #[test]
+    fn shift_token_rows() {
+        let log = Log::default();
+        let (a, b, ..) = row_pins(&log);
+        let mut display = Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&log),
+            ShiftTokenRowPins::<_, _, 16>::new(a, b),
+            data_pins(&log),
+            0.5,
+        );
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        // replay the row driver chain, checking the selected output at every latch
+        let mut data = Low;
+        let mut chain = [High; 16];
+        let mut latches = 0;
+
+        for event in log.borrow().iter() {
+            match event {
+                Event::Pin(PinId::A, state) => data = *state,
+                Event::Pin(PinId::B, High) => {
+                    chain.rotate_right(1);
+                    chain[0] = data;
+                }
+                Event::Pin(PinId::Lat, High) => {
+                    let selected = chain.iter().filter(|output| **output == Low).count();
+                    let row = chain.iter().position(|output| *output == Low);
+
+                    assert_eq!(selected, 1);
+                    assert_eq!(row, Some(latches / 2 % 16));
+
+                    latches += 1;
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(latches, 2 * 16 * 2);
+    }
+
+    #[test]
+    fn long_chain_frame_time() {
+        let log = Log::default();
+        let short = Hub75::<64, 32, 16, 4, _, _, _>::new(NoPins, NoPins, data_pins(&log), 0.5);
+        let long = Hub75::<512, 32, 16, 4, _, _, _>::new(NoPins, NoPins, data_pins(&log), 0.5);
+
+        // 448 more columns, shifted for each of the 4 bit planes of the 16 rows
+        assert_eq!(
+            long.frame_time_us() - short.frame_time_us(),
+            448 * 2 * 4 * 16
+        );
+        assert_eq!(long.refresh_rate_hz(), 1_000_000 / long.frame_time_us());
+        assert!(long.refresh_rate_hz() < 30);
+
+        let undelayed =
+            Hub75::<512, 32, 16, 4, _, _, _>::new(NoPins, NoPins, Undelayed(data_pins(&log)), 0.5);
+
+        // neither the 2µs per column nor the 1µs latch of the standard data pins
+        assert_eq!(
+            long.frame_time_us() - undelayed.frame_time_us(),
+            (512 * 2 + 1) * 4 * 16
+        );
+    }
+
+    #[test]
+    fn color_temperature() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.set_color_temperature((255, 127, 0));
+        Pixel(Point::zero(), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log)[0], [High, Low, Low, Low, Low, Low]);
+        assert_eq!(display.frame.data[0][0], (255, 255, 255));
+        assert_eq!(scale_color(&(200, 255, 255), &(255, 127, 0)), (200, 127, 0));
+    }
+
+    #[test]
+    fn white_balance() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.set_white_balance((255, 127, 255));
+        display.set_color_temperature((255, 255, 127));
+        Pixel(Point::zero(), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log)[0], [High, Low, Low, Low, Low, Low]);
+        assert_eq!(display.channel_scale, (255, 127, 127));
+
+        display.set_color_temperature((255, 255, 255));
+
+        assert_eq!(display.channel_scale, (255, 127, 255));
+    }
+
+    #[test]
+    fn temporal_dithering() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let shown = |display: &mut Hub75<64, 32, 16, 1, _, _, _>| {
+            (0..4)
+                .filter(|_| {
+                    log.borrow_mut().clear();
+                    display.output(&mut ProbeDelay(&log)).unwrap();
+
+                    shifted_colors(&log)[0][0] == High
+                })
+                .count()
+        };
+
+        display.set_pixel_raw(0, 0, (64, 0, 0)).unwrap();
+
+        assert_eq!(shown(&mut display), 0);
+
+        display.set_dithering(true);
+
+        assert_eq!(shown(&mut display), 2);
+
+        display.set_pixel_raw(0, 0, (255, 0, 0)).unwrap();
+
+        assert_eq!(shown(&mut display), 4);
+    }
+
+    #[test]
+    fn latch_guard() {
+        for guard in [0, 3] {
+            let log = Log::default();
+            let (clk, lat, oe) = data_pins(&log);
+            let mut display = Hub75_64_32_2::<4, _, _, _>::new(
+                color_pins(&log),
+                row_pins(&log),
+                Undelayed((clk, lat, oe)),
+                0.5,
+            );
+
+            display.set_latch_guard_us(guard);
+            display.output(&mut ProbeDelay(&log)).unwrap();
+
+            let mut since_disable = None;
+            let mut transitions = 0;
+
+            for event in log.borrow().iter() {
+                match event {
+                    Event::Pin(PinId::Oe, High) => since_disable = Some(0),
+                    Event::Delay(us) => {
+                        if let Some(since) = &mut since_disable {
+                            *since += us;
+                        }
+                    }
+                    Event::Pin(PinId::Lat, High) => {
+                        if let Some(since) = since_disable.take() {
+                            assert_eq!(since, guard);
+                            transitions += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            assert_eq!(transitions, 16 * 4 - 1);
+        }
+    }
+
+    /// Delays directly following a rising clock or latch edge, in the order they
+    /// were logged.
+    fn pulse_delays(log: &Log) -> std::vec::Vec<u32> {
+        log.borrow()
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [Event::Pin(PinId::Clk | PinId::Lat, High), Event::Delay(us)] => Some(*us),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pulse_timing() {
+        let timed = |timing| {
+            let log = Log::default();
+            let (clk, lat, oe) = data_pins(&log);
+
+            Hub75_64_32_2::<2, _, _, _>::new(
+                color_pins(&log),
+                row_pins(&log),
+                TimedDataPins::new(clk, lat, oe, timing),
+                0.5,
+            )
+            .output(&mut ProbeDelay(&log))
+            .unwrap();
+
+            log
+        };
+
+        let standard = Log::default();
+
+        display::<2>(&standard)
+            .output(&mut ProbeDelay(&standard))
+            .unwrap();
+
+        assert_eq!(*timed(Timing::STANDARD).borrow(), *standard.borrow());
+
+        let slow = timed(Timing {
+            clock_us: 3,
+            latch_us: 5,
+        });
+        let delays = pulse_delays(&slow);
+
+        assert_eq!(delays.iter().filter(|us| **us == 3).count(), 64 * 16 * 2);
+        assert_eq!(delays.iter().filter(|us| **us == 5).count(), 16 * 2);
+        assert_eq!(latched_rows(&slow), latched_rows(&standard));
+    }
+
+    #[test]
+    fn undelayed() {
+        let standard = Log::default();
+        let undelayed = Log::default();
+
+        display::<2>(&standard)
+            .output(&mut ProbeDelay(&standard))
+            .unwrap();
+        Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&undelayed),
+            row_pins(&undelayed),
+            Undelayed(data_pins(&undelayed)),
+            0.5,
+        )
+        .output(&mut ProbeDelay(&undelayed))
+        .unwrap();
+
+        assert!(pulse_delays(&undelayed).is_empty());
+        assert_eq!(latched_rows(&undelayed), latched_rows(&standard));
+        assert_eq!(shifted_colors(&undelayed), shifted_colors(&standard));
+    }
+
+    #[test]
+    fn cached_pins() {
+        let plain = Log::default();
+        let cached = Log::default();
+        let mut reference = display::<2>(&plain);
+        let ((r1, g1, b1), (r2, g2, b2)) = color_pins(&cached);
+        let mut display = Hub75_64_32_2::<2, _, _, _>::new(
+            (
+                (CachedPin::new(r1), CachedPin::new(g1), CachedPin::new(b1)),
+                (CachedPin::new(r2), CachedPin::new(g2), CachedPin::new(b2)),
+            ),
+            row_pins(&cached),
+            data_pins(&cached),
+            0.5,
+        );
+        let area = Rectangle::new(Point::zero(), Size::new(20, 40));
+
+        display.fill_solid(&area, Rgb565::RED).unwrap();
+        reference.fill_solid(&area, Rgb565::RED).unwrap();
+        display.output(&mut ProbeDelay(&cached)).unwrap();
+        reference.output(&mut ProbeDelay(&plain)).unwrap();
+
+        assert_eq!(shifted_colors(&cached), shifted_colors(&plain));
+        assert!(cached.borrow().len() * 2 < plain.borrow().len());
+    }
+
+    #[test]
+    fn resumable_output() {
+        let full = Log::default();
+        let stepped = Log::default();
+        let rows = Log::default();
+
+        display::<2>(&full).output(&mut ProbeDelay(&full)).unwrap();
+
+        let mut display_stepped = display::<2>(&stepped);
+        let mut steps = 1;
+
+        while display_stepped
+            .output_step(&mut ProbeDelay(&stepped))
+            .unwrap()
+            != scan::Progress::FrameComplete
+        {
+            steps += 1;
+        }
+
+        let mut display_rows = display::<2>(&rows);
+
+        for row in 1..16 {
+            assert_eq!(
+                display_rows.output_row(&mut ProbeDelay(&rows)).unwrap(),
+                scan::Progress::Partial {
+                    next_row: row,
+                    next_mask: 0
+                }
+            );
+        }
+
+        assert_eq!(
+            display_rows.output_row(&mut ProbeDelay(&rows)).unwrap(),
+            scan::Progress::FrameComplete
+        );
+        assert_eq!(steps, 16 * 2);
+
+        for log in [&stepped, &rows] {
+            assert_eq!(shifted_colors(log), shifted_colors(&full));
+            assert_eq!(latched_rows(log), latched_rows(&full));
+        }
+    }
+
+    #[test]
+    fn isr_stepper() {
+        let full = Log::default();
+        let ticked = Log::default();
+        let mut reference = display::<2>(&full);
+        let mut display = display::<2>(&ticked);
+        let mut stepper = scan::IsrStepper::new();
+        let mut shown = 0;
+
+        reference.output(&mut ProbeDelay(&full)).unwrap();
+
+        for n in 1..=16 * 2 {
+            let tick = stepper
+                .tick(&mut display, &mut ProbeDelay(&ticked))
+                .unwrap();
+
+            shown += tick.show_us;
+            assert_eq!(tick.frame_complete, n == 16 * 2);
+        }
+
+        // the first pair of the next frame is already shifted in
+        let shifted = shifted_colors(&ticked);
+
+        assert_eq!(shifted[..shifted.len() - 64], shifted_colors(&full)[..]);
+        assert_eq!(latched_rows(&ticked), latched_rows(&full));
+        assert_eq!(
+            shown,
+            reference
+                .ftc
+                .masks()
+                .map(|mask| reference.ftc.duration(&mask))
+                .sum::<u32>()
+                * 16
+        );
+    }
+
+    #[test]
+    fn fill_fast_paths() {
+        let log = Log::default();
+        let mut fast = display::<4>(&log);
+        let mut slow = display::<4>(&log);
+        let area = Rectangle::new(Point::new(-3, 20), Size::new(10, 20));
+        let colors = |n: i32| (0..n).map(|i| Rgb565::new(i as u8 % 32, 0, 31));
+
+        for blend_mode in [BlendMode::Replace, BlendMode::Add] {
+            fast.set_blend_mode(blend_mode);
+            slow.set_blend_mode(blend_mode);
+
+            fast.fill_solid(&area, Rgb565::GREEN).unwrap();
+            slow.draw_iter(area.points().map(|point| Pixel(point, Rgb565::GREEN)))
+                .unwrap();
+            fast.fill_contiguous(&area, colors(150)).unwrap();
+            slow.draw_iter(
+                area.points()
+                    .zip(colors(150))
+                    .map(|(point, color)| Pixel(point, color)),
+            )
+            .unwrap();
+
+            assert_eq!(fast.frame.data, slow.frame.data);
+        }
+
+        let generation = fast.generation;
+
+        fast.set_blend_mode(BlendMode::Replace);
+        slow.set_blend_mode(BlendMode::Replace);
+        fast.clear(Rgb565::BLACK).unwrap();
+        slow.clear(Rgb565::BLACK).unwrap();
+
+        assert_eq!(fast.frame.data, slow.frame.data);
+        assert_ne!(fast.generation, generation);
+
+        let generation = fast.generation;
+
+        fast.clear(Rgb565::BLACK).unwrap();
+
+        assert_eq!(fast.generation, generation);
+    }
+
+    #[test]
+    fn pwm_output_enable() {
+        use hal::{OutputPin, PwmChannel};
+
+        /// A channel whose pulses run until polled as complete.
+        struct Pwm<'a> {
+            oe: Probe<'a>,
+            log: &'a Log,
+            duty: &'a core::cell::Cell<u16>,
+            pulsing: bool,
+        }
+
+        impl PwmChannel for Pwm<'_> {
+            type Error = Infallible;
+
+            fn max_duty(&self) -> u16 {
+                1000
+            }
+
+            fn set_duty(&mut self, duty: u16) -> Result<(), Infallible> {
+                self.duty.set(self.duty.get().max(duty % 1000));
+                self.oe.set_state(if duty == 1000 { High } else { Low })
+            }
+        }
+
+        impl PwmPulse for Pwm<'_> {
+            fn start_pulse(&mut self, duty: u16, us: u32) -> Result<(), Infallible> {
+                self.set_duty(duty)?;
+                self.log.borrow_mut().push(Event::Delay(us));
+                self.pulsing = true;
+
+                Ok(())
+            }
+
+            fn is_pulse_done(&mut self) -> bool {
+                if core::mem::take(&mut self.pulsing) {
+                    let Ok(()) = self.oe.set_high();
+                }
+
+                true
+            }
+        }
+
+        let plain = Log::default();
+        let pwm = Log::default();
+        let duty = core::cell::Cell::new(0);
+
+        display::<2>(&plain)
+            .output(&mut ProbeDelay(&plain))
+            .unwrap();
+
+        let (clk, lat, oe) = data_pins(&pwm);
+        let mut display = Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&pwm),
+            row_pins(&pwm),
+            PwmDataPins::new(
+                clk,
+                lat,
+                Pwm {
+                    oe,
+                    log: &pwm,
+                    duty: &duty,
+                    pulsing: false,
+                },
+            ),
+            0.5,
+        );
+
+        display.output(&mut ProbeDelay(&pwm)).unwrap();
+
+        assert_eq!(shifted_colors(&pwm), shifted_colors(&plain));
+        assert_eq!(latched_rows(&pwm), latched_rows(&plain));
+        assert_eq!(duty.get(), 0);
+
+        {
+            // the next bit plane is shifted while the pulse runs
+            let events = pwm.borrow();
+            let shown = events
+                .iter()
+                .position(|event| *event == Event::Pin(PinId::Oe, Low))
+                .unwrap();
+            let dark = events[shown..]
+                .iter()
+                .position(|event| *event == Event::Pin(PinId::Oe, High))
+                .unwrap();
+
+            assert!(events[shown..shown + dark].contains(&Event::Pin(PinId::Clk, High)));
+        }
+
+        display.data_pins.set_brightness(51);
+        display.output(&mut ProbeDelay(&pwm)).unwrap();
+
+        assert_eq!(duty.get(), 800);
+    }
+
+    #[test]
+    fn interleaved_scan() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        assert_eq!(
+            scan::scan_order::<6>(ScanOrder::Interleaved),
+            [0, 4, 2, 1, 5, 3]
+        );
+
+        display.set_scan_order(ScanOrder::Interleaved);
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(
+            latched_rows(&log),
+            [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15]
+        );
+    }
+
+    #[test]
+    fn vsync_counter() {
+        static VSYNC: AtomicU32 = AtomicU32::new(0);
+
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+
+        display.set_vsync_counter(Some(&VSYNC));
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(display.frame_count(), 1);
+        assert_eq!(VSYNC.load(Ordering::Acquire), 1);
+
+        for _ in 0..16 * 2 - 1 {
+            display.output_step(&mut ProbeDelay(&log)).unwrap();
+        }
+
+        assert_eq!(VSYNC.load(Ordering::Acquire), 1);
+
+        display.output_step(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(VSYNC.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn bounded_resume() {
+        let full = Log::default();
+        let log = Log::default();
+        let draw = |display: &mut ProbeDisplay<2>| {
+            for (i, color) in [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE, Rgb565::WHITE]
+                .into_iter()
+                .enumerate()
+            {
+                Pixel(Point::new(i as i32 * 3, i as i32 * 9), color)
+                    .draw(display)
+                    .unwrap();
+            }
+        };
+
+        let mut reference = display::<2>(&full);
+
+        draw(&mut reference);
+        reference.output(&mut ProbeDelay(&full)).unwrap();
+
+        let mut display = display::<2>(&log);
+        let pair = |mask| reference.ftc.duration(&mask) + reference.plane_overhead_us();
+
+        draw(&mut display);
+
+        // fits a few pairs at a time, splitting rows at varying bit planes
+        for (call, budget) in [0, pair(1), pair(0) + pair(1) + pair(0)]
+            .into_iter()
+            .cycle()
+            .enumerate()
+        {
+            assert!(call < 16 * 2);
+
+            if display
+                .output_bounded(&mut ProbeDelay(&log), budget)
+                .unwrap()
+                == scan::Progress::FrameComplete
+            {
+                assert!(call > 1);
+
+                break;
+            }
+        }
+
+        assert_eq!(display.frame_count(), 1);
+        assert_eq!(shifted_colors(&log), shifted_colors(&full));
+        assert_eq!(latched_rows(&log), latched_rows(&full));
+    }
+
+    #[test]
+    fn deadline_output() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+        let elapsed = || {
+            log.borrow()
+                .iter()
+                .map(|event| match event {
+                    Event::Delay(us) => *us,
+                    _ => 0,
+                })
+                .sum::<u32>()
+        };
+        let pair = |mask| display.ftc.duration(&mask) + display.plane_overhead_us();
+        // the first pair, and the second one just fitting
+        let deadline = pair(0) + pair(1);
+
+        assert_eq!(
+            display
+                .output_with_deadline(&mut ProbeDelay(&log), elapsed, deadline)
+                .unwrap(),
+            scan::Progress::Partial {
+                next_row: 1,
+                next_mask: 0
+            }
+        );
+        // already past the deadline
+        assert_eq!(
+            display
+                .output_with_deadline(&mut ProbeDelay(&log), elapsed, 0)
+                .unwrap(),
+            scan::Progress::Partial {
+                next_row: 1,
+                next_mask: 1
+            }
+        );
+    }
+
+    #[test]
+    fn interlaced_steps() {
+        let whole = Log::default();
+        let stepped = Log::default();
+        let mut reference = display::<2>(&whole);
+        let mut display = display::<2>(&stepped);
+
+        for display in [&mut reference, &mut display] {
+            display.set_scan_order(ScanOrder::Interleaved);
+            display.set_interlaced(true);
+        }
+
+        reference.output(&mut ProbeDelay(&whole)).unwrap();
+        reference.output(&mut ProbeDelay(&whole)).unwrap();
+
+        let mut next_rows = std::vec::Vec::new();
+
+        while let scan::Progress::Partial { next_row, .. } =
+            display.output_step(&mut ProbeDelay(&stepped)).unwrap()
+        {
+            next_rows.push(next_row);
+        }
+
+        let latched = latched_rows(&stepped);
+
+        // both fields, each row reported before it is output
+        assert_eq!(latched, latched_rows(&whole));
+        assert_eq!(next_rows, latched[1..]);
+        assert_eq!(display.frame_count(), 1);
+        assert_eq!(reference.frame_count(), 1);
+    }
+
+    #[test]
+    fn blank_resume() {
+        let full = Log::default();
+        let log = Log::default();
+
+        display::<2>(&full).output(&mut ProbeDelay(&full)).unwrap();
+
+        let mut display = display::<2>(&log);
+
+        for _ in 0..5 {
+            display.output_step(&mut ProbeDelay(&log)).unwrap();
+        }
+
+        display.blank().unwrap();
+        assert!(display.is_blanked());
+        let parked = log.borrow().len();
+
+        assert_eq!(display.output(&mut ProbeDelay(&log)).unwrap(), 0);
+        assert_eq!(
+            display.output_step(&mut ProbeDelay(&log)).unwrap(),
+            scan::Progress::Partial {
+                next_row: 2,
+                next_mask: 1
+            }
+        );
+        assert_eq!(log.borrow()[parked - 1], Event::Pin(PinId::Oe, High));
+        assert_eq!(log.borrow().len(), parked);
+
+        display.unblank();
+
+        while display.output_step(&mut ProbeDelay(&log)).unwrap() != scan::Progress::FrameComplete {
+        }
+
+        assert_eq!(shifted_colors(&log), shifted_colors(&full));
+        assert_eq!(latched_rows(&log), latched_rows(&full));
+    }
+
+    #[test]
+    fn polarity() {
+        let plain = Log::default();
+        let inverted = Log::default();
+        let polarity = Polarity {
+            output_enable_active_high: true,
+            latch_active_low: true,
+        };
+
+        display::<2>(&plain)
+            .output(&mut ProbeDelay(&plain))
+            .unwrap();
+        Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&inverted),
+            row_pins(&inverted),
+            PolarizedDataPins::new(data_pins(&inverted), polarity),
+            0.5,
+        )
+        .output(&mut ProbeDelay(&inverted))
+        .unwrap();
+
+        let plain = plain.borrow();
+        let expected = plain.iter().map(|event| match event {
+            Event::Pin(id @ (PinId::Lat | PinId::Oe), state) => Event::Pin(*id, !*state),
+            event => *event,
+        });
+
+        assert!(expected.eq(inverted.borrow().iter().copied()));
+    }
+
+    #[test]
+    fn polarized_latch_timing() {
+        let polarity = Polarity {
+            output_enable_active_high: false,
+            latch_active_low: true,
+        };
+        // the latch events, with the delays between them
+        let latches = |log: &Log| {
+            let log = log.borrow();
+
+            log.windows(3)
+                .filter_map(|events| match events {
+                    [Event::Pin(PinId::Lat, Low), between, Event::Pin(PinId::Lat, High)] => {
+                        Some(Some(*between))
+                    }
+                    [Event::Pin(PinId::Lat, Low), Event::Pin(PinId::Lat, High), _] => Some(None),
+                    _ => None,
+                })
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        let timed = Log::default();
+        let (clk, lat, oe) = data_pins(&timed);
+
+        Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&timed),
+            row_pins(&timed),
+            PolarizedDataPins::new(
+                TimedDataPins::new(
+                    clk,
+                    lat,
+                    oe,
+                    Timing {
+                        clock_us: 1,
+                        latch_us: 5,
+                    },
+                ),
+                polarity,
+            ),
+            0.5,
+        )
+        .output(&mut ProbeDelay(&timed))
+        .unwrap();
+
+        assert_eq!(latches(&timed), [Some(Event::Delay(5)); 16 * 2]);
+
+        let fast = Log::default();
+        let (clk, lat, oe) = data_pins(&fast);
+
+        Hub75_64_32_2::<2, _, _, _>::new(
+            color_pins(&fast),
+            row_pins(&fast),
+            PolarizedDataPins::new(Undelayed((clk, lat, oe)), polarity),
+            0.5,
+        )
+        .output(&mut ProbeDelay(&fast))
+        .unwrap();
+
+        assert_eq!(latches(&fast), [None; 16 * 2]);
+    }
+
+    #[test]
+    fn halves_swapped() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+
+        display.set_halves_swapped(true);
+        Pixel(Point::zero(), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log)[0], [Low, Low, Low, High, High, High]);
+    }
 }
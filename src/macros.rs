@@ -0,0 +1,233 @@
+//! Macros reducing the boilerplate of constructing displays.
+
+/// Group named pins into the color, row, and data pin tuples expected by the
+/// display constructors.
+///
+/// Pins may be provided in any order. Missing, duplicate, or unknown pin names are
+/// rejected at compile time.
+///
+/// ```
+/// # use hub75_remastered::hub75_pins;
+/// # let (pa0, pa1, pa2, pb0, pb1, pb2, pc0, pc1, pc2, pc3, pd0, pd1, pd2) =
+/// #     (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12);
+/// let (color_pins, row_pins, data_pins) = hub75_pins! {
+///     r1: pa0, g1: pa1, b1: pa2,
+///     r2: pb0, g2: pb1, b2: pb2,
+///     a: pc0, b: pc1, c: pc2, d: pc3,
+///     clk: pd0, lat: pd1, oe: pd2,
+/// };
+///
+/// assert_eq!(color_pins, ((pa0, pa1, pa2), (pb0, pb1, pb2)));
+/// assert_eq!(row_pins, (pc0, pc1, pc2, pc3));
+/// assert_eq!(data_pins, (pd0, pd1, pd2));
+/// ```
+///
+/// Displays with 32 scan rows (e.g. 64x64) take an additional `e` row pin:
+///
+/// ```
+/// # use hub75_remastered::hub75_pins;
+/// let (_, row_pins, _) = hub75_pins! {
+///     r1: 0, g1: 1, b1: 2,
+///     r2: 3, g2: 4, b2: 5,
+///     a: 6, b: 7, c: 8, d: 9, e: 10,
+///     clk: 11, lat: 12, oe: 13,
+/// };
+///
+/// assert_eq!(row_pins, (6, 7, 8, 9, 10));
+/// ```
+///
+/// A missing pin is an error:
+///
+/// ```compile_fail,E0063
+/// # use hub75_remastered::hub75_pins;
+/// let pins = hub75_pins! {
+///     r1: 0, g1: 1, b1: 2,
+///     r2: 3, g2: 4, b2: 5,
+///     a: 6, b: 7, c: 8, d: 9,
+///     clk: 10, lat: 11,
+/// };
+/// ```
+///
+/// As is a duplicate pin:
+///
+/// ```compile_fail,E0062
+/// # use hub75_remastered::hub75_pins;
+/// let pins = hub75_pins! {
+///     r1: 0, g1: 1, b1: 2,
+///     r2: 3, g2: 4, b2: 5,
+///     a: 6, b: 7, c: 8, d: 9,
+///     clk: 10, lat: 11, oe: 12, lat: 13,
+/// };
+/// ```
+#[macro_export]
+macro_rules! hub75_pins {
+    (@select [$($fields:tt)*] e $($rest:ident)*) => {
+        $crate::macros::PinsE { $($fields)* }.into_parts()
+    };
+    (@select [$($fields:tt)*] $first:ident $($rest:ident)*) => {
+        $crate::hub75_pins!(@select [$($fields)*] $($rest)*)
+    };
+    (@select [$($fields:tt)*]) => {
+        $crate::macros::Pins { $($fields)* }.into_parts()
+    };
+    ($($name:ident : $pin:expr),+ $(,)?) => {
+        $crate::hub75_pins!(@select [$($name: $pin),+] $($name)+)
+    };
+}
+
+/// Declare a display by its geometry, color depth, and chain length.
+///
+/// The `type` form expands to the concrete driver type, with the pin types provided
+/// as the color, row, and data pin tuples:
+///
+/// ```
+/// # use hub75_remastered::{hub75, pins::NoPins};
+/// // two chained 64x32 panels
+/// type Display = hub75!(type {
+///     width: 64, height: 32, scan: 16, bits: 4, chain: 2,
+///     color: NoPins, row: NoPins, data: NoPins,
+/// });
+///
+/// let display = Display::new(NoPins, NoPins, NoPins, 0.5);
+/// ```
+///
+/// The other form expands to the constructor call, taking the named pins as for
+/// [`hub75_pins!`](crate::hub75_pins):
+///
+/// ```ignore
+/// let mut display = hub75! {
+///     width: 64, height: 32, scan: 16, bits: 4, chain: 2,
+///     on_ratio: 0.5,
+///     pins: {
+///         r1: pa0, g1: pa1, b1: pa2,
+///         r2: pb0, g2: pb1, b2: pb2,
+///         a: pc0, b: pc1, c: pc2, d: pc3,
+///         clk: pd0, lat: pd1, oe: pd2,
+///     },
+/// };
+/// ```
+///
+/// `chain` defaults to a single panel.
+#[macro_export]
+macro_rules! hub75 {
+    (type {
+        width: $width:expr, height: $height:expr, scan: $scan:expr, bits: $bits:expr,
+        $(chain: $chain:expr,)?
+        color: $color:ty, row: $row:ty, data: $data:ty $(,)?
+    }) => {
+        $crate::Hub75<{ $width $(* $chain)? }, { $height }, { $scan }, { $bits }, $color, $row, $data>
+    };
+    (
+        width: $width:expr, height: $height:expr, scan: $scan:expr, bits: $bits:expr,
+        $(chain: $chain:expr,)?
+        on_ratio: $on_ratio:expr,
+        pins: { $($pins:tt)* } $(,)?
+    ) => {{
+        let (color_pins, row_pins, data_pins) = $crate::hub75_pins! { $($pins)* };
+
+        $crate::Hub75::<{ $width $(* $chain)? }, { $height }, { $scan }, { $bits }, _, _, _>::new(
+            color_pins, row_pins, data_pins, $on_ratio,
+        )
+    }};
+}
+
+/// The named pins of a display, used by [`hub75_pins!`](crate::hub75_pins).
+#[doc(hidden)]
+pub struct Pins<R1, G1, B1, R2, G2, B2, A, B, C, D, Clk, Lat, Oe> {
+    pub r1: R1,
+    pub g1: G1,
+    pub b1: B1,
+    pub r2: R2,
+    pub g2: G2,
+    pub b2: B2,
+    pub a: A,
+    pub b: B,
+    pub c: C,
+    pub d: D,
+    pub clk: Clk,
+    pub lat: Lat,
+    pub oe: Oe,
+}
+
+impl<R1, G1, B1, R2, G2, B2, A, B, C, D, Clk, Lat, Oe>
+    Pins<R1, G1, B1, R2, G2, B2, A, B, C, D, Clk, Lat, Oe>
+{
+    #[allow(clippy::type_complexity)]
+    pub fn into_parts(self) -> (((R1, G1, B1), (R2, G2, B2)), (A, B, C, D), (Clk, Lat, Oe)) {
+        (
+            ((self.r1, self.g1, self.b1), (self.r2, self.g2, self.b2)),
+            (self.a, self.b, self.c, self.d),
+            (self.clk, self.lat, self.oe),
+        )
+    }
+}
+
+/// The named pins of a display with an `e` row pin, used by
+/// [`hub75_pins!`](crate::hub75_pins).
+#[doc(hidden)]
+pub struct PinsE<R1, G1, B1, R2, G2, B2, A, B, C, D, E, Clk, Lat, Oe> {
+    pub r1: R1,
+    pub g1: G1,
+    pub b1: B1,
+    pub r2: R2,
+    pub g2: G2,
+    pub b2: B2,
+    pub a: A,
+    pub b: B,
+    pub c: C,
+    pub d: D,
+    pub e: E,
+    pub clk: Clk,
+    pub lat: Lat,
+    pub oe: Oe,
+}
+
+impl<R1, G1, B1, R2, G2, B2, A, B, C, D, E, Clk, Lat, Oe>
+    PinsE<R1, G1, B1, R2, G2, B2, A, B, C, D, E, Clk, Lat, Oe>
+{
+    #[allow(clippy::type_complexity)]
+    pub fn into_parts(
+        self,
+    ) -> (
+        ((R1, G1, B1), (R2, G2, B2)),
+        (A, B, C, D, E),
+        (Clk, Lat, Oe),
+    ) {
+        (
+            ((self.r1, self.g1, self.b1), (self.r2, self.g2, self.b2)),
+            (self.a, self.b, self.c, self.d, self.e),
+            (self.clk, self.lat, self.oe),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use crate::test_utils::*;
+    use embedded_graphics::geometry::{Dimensions, Size};
+
+    #[test]
+    fn hub75_constructs_chain() {
+        let log = Log::default();
+        let ((r1, g1, b1), (r2, g2, b2)) = color_pins(&log);
+        let (a, b, c, d) = row_pins(&log);
+        let (clk, lat, oe) = data_pins(&log);
+
+        let mut display = hub75! {
+            width: 64, height: 32, scan: 16, bits: 1, chain: 2,
+            on_ratio: 0.5,
+            pins: {
+                r1: r1, g1: g1, b1: b1,
+                r2: r2, g2: g2, b2: b2,
+                a: a, b: b, c: c, d: d,
+                clk: clk, lat: lat, oe: oe,
+            },
+        };
+
+        assert_eq!(display.bounding_box().size, Size::new(128, 32));
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log).len(), 128 * 16);
+    }
+}
@@ -0,0 +1,284 @@
+//! Indexed (palette) drawing.
+//!
+//! Effects like fire, plasma, or scrollers typically compute a single index per
+//! pixel and map it through a palette. Drawing these through [`Rgb565`] and the
+//! gamma table for every pixel of every frame is wasteful, so the palette is
+//! gamma corrected once when it is set and its entries are written directly into
+//! the framebuffer.
+//!
+//! Indexed drawing is opt-in: the palette and the drawn indices live in an
+//! [`IndexedFrame`] provided by the application, which an [`Indexed`] view of the
+//! display renders into its framebuffer. Since every pixel keeps its index,
+//! changing the palette re-renders the frame, so classic palette cycling effects
+//! need no redraw.
+//!
+//! [`Rgb565`]: embedded_graphics::pixelcolor::Rgb565
+
+use crate::{
+    gamma::Gamma, pins::*, pipeline::DisplayPipeline, update, FrameBuffer, FrameStorage, Hub75,
+};
+
+/// The palette and the drawn palette indices of a `WIDTH` by `HEIGHT` display.
+pub struct IndexedFrame<const WIDTH: usize, const HEIGHT: usize> {
+    /// The linear palette entries.
+    palette: [(u8, u8, u8); 256],
+    indices: [[u8; WIDTH]; HEIGHT],
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> IndexedFrame<WIDTH, HEIGHT> {
+    /// Create an indexed frame with an all black palette and every pixel at index
+    /// 0, which can be placed in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            palette: [(0, 0, 0); 256],
+            indices: [[0; WIDTH]; HEIGHT],
+        }
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for IndexedFrame<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A display drawn through palette indices, see [`Hub75::indexed`].
+pub struct Indexed<
+    'a,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    display:
+        &'a mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    frame: &'a mut IndexedFrame<WIDTH, HEIGHT>,
+    /// The palette entries, corrected by the pipeline of the display.
+    corrected: [(u8, u8, u8); 256],
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Indexed<'_, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Set the palette, and re-render every pixel with its new entry.
+    ///
+    /// The palette entries are linear 8-bit colors and are gamma corrected once here.
+    pub fn set_palette(&mut self, palette: &[(u8, u8, u8); 256]) {
+        self.frame.palette = *palette;
+        self.correct_palette();
+        self.render();
+    }
+
+    /// Draw a single pixel with the palette entry at `index`.
+    ///
+    /// Out of bounds pixels are ignored.
+    pub fn draw_indexed(&mut self, x: u16, y: u16, index: u8) {
+        let Some(entry) = self
+            .frame
+            .indices
+            .get_mut(y as usize)
+            .and_then(|row| row.get_mut(x as usize))
+        else {
+            return;
+        };
+
+        *entry = index;
+
+        let color = self.corrected[index as usize];
+
+        if let Some(pixel) = self.display.frame.pixel_mut(x as usize, y as usize) {
+            let changed = update(pixel, color);
+
+            if changed {
+                self.display.dirty_rows[y as usize % SCAN] = self.display.dirty_stamp;
+            }
+
+            self.display.mark_rows_changed(changed);
+        }
+    }
+
+    /// Load a full frame of palette indices, in row-major order.
+    ///
+    /// If `indices` is shorter than the display, only the leading pixels are
+    /// written; excess indices are ignored.
+    pub fn load_indexed(&mut self, indices: &[u8]) {
+        for (entry, index) in self.frame.indices.iter_mut().flatten().zip(indices) {
+            *entry = *index;
+        }
+
+        self.render();
+    }
+
+    /// The display, e.g. for its settings or output.
+    ///
+    /// *Draws to it are overwritten by the next re-render, i.e. palette change or
+    /// [`load_indexed`](Self::load_indexed).*
+    pub fn display(
+        &mut self,
+    ) -> &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        self.display
+    }
+
+    /// Correct the palette by the pipeline of the display.
+    fn correct_palette(&mut self) {
+        let pipeline = self.display.pipeline;
+
+        for (entry, color) in self.corrected.iter_mut().zip(&self.frame.palette) {
+            *entry = pipeline.correct(color);
+        }
+    }
+
+    /// Render every pixel with its palette entry, marking the changed rows dirty.
+    fn render(&mut self) {
+        let mut changed = false;
+
+        for (y, (row, indices)) in self
+            .display
+            .frame
+            .data
+            .iter_mut()
+            .zip(&self.frame.indices)
+            .enumerate()
+        {
+            let mut row_changed = false;
+
+            for (pixel, index) in row.iter_mut().zip(indices) {
+                row_changed |= update(pixel, self.corrected[*index as usize]);
+            }
+
+            if row_changed {
+                self.display.dirty_rows[y % SCAN] = self.display.dirty_stamp;
+            }
+
+            changed |= row_changed;
+        }
+
+        self.display.mark_rows_changed(changed);
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Draw the display through the palette and indices of `frame`.
+    ///
+    /// The framebuffer is rendered from the indices of `frame` right away, and on
+    /// every palette change. The palette is corrected by the current
+    /// [pipeline](crate::pipeline) of the display.
+    pub fn indexed<'a>(
+        &'a mut self,
+        frame: &'a mut IndexedFrame<WIDTH, HEIGHT>,
+    ) -> Indexed<'a, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        let mut indexed = Indexed {
+            display: self,
+            frame,
+            corrected: [(0, 0, 0); 256],
+        };
+
+        indexed.correct_palette();
+        indexed.render();
+
+        indexed
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{gamma::Gamma, test_utils::*};
+
+    /// A palette with the index in one channel.
+    fn ramp(channel: usize) -> [(u8, u8, u8); 256] {
+        core::array::from_fn(|i| match channel {
+            0 => (i as u8, 0, 0),
+            1 => (0, i as u8, 0),
+            _ => (0, 0, i as u8),
+        })
+    }
+
+    #[test]
+    fn indexed_drawing() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let mut frame = IndexedFrame::new();
+
+        display.set_gamma(Gamma::LINEAR);
+
+        let mut indexed = display.indexed(&mut frame);
+
+        indexed.set_palette(&ramp(0));
+        indexed.draw_indexed(1, 2, 5);
+
+        assert_eq!(indexed.display().frame().pixel(1, 2), Some(&(5, 0, 0)));
+
+        let generation = indexed.display().generation();
+
+        indexed.draw_indexed(64, 0, 5);
+
+        assert_eq!(indexed.display().generation(), generation);
+
+        indexed.load_indexed(&[1, 2, 3]);
+
+        assert!(indexed.display().frame().pixels().take(4).eq([
+            &(1, 0, 0),
+            &(2, 0, 0),
+            &(3, 0, 0),
+            &(0, 0, 0)
+        ]));
+        assert_eq!(indexed.display().frame().pixel(1, 2), Some(&(5, 0, 0)));
+    }
+
+    #[test]
+    fn remap() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let mut frame = IndexedFrame::new();
+        let mut previous = ramp(0);
+
+        previous[1] = (200, 200, 200);
+        previous[2] = (200, 200, 200);
+
+        display.set_gamma(Gamma::LINEAR);
+
+        let mut indexed = display.indexed(&mut frame);
+
+        indexed.set_palette(&previous);
+        indexed.draw_indexed(0, 0, 2);
+        indexed.draw_indexed(1, 0, 7);
+        indexed.display().set_pixel_raw(2, 0, (0, 0, 7)).unwrap();
+        indexed.set_palette(&ramp(1));
+
+        // duplicate entries keep their own index
+        assert_eq!(indexed.display().frame().pixel(0, 0), Some(&(0, 2, 0)));
+        assert_eq!(indexed.display().frame().pixel(1, 0), Some(&(0, 7, 0)));
+        // raw draws are re-rendered from their index
+        assert_eq!(indexed.display().frame().pixel(2, 0), Some(&(0, 0, 0)));
+
+        indexed.draw_indexed(3, 0, 9);
+
+        assert_eq!(indexed.display().frame().pixel(3, 0), Some(&(0, 9, 0)));
+    }
+}
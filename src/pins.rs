@@ -1,9 +1,9 @@
-#[cfg(feature = "hal-02")]
-use embedded_hal_02::digital::v2::{OutputPin, PinState};
-#[cfg(feature = "hal-1")]
-use embedded_hal_1::digital::{OutputPin, PinState};
+use core::convert::Infallible;
 
-use crate::DelayProvider;
+use crate::{
+    hal::{NoDelay, OutputPin, PinState, PwmChannel},
+    sink, DelayProvider,
+};
 
 // Traits
 
@@ -17,6 +17,50 @@ pub trait IsColorPins<Color = (u8, u8, u8), Mask = u8> {
     fn set_color<const BITS: u8>(&mut self, color: &Color, mask: &Mask) -> Result<(), Self::Error>;
 }
 
+/// Represents pins that control the set colors to be written to the corresponding
+/// upper and lower rows of the display at once.
+///
+/// This is implemented for any pair of [`IsColorPins`], but may also be implemented
+/// directly to set all color pins in one logical operation (e.g. a port write).
+pub trait IsDualColorPins<Color = (u8, u8, u8), Mask = u8> {
+    type Error;
+
+    /// Set the pin states to represent the provided upper and lower colors, color
+    /// depth, and color mask.
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        upper: &Color,
+        lower: &Color,
+        mask: &Mask,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Represents a group of color pins written in one operation (e.g. a whole GPIO
+/// port register), receiving the upper and lower colors pre-combined.
+///
+/// Use [`ColorBus`] to drive a display with it.
+pub trait IsColorBus {
+    type Error;
+
+    /// Write the bits of the upper and lower colors, combined as a packed column
+    /// (see [`sink`](crate::sink) for the format).
+    fn write(&mut self, packed: u8) -> Result<(), Self::Error>;
+}
+
+/// Represents pins that control the set colors to be written to the four
+/// corresponding rows (one per quarter) of a display with four color groups.
+pub trait IsQuadColorPins<Color = (u8, u8, u8), Mask = u8> {
+    type Error;
+
+    /// Set the pin states to represent the provided colors (from the top quarter
+    /// down), color depth, and color mask.
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        colors: &[Color; 4],
+        mask: &Mask,
+    ) -> Result<(), Self::Error>;
+}
+
 /// Represents pins that control the row of the display to be written to.
 pub trait IsRowPins<Row = u8> {
     type Error;
@@ -35,6 +79,71 @@ pub trait IsDataPins {
     /// Toggle the latch pin to confirm the shifted values.
     fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::Error>;
 
+    /// The total time (in microseconds) [`shift`](Self::shift) delays for, used to
+    /// estimate the frame time.
+    ///
+    /// By default, this is the 2µs of the standard data pins, which hold the clock
+    /// high and then low for 1µs each.
+    fn shift_delay_us(&self) -> u32 {
+        2
+    }
+
+    /// The total time (in microseconds) [`latch`](Self::latch) delays for, used to
+    /// estimate the frame time.
+    ///
+    /// By default, this is the 1µs of the standard data pins.
+    fn latch_delay_us(&self) -> u32 {
+        1
+    }
+
+    /// Pulse the latch pin to the `active` level and back to confirm the shifted
+    /// values, e.g. for [inverted latch lines](PolarizedDataPins).
+    ///
+    /// By default, high pulses are [`latch`](Self::latch)es, and low pulses are
+    /// driven through [`set_latch`](Self::set_latch) and last one microsecond.
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), Self::Error> {
+        match active {
+            PinState::High => self.latch(delay),
+            PinState::Low => {
+                self.set_latch(PinState::Low)?;
+                delay.delay_us(1);
+                self.set_latch(PinState::High)
+            }
+        }
+    }
+
+    /// Drive the latch pin to the provided state, e.g. to hold it while shifting
+    /// (as some driver chips require for configuration writes).
+    ///
+    /// This is only needed for [driver chips](crate::chip) which are configured
+    /// or driven with latch-framed commands. By default this does nothing, so
+    /// such chips can't be used with data pins which only pulse the latch.
+    fn set_latch(&mut self, _state: PinState) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Assert the output enable pin, displaying the registered pixel values of the
+    /// selected row until [`disable_output`](Self::disable_output).
+    ///
+    /// This holds the output enabled across other operations, e.g. for S-PWM
+    /// driver chips, [synchronized panels](crate::synced) and async output.
+    fn enable_output(&mut self) -> Result<(), Self::Error>;
+
+    /// Deassert the output enable pin, blanking the display.
+    fn disable_output(&mut self) -> Result<(), Self::Error>;
+
+    /// Pulse the grayscale clock of S-PWM driver chips (e.g. ICN2053), which
+    /// generate their own PWM from it.
+    ///
+    /// Data pins without a grayscale clock do nothing.
+    fn pulse_gclk(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Toggle the output enable pin to display the registered pixel values of the selected
     /// row for the provided duration.
     fn show<Delay: DelayProvider>(
@@ -42,10 +151,65 @@ pub trait IsDataPins {
         delay: &mut Delay,
         duration: u32, /* defined by DelayNs */
     ) -> Result<(), Self::Error>;
+
+    /// Block until the row shown by the last [`show`](Self::show) is dark, before
+    /// another row is selected.
+    ///
+    /// By default `show` blocks until then, so this returns right away. Data pins
+    /// timing the show in hardware (e.g. [`PwmDataPins`]) return from `show` early
+    /// instead, so the next row is shifted meanwhile.
+    fn wait_shown(&mut self) {}
+}
+
+/// Show the selected row for `duration` microseconds by holding the output
+/// enabled, for data pins implementing [`IsDataPins::enable_output`].
+pub(crate) fn show_held<DataPins: IsDataPins, Delay: DelayProvider>(
+    pins: &mut DataPins,
+    delay: &mut Delay,
+    duration: u32,
+) -> Result<(), DataPins::Error> {
+    pins.enable_output()?;
+    delay.delay_us(duration);
+    pins.disable_output()
+}
+
+/// Clock one datum, holding `clk` high and then low for `us` microseconds each.
+fn pulse_clock<P: OutputPin, Delay: DelayProvider>(
+    clk: &mut P,
+    delay: &mut Delay,
+    us: u32,
+) -> Result<(), P::Error> {
+    clk.set_high()?;
+    delay.delay_us(us);
+    clk.set_low()?;
+    delay.delay_us(us);
+
+    Ok(())
+}
+
+/// Pulse `pin` to the `active` level for `us` microseconds and back.
+fn pulse_pin<P: OutputPin, Delay: DelayProvider>(
+    pin: &mut P,
+    delay: &mut Delay,
+    active: PinState,
+    us: u32,
+) -> Result<(), P::Error> {
+    pin.set_state(active)?;
+    delay.delay_us(us);
+    pin.set_state(!active)
 }
 
 // Impls
-// TODO: macro generation?
+
+/// The state of the pin representing the provided channel value, color depth, and
+/// color mask.
+fn channel_state<const BITS: u8>(value: u8, mask: &u8) -> PinState {
+    if (value >> (mask + 8 - BITS)) & 0x1 == 1 {
+        PinState::High
+    } else {
+        PinState::Low
+    }
+}
 
 /// Standard three R, G, B color pins.
 impl<E, R, G, B> IsColorPins for (R, G, B)
@@ -61,63 +225,311 @@ where
         color: &(u8, u8, u8),
         mask: &u8,
     ) -> Result<(), Self::Error> {
-        self.0
-            .set_state(if (color.0 >> (mask + 8 - BITS)) & 0x1 == 1 {
-                PinState::High
-            } else {
+        self.0.set_state(channel_state::<BITS>(color.0, mask))?;
+        self.1.set_state(channel_state::<BITS>(color.1, mask))?;
+        self.2.set_state(channel_state::<BITS>(color.2, mask))?;
+
+        Ok(())
+    }
+}
+
+/// A single color pin of single color panels, lit by the brightest channel.
+impl<P: OutputPin> IsColorPins for (P,) {
+    type Error = P::Error;
+
+    fn set_color<const BITS: u8>(
+        &mut self,
+        color: &(u8, u8, u8),
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        let value = color.0.max(color.1).max(color.2);
+
+        self.0.set_state(channel_state::<BITS>(value, mask))
+    }
+}
+
+/// Two R, G color pins of bicolor panels.
+impl<E, R, G> IsColorPins for (R, G)
+where
+    R: OutputPin<Error = E>,
+    G: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn set_color<const BITS: u8>(
+        &mut self,
+        color: &(u8, u8, u8),
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        self.0.set_state(channel_state::<BITS>(color.0, mask))?;
+        self.1.set_state(channel_state::<BITS>(color.1, mask))?;
+
+        Ok(())
+    }
+}
+
+/// A pair of upper and lower color pins.
+impl<E, Upper, Lower> IsDualColorPins for (Upper, Lower)
+where
+    Upper: IsColorPins<Error = E>,
+    Lower: IsColorPins<Error = E>,
+{
+    type Error = E;
+
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        upper: &(u8, u8, u8),
+        lower: &(u8, u8, u8),
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        self.0.set_color::<BITS>(upper, mask)?;
+        self.1.set_color::<BITS>(lower, mask)?;
+
+        Ok(())
+    }
+}
+
+/// Color pins written through an [`IsColorBus`], combining all six color bits
+/// before a single write.
+pub struct ColorBus<Bus>(pub Bus);
+
+impl<Bus: IsColorBus> IsDualColorPins for ColorBus<Bus> {
+    type Error = Bus::Error;
+
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        upper: &(u8, u8, u8),
+        lower: &(u8, u8, u8),
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        self.0.write(sink::pack::<BITS>(upper, lower, *mask))
+    }
+}
+
+/// Four groups of color pins, from the top quarter down.
+impl<E, C1, C2, C3, C4> IsQuadColorPins for (C1, C2, C3, C4)
+where
+    C1: IsColorPins<Error = E>,
+    C2: IsColorPins<Error = E>,
+    C3: IsColorPins<Error = E>,
+    C4: IsColorPins<Error = E>,
+{
+    type Error = E;
+
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        colors: &[(u8, u8, u8); 4],
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        self.0.set_color::<BITS>(&colors[0], mask)?;
+        self.1.set_color::<BITS>(&colors[1], mask)?;
+        self.2.set_color::<BITS>(&colors[2], mask)?;
+        self.3.set_color::<BITS>(&colors[3], mask)?;
+
+        Ok(())
+    }
+}
+
+/// Six R1, G1, B1, R2, G2, B2 color pins, written as a bus.
+impl<E, R1, G1, B1, R2, G2, B2> IsColorBus for (R1, G1, B1, R2, G2, B2)
+where
+    R1: OutputPin<Error = E>,
+    G1: OutputPin<Error = E>,
+    B1: OutputPin<Error = E>,
+    R2: OutputPin<Error = E>,
+    G2: OutputPin<Error = E>,
+    B2: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn write(&mut self, packed: u8) -> Result<(), Self::Error> {
+        let state = |flag: u8| {
+            if packed & flag == 0 {
                 PinState::Low
-            })?;
-        self.1
-            .set_state(if (color.1 >> (mask + 8 - BITS)) & 0x1 == 1 {
-                PinState::High
             } else {
-                PinState::Low
-            })?;
-        self.2
-            .set_state(if (color.2 >> (mask + 8 - BITS)) & 0x1 == 1 {
                 PinState::High
-            } else {
-                PinState::Low
-            })?;
+            }
+        };
+
+        self.0.set_state(state(sink::R1))?;
+        self.1.set_state(state(sink::G1))?;
+        self.2.set_state(state(sink::B1))?;
+        self.3.set_state(state(sink::R2))?;
+        self.4.set_state(state(sink::G2))?;
+        self.5.set_state(state(sink::B2))?;
 
         Ok(())
     }
 }
 
-/// 4 Row control pins for 16 (2^4) rows.
-impl<E, A, B, C, D> IsRowPins for (A, B, C, D)
+/// Six R1, G1, B1, R2, G2, B2 color pins, with the bits of both halves computed
+/// together.
+impl<E, R1, G1, B1, R2, G2, B2> IsDualColorPins for (R1, G1, B1, R2, G2, B2)
+where
+    R1: OutputPin<Error = E>,
+    G1: OutputPin<Error = E>,
+    B1: OutputPin<Error = E>,
+    R2: OutputPin<Error = E>,
+    G2: OutputPin<Error = E>,
+    B2: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        upper: &(u8, u8, u8),
+        lower: &(u8, u8, u8),
+        mask: &u8,
+    ) -> Result<(), Self::Error> {
+        self.write(sink::pack::<BITS>(upper, lower, *mask))
+    }
+}
+
+/// The state of the address pin of the provided bit.
+fn address_state(row: &u8, bit: u8) -> PinState {
+    if (row >> bit) & 0x1 == 0 {
+        PinState::Low
+    } else {
+        PinState::High
+    }
+}
+
+/// Implement [`IsRowPins`] for a tuple of binary address pins, from the least
+/// significant bit up.
+macro_rules! impl_row_pins {
+    ($count:literal, $rows:literal: $($pin:ident . $index:tt),+) => {
+        #[doc = concat!($count, " row control pins for ", $rows, " (2^", $count, ") rows.")]
+        impl<Err, $($pin),+> IsRowPins for ($($pin,)+)
+        where
+            $($pin: OutputPin<Error = Err>),+
+        {
+            type Error = Err;
+
+            fn set_row(&mut self, row: &u8) -> Result<(), Self::Error> {
+                $(self.$index.set_state(address_state(row, $index))?;)+
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_row_pins!(1, 2: A.0);
+impl_row_pins!(2, 4: A.0, B.1);
+impl_row_pins!(3, 8: A.0, B.1, C.2);
+impl_row_pins!(4, 16: A.0, B.1, C.2, D.3);
+impl_row_pins!(5, 32: A.0, B.1, C.2, D.3, E.4);
+impl_row_pins!(6, 64: A.0, B.1, C.2, D.3, E.4, F.5);
+impl_row_pins!(7, 128: A.0, B.1, C.2, D.3, E.4, F.5, G.6);
+impl_row_pins!(8, 256: A.0, B.1, C.2, D.3, E.4, F.5, G.6, H.7);
+
+/// Row pins driving the address inputs of a 74HC138 (or 74HC238) decoder for 8
+/// rows, with its active low enable input (G2A or G2B).
+///
+/// The decoder is disabled while the address pins change, so no other row is
+/// briefly selected (which shows as ghosting) when several address pins toggle.
+pub struct DecoderRowPins<A, B, C, Enable> {
+    a: A,
+    b: B,
+    c: C,
+    enable: Enable,
+}
+
+impl<A, B, C, Enable> DecoderRowPins<A, B, C, Enable> {
+    pub fn new(a: A, b: B, c: C, enable: Enable) -> Self {
+        Self { a, b, c, enable }
+    }
+
+    /// Release the underlying pins.
+    pub fn release(self) -> (A, B, C, Enable) {
+        (self.a, self.b, self.c, self.enable)
+    }
+}
+
+impl<E, A, B, C, Enable> IsRowPins for DecoderRowPins<A, B, C, Enable>
 where
     A: OutputPin<Error = E>,
     B: OutputPin<Error = E>,
     C: OutputPin<Error = E>,
-    D: OutputPin<Error = E>,
+    Enable: OutputPin<Error = E>,
 {
     type Error = E;
 
     fn set_row(&mut self, row: &u8) -> Result<(), Self::Error> {
-        self.0.set_state(if row & 0x1 == 0 {
-            PinState::Low
-        } else {
-            PinState::High
-        })?;
+        self.enable.set_high()?;
 
-        self.1.set_state(if (row >> 1) & 0x1 == 0 {
-            PinState::Low
-        } else {
-            PinState::High
-        })?;
+        self.a.set_state(address_state(row, 0))?;
+        self.b.set_state(address_state(row, 1))?;
+        self.c.set_state(address_state(row, 2))?;
 
-        self.2.set_state(if (row >> 2) & 0x1 == 0 {
-            PinState::Low
-        } else {
-            PinState::High
-        })?;
+        self.enable.set_low()
+    }
+}
+
+/// Row pins of panels (e.g. 1/32 scan SM5266P panels) whose row drivers select
+/// rows by a token shifted through a chain of `ROWS` outputs, with the A pin as
+/// data and the B pin as clock, instead of a binary address.
+///
+/// The token is active low. Consecutive rows only advance the token by one clock,
+/// any other row reloads the whole chain.
+pub struct ShiftTokenRowPins<Data, Clk, const ROWS: usize> {
+    data: Data,
+    clk: Clk,
+    row: Option<u8>,
+}
+
+impl<Data, Clk, const ROWS: usize> ShiftTokenRowPins<Data, Clk, ROWS> {
+    pub fn new(data: Data, clk: Clk) -> Self {
+        Self {
+            data,
+            clk,
+            row: None,
+        }
+    }
 
-        self.3.set_state(if (row >> 3) & 0x1 == 0 {
+    /// Release the underlying pins.
+    pub fn release(self) -> (Data, Clk) {
+        (self.data, self.clk)
+    }
+}
+
+impl<E, Data, Clk, const ROWS: usize> ShiftTokenRowPins<Data, Clk, ROWS>
+where
+    Data: OutputPin<Error = E>,
+    Clk: OutputPin<Error = E>,
+{
+    /// Shift one output of the chain, selecting it if `active`.
+    fn shift(&mut self, active: bool) -> Result<(), E> {
+        self.data.set_state(if active {
             PinState::Low
         } else {
             PinState::High
         })?;
+        self.clk.set_high()?;
+        self.clk.set_low()
+    }
+}
+
+impl<E, Data, Clk, const ROWS: usize> IsRowPins for ShiftTokenRowPins<Data, Clk, ROWS>
+where
+    Data: OutputPin<Error = E>,
+    Clk: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn set_row(&mut self, row: &u8) -> Result<(), Self::Error> {
+        match self.row {
+            Some(selected) if selected == *row => {}
+            Some(selected) if selected as usize + 1 == *row as usize => self.shift(false)?,
+            _ => {
+                // the first output shifted in ends up farthest down the chain
+                for output in (0..ROWS).rev() {
+                    self.shift(output == *row as usize)?;
+                }
+            }
+        }
+
+        self.row = Some(*row);
 
         Ok(())
     }
@@ -133,31 +545,688 @@ where
     type Error = E;
 
     fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
-        self.0.set_high()?;
-        delay.delay_us(1);
-        self.0.set_low()?;
-        delay.delay_us(1);
+        pulse_clock(&mut self.0, delay, 1)
+    }
 
-        Ok(())
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), E> {
+        pulse_pin(&mut self.1, delay, active, 1)
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), E> {
+        self.1.set_state(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        self.2.set_low()
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        self.2.set_high()
+    }
+
+    fn show<Delay: DelayProvider>(&mut self, delay: &mut Delay, duration: u32) -> Result<(), E> {
+        show_held(self, delay, duration)
+    }
+}
+
+/// Data pins for S-PWM driver chips: clock, latch, output enable, and grayscale
+/// clock.
+impl<E, Clk, Latch, Output, Gclk> IsDataPins for (Clk, Latch, Output, Gclk)
+where
+    Clk: OutputPin<Error = E>,
+    Latch: OutputPin<Error = E>,
+    Output: OutputPin<Error = E>,
+    Gclk: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        pulse_clock(&mut self.0, delay, 1)
     }
 
     fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
-        self.1.set_high()?;
-        delay.delay_us(1);
-        self.1.set_low()?;
+        self.pulse_latch(delay, PinState::High)
+    }
 
-        Ok(())
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), E> {
+        pulse_pin(&mut self.1, delay, active, 1)
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), E> {
+        self.1.set_state(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        self.2.set_low()
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        self.2.set_high()
+    }
+
+    fn show<Delay: DelayProvider>(&mut self, delay: &mut Delay, duration: u32) -> Result<(), E> {
+        show_held(self, delay, duration)
+    }
+
+    fn pulse_gclk(&mut self) -> Result<(), E> {
+        self.3.set_high()?;
+        self.3.set_low()
+    }
+}
+
+/// The pulse widths of the clock and latch pins, in microseconds.
+///
+/// The standard data pins hold every edge for 1µs, which is far longer than most
+/// panels need. To omit the delays entirely, use [`Undelayed`] instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// The duration the clock is held high and then low for each shifted datum.
+    pub clock_us: u32,
+    /// The duration the latch is held active.
+    pub latch_us: u32,
+}
+
+impl Timing {
+    /// The timing of the standard data pins.
+    pub const STANDARD: Self = Self {
+        clock_us: 1,
+        latch_us: 1,
+    };
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// Data pins like the standard clock, latch, and output enable tuple, but with the
+/// pulse widths of the provided [`Timing`].
+pub struct TimedDataPins<Clk, Latch, Output> {
+    clk: Clk,
+    latch: Latch,
+    output: Output,
+    timing: Timing,
+}
+
+impl<Clk, Latch, Output> TimedDataPins<Clk, Latch, Output> {
+    pub fn new(clk: Clk, latch: Latch, output: Output, timing: Timing) -> Self {
+        Self {
+            clk,
+            latch,
+            output,
+            timing,
+        }
+    }
+
+    /// Set the pulse widths used for subsequent shifts and latches.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Release the underlying pins.
+    pub fn release(self) -> (Clk, Latch, Output) {
+        (self.clk, self.latch, self.output)
+    }
+}
+
+impl<E, Clk, Latch, Output> IsDataPins for TimedDataPins<Clk, Latch, Output>
+where
+    Clk: OutputPin<Error = E>,
+    Latch: OutputPin<Error = E>,
+    Output: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        pulse_clock(&mut self.clk, delay, self.timing.clock_us)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), E> {
+        pulse_pin(&mut self.latch, delay, active, self.timing.latch_us)
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        2 * self.timing.clock_us
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        self.timing.latch_us
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), E> {
+        self.latch.set_state(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        self.output.set_low()
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        self.output.set_high()
+    }
+
+    fn show<Delay: DelayProvider>(&mut self, delay: &mut Delay, duration: u32) -> Result<(), E> {
+        show_held(self, delay, duration)
+    }
+}
+
+/// The standard clock, latch, and output enable tuple without any delays in
+/// `shift` and `latch`, see [`Undelayed`].
+pub type FastDataPins<Clk, Latch, Output> = Undelayed<(Clk, Latch, Output)>;
+
+/// A PWM channel which outputs a duty cycle for a time on its own, e.g. a timer
+/// with a repetition counter in one-pulse mode.
+pub trait PwmPulse: PwmChannel {
+    /// Output the duty cycle `duty` for `us` microseconds, and the maximum duty
+    /// cycle after, returning right away.
+    fn start_pulse(&mut self, duty: u16, us: u32) -> Result<(), Self::Error>;
+
+    /// Whether the last started pulse is complete.
+    fn is_pulse_done(&mut self) -> bool;
+}
+
+/// Data pins driving the output enable with a hardware PWM channel instead of a
+/// GPIO.
+///
+/// While shown, the output enable is modulated with the duty cycle set by
+/// [`set_brightness`](Self::set_brightness), dimming the whole display smoothly
+/// without shortening the show durations (unlike a lower `on_ratio`). The PWM
+/// period must be much shorter than the shortest show duration, or low bit planes
+/// will flicker.
+///
+/// `show` hands the duration to the channel as a [pulse](PwmPulse) and returns,
+/// so the CPU shifts the next row while the current one is shown. Latching and
+/// the other output enable changes wait for the pulse to complete.
+///
+/// The channel is expected to drive the active-low output enable directly, so a
+/// duty cycle of zero shows the row at full brightness, and the maximum duty
+/// cycle blanks the display.
+pub struct PwmDataPins<Clk, Latch, Pwm> {
+    clk: Clk,
+    latch: Latch,
+    pwm: Pwm,
+    brightness: u8,
+}
+
+impl<Clk, Latch, Pwm> PwmDataPins<Clk, Latch, Pwm> {
+    pub fn new(clk: Clk, latch: Latch, pwm: Pwm) -> Self {
+        Self {
+            clk,
+            latch,
+            pwm,
+            brightness: 255,
+        }
+    }
+
+    /// Set the proportion of the show durations the output is enabled, where 255
+    /// is always.
+    ///
+    /// This takes effect from the next shown row.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+
+    /// Release the underlying pins and PWM channel.
+    pub fn release(self) -> (Clk, Latch, Pwm) {
+        (self.clk, self.latch, self.pwm)
+    }
+}
+
+impl<Clk, Latch, Pwm: PwmPulse> PwmDataPins<Clk, Latch, Pwm> {
+    /// The duty cycle of the enabled output at the set brightness.
+    fn enabled_duty(&self) -> u16 {
+        let max = self.pwm.max_duty() as u32;
+
+        (max - max * self.brightness as u32 / 255) as u16
+    }
+}
+
+impl<E, Clk, Latch, Pwm> IsDataPins for PwmDataPins<Clk, Latch, Pwm>
+where
+    Clk: OutputPin<Error = E>,
+    Latch: OutputPin<Error = E>,
+    Pwm: PwmPulse<Error = E>,
+{
+    type Error = E;
+
+    fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        pulse_clock(&mut self.clk, delay, 1)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), E> {
+        self.wait_shown();
+
+        pulse_pin(&mut self.latch, delay, active, 1)
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), E> {
+        self.wait_shown();
+
+        self.latch.set_state(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        self.wait_shown();
+
+        self.pwm.set_duty(self.enabled_duty())
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        self.wait_shown();
+
+        self.pwm.set_duty(self.pwm.max_duty())
+    }
+
+    fn show<Delay: DelayProvider>(&mut self, _delay: &mut Delay, duration: u32) -> Result<(), E> {
+        self.wait_shown();
+
+        self.pwm.start_pulse(self.enabled_duty(), duration)
+    }
+
+    fn wait_shown(&mut self) {
+        while !self.pwm.is_pulse_done() {}
+    }
+}
+
+/// Any data pins with the delays of `shift` and `latch` omitted, e.g.
+/// `Undelayed((clk, latch, output))` (see [`FastDataPins`]).
+///
+/// The panel's shift registers typically require clock and latch pulses of a few
+/// tens of nanoseconds. On MCUs where toggling a GPIO already takes longer than
+/// that (e.g. most MCUs running at or below ~50MHz), the delays of the standard
+/// data pins are pure waste, and omitting them can multiply the refresh rate.
+///
+/// *On faster MCUs or with very fast GPIO, the pulses may become too short for the
+/// panel, resulting in corrupted or shifted pixels.*
+///
+/// The `show` duration is unaffected.
+pub struct Undelayed<DataPins>(pub DataPins);
+
+impl<DataPins: IsDataPins> IsDataPins for Undelayed<DataPins> {
+    type Error = DataPins::Error;
+
+    fn shift<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        self.0.shift(&mut NoDelay)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        self.0.latch(&mut NoDelay)
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        _delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), Self::Error> {
+        self.0.pulse_latch(&mut NoDelay, active)
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), Self::Error> {
+        self.0.set_latch(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.enable_output()
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.disable_output()
+    }
+
+    fn pulse_gclk(&mut self) -> Result<(), Self::Error> {
+        self.0.pulse_gclk()
     }
 
     fn show<Delay: DelayProvider>(
         &mut self,
         delay: &mut Delay,
-        duration: u32, /* defined by DelayNs */
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        self.0.show(delay, duration)
+    }
+
+    fn wait_shown(&mut self) {
+        self.0.wait_shown()
+    }
+}
+
+/// Data pins driving a shared clock and latch, with a separate output enable per
+/// stacked chain.
+///
+/// Chains sharing their clock and latch lines are shifted by the same pulses, so
+/// their color pins can be driven as one display (e.g. two stacked chains as a
+/// [`Hub75Quad`](crate::quad::Hub75Quad)) and shifted once. The output enables are
+/// asserted together, unless a chain is blanked with
+/// [`set_enabled`](Self::set_enabled).
+pub struct SharedClockDataPins<Clk, Latch, Output, const N: usize> {
+    clk: Clk,
+    latch: Latch,
+    outputs: [Output; N],
+    enabled: [bool; N],
+}
+
+impl<Clk, Latch, Output, const N: usize> SharedClockDataPins<Clk, Latch, Output, N> {
+    pub fn new(clk: Clk, latch: Latch, outputs: [Output; N]) -> Self {
+        Self {
+            clk,
+            latch,
+            outputs,
+            enabled: [true; N],
+        }
+    }
+
+    /// Set whether the chain of the output enable `index` is shown, blanking it
+    /// otherwise.
+    ///
+    /// *`index` must be less than `N`.*
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.enabled[index] = enabled;
+    }
+
+    /// Release the underlying pins.
+    pub fn release(self) -> (Clk, Latch, [Output; N]) {
+        (self.clk, self.latch, self.outputs)
+    }
+}
+
+impl<E, Clk, Latch, Output, const N: usize> IsDataPins
+    for SharedClockDataPins<Clk, Latch, Output, N>
+where
+    Clk: OutputPin<Error = E>,
+    Latch: OutputPin<Error = E>,
+    Output: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        pulse_clock(&mut self.clk, delay, 1)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
     ) -> Result<(), E> {
-        self.2.set_low()?;
-        delay.delay_us(duration);
-        self.2.set_high()?;
+        pulse_pin(&mut self.latch, delay, active, 1)
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), E> {
+        self.latch.set_state(state)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        for (output, enabled) in self.outputs.iter_mut().zip(self.enabled) {
+            if enabled {
+                output.set_low()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        for output in &mut self.outputs {
+            output.set_high()?;
+        }
 
         Ok(())
     }
+
+    fn show<Delay: DelayProvider>(&mut self, delay: &mut Delay, duration: u32) -> Result<(), E> {
+        show_held(self, delay, duration)
+    }
+}
+
+/// The logic levels of the data pins, for adapter boards inverting them (e.g.
+/// through transistors).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Polarity {
+    /// The output enable pin is asserted high instead of low.
+    pub output_enable_active_high: bool,
+    /// The latch pin is pulsed low instead of high.
+    pub latch_active_low: bool,
+}
+
+/// Any data pins driven with the provided [`Polarity`].
+///
+/// *With an inverted latch, the latch pin must idle high, i.e. be initialized high.*
+pub struct PolarizedDataPins<DataPins> {
+    pins: DataPins,
+    polarity: Polarity,
+}
+
+impl<DataPins> PolarizedDataPins<DataPins> {
+    pub fn new(pins: DataPins, polarity: Polarity) -> Self {
+        Self { pins, polarity }
+    }
+
+    /// Release the underlying pins.
+    pub fn release(self) -> DataPins {
+        self.pins
+    }
+}
+
+impl<DataPins: IsDataPins> IsDataPins for PolarizedDataPins<DataPins> {
+    type Error = DataPins::Error;
+
+    fn shift<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::Error> {
+        self.pins.shift(delay)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::Error> {
+        self.pulse_latch(delay, PinState::High)
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        self.pins.shift_delay_us()
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        self.pins.latch_delay_us()
+    }
+
+    fn pulse_latch<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        active: PinState,
+    ) -> Result<(), Self::Error> {
+        self.pins.pulse_latch(
+            delay,
+            if self.polarity.latch_active_low {
+                !active
+            } else {
+                active
+            },
+        )
+    }
+
+    fn set_latch(&mut self, state: PinState) -> Result<(), Self::Error> {
+        self.pins.set_latch(if self.polarity.latch_active_low {
+            !state
+        } else {
+            state
+        })
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        if self.polarity.output_enable_active_high {
+            self.pins.disable_output()
+        } else {
+            self.pins.enable_output()
+        }
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        if self.polarity.output_enable_active_high {
+            self.pins.enable_output()
+        } else {
+            self.pins.disable_output()
+        }
+    }
+
+    fn show<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        if self.polarity.output_enable_active_high {
+            show_held(self, delay, duration)
+        } else {
+            self.pins.show(delay, duration)
+        }
+    }
+
+    fn wait_shown(&mut self) {
+        self.pins.wait_shown()
+    }
+
+    fn pulse_gclk(&mut self) -> Result<(), Self::Error> {
+        self.pins.pulse_gclk()
+    }
+}
+
+/// Data pins driving only the output enable, for displays whose clock and latch
+/// are driven by a [`DmaBackend`](crate::dma::DmaBackend).
+///
+/// `shift` and `latch` do nothing.
+pub struct OutputEnablePin<Output>(pub Output);
+
+impl<Output: OutputPin> IsDataPins for OutputEnablePin<Output> {
+    type Error = Output::Error;
+
+    fn shift<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+
+    fn show<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        show_held(self, delay, duration)
+    }
+}
+
+/// Placeholder pins for displays which are only output through a
+/// [`RowSink`](crate::sink::RowSink).
+pub struct NoPins;
+
+impl IsDualColorPins for NoPins {
+    type Error = Infallible;
+
+    fn set_colors<const BITS: u8>(
+        &mut self,
+        _upper: &(u8, u8, u8),
+        _lower: &(u8, u8, u8),
+        _mask: &u8,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl IsRowPins for NoPins {
+    type Error = Infallible;
+
+    fn set_row(&mut self, _row: &u8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl IsDataPins for NoPins {
+    type Error = Infallible;
+
+    fn shift<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn show<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        show_held(self, delay, duration)
+    }
 }
@@ -0,0 +1,401 @@
+//! The conversion of drawn colors to stored values and their scaling at output.
+//!
+//! By default, drawn colors are corrected by the display's [`Gamma`] tables and
+//! scaled by its white balance and color temperature. Calibrated panels or
+//! unusual LEDs can take full control of both stages with their own
+//! [`ColorPipeline`], see [`Hub75::with_color_pipeline`].
+//!
+//! The pipeline is a type parameter of the display, so shifting data out calls it
+//! directly, without a virtual call per pixel.
+
+use core::any::TypeId;
+
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+
+use crate::{gamma::Gamma, pins::*, scale_color, FrameStorage, Hub75};
+
+/// The conversion of drawn colors to the values stored in the framebuffer, and of
+/// stored values to the values shifted out.
+///
+/// Stored values are shown in proportion to their magnitude, i.e. they are
+/// proportional to the light output, and only their most significant `BITS` bits
+/// are shown.
+pub trait ColorPipeline: Sync {
+    /// Convert a linear 8-bit color to its stored value.
+    fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8);
+
+    /// Convert an [`Rgb565`] color to its stored value.
+    ///
+    /// By default, the channels are expanded to 8 bits (keeping black black) and
+    /// [corrected](Self::correct).
+    fn correct_rgb565(&self, color: Rgb565) -> (u8, u8, u8) {
+        self.correct(&expand_rgb565(color))
+    }
+
+    /// Scale a stored color by the combined white balance and color temperature
+    /// of the display (255 is unity) while shifting data out.
+    ///
+    /// With a custom pipeline, this is called for every shown pixel.
+    fn scale(&self, color: &(u8, u8, u8), scale: &(u8, u8, u8)) -> (u8, u8, u8) {
+        scale_color(color, scale)
+    }
+
+    /// Approximately invert [`correct`](Self::correct), e.g. for dumps of the
+    /// framebuffer. By default, stored values are returned as is.
+    fn linearize(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        *color
+    }
+}
+
+impl<P: ColorPipeline + ?Sized> ColorPipeline for &P {
+    fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        P::correct(self, color)
+    }
+
+    fn correct_rgb565(&self, color: Rgb565) -> (u8, u8, u8) {
+        P::correct_rgb565(self, color)
+    }
+
+    fn scale(&self, color: &(u8, u8, u8), scale: &(u8, u8, u8)) -> (u8, u8, u8) {
+        P::scale(self, color, scale)
+    }
+
+    fn linearize(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        P::linearize(self, color)
+    }
+}
+
+mod sealed {
+    use core::any::TypeId;
+
+    use super::{ColorPipeline, Gamma};
+
+    pub trait Sealed {
+        /// Whether this replaces the gamma tables and the white balance scaling.
+        const CUSTOM: bool;
+
+        /// The pipeline, as tracked by framebuffers.
+        fn selected(&self) -> Selected;
+    }
+
+    /// The pipeline selected by a display, as tracked by framebuffers: its gamma
+    /// tables, unless replaced.
+    #[derive(Clone, Copy)]
+    pub struct Selected {
+        pub(crate) gamma: Gamma,
+        pub(crate) custom: Option<Custom>,
+    }
+
+    /// A custom pipeline, and the type it was selected as.
+    #[derive(Clone, Copy)]
+    pub struct Custom {
+        pub(super) pipeline: &'static dyn ColorPipeline,
+        pub(super) type_id: TypeId,
+    }
+}
+
+use sealed::Custom;
+pub(crate) use sealed::Selected;
+
+/// The pipeline of a display: its [`Gamma`] tables (the default), or a
+/// `&'static` custom [`ColorPipeline`].
+pub trait DisplayPipeline: ColorPipeline + Copy + sealed::Sealed {}
+
+impl<T: ColorPipeline + Copy + sealed::Sealed> DisplayPipeline for T {}
+
+impl sealed::Sealed for Gamma {
+    const CUSTOM: bool = false;
+
+    fn selected(&self) -> Selected {
+        Selected {
+            gamma: *self,
+            custom: None,
+        }
+    }
+}
+
+impl<P: ColorPipeline + 'static> sealed::Sealed for &'static P {
+    const CUSTOM: bool = true;
+
+    fn selected(&self) -> Selected {
+        Selected {
+            gamma: Gamma::POWER,
+            custom: Some(Custom {
+                pipeline: *self,
+                type_id: TypeId::of::<P>(),
+            }),
+        }
+    }
+}
+
+/// Expand each channel of an [`Rgb565`] color to 8 bits, keeping black black.
+pub fn expand_rgb565(color: Rgb565) -> (u8, u8, u8) {
+    fn expand(value: u8, step: u16) -> u8 {
+        match value {
+            0 => 0,
+            value => ((value as u16 + 1) * step - 1) as u8,
+        }
+    }
+
+    (
+        expand(color.r(), 8),
+        expand(color.g(), 4),
+        expand(color.b(), 8),
+    )
+}
+
+impl ColorPipeline for Gamma {
+    fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        Gamma::correct(self, color)
+    }
+
+    fn linearize(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        Gamma::linearize(self, color)
+    }
+}
+
+impl Selected {
+    /// The built-in conversion, like that of a new display.
+    pub(crate) const DEFAULT: Self = Self {
+        gamma: Gamma::POWER,
+        custom: None,
+    };
+
+    /// Whether both select the same tables and custom pipeline, i.e. convert colors
+    /// to the same stored values.
+    ///
+    /// Custom pipelines are the same if they are the same value of the same type.
+    /// Zero-sized pipelines have no value, so their addresses are meaningless and
+    /// only their types are compared.
+    pub(crate) fn same(&self, other: &Self) -> bool {
+        self.gamma.same_tables(&other.gamma)
+            && match (self.custom, other.custom) {
+                (None, None) => true,
+                (Some(this), Some(other)) => {
+                    this.type_id == other.type_id
+                        && (core::mem::size_of_val(this.pipeline) == 0
+                            || core::ptr::addr_eq(this.pipeline, other.pipeline))
+                }
+                _ => false,
+            }
+    }
+}
+
+impl ColorPipeline for Selected {
+    fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        match self.custom {
+            Some(custom) => custom.pipeline.correct(color),
+            None => self.gamma.correct(color),
+        }
+    }
+
+    fn correct_rgb565(&self, color: Rgb565) -> (u8, u8, u8) {
+        match self.custom {
+            Some(custom) => custom.pipeline.correct_rgb565(color),
+            None => self.gamma.correct_rgb565(color),
+        }
+    }
+
+    fn scale(&self, color: &(u8, u8, u8), scale: &(u8, u8, u8)) -> (u8, u8, u8) {
+        match self.custom {
+            Some(custom) => custom.pipeline.scale(color, scale),
+            None => scale_color(color, scale),
+        }
+    }
+
+    fn linearize(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+        match self.custom {
+            Some(custom) => custom.pipeline.linearize(color),
+            None => self.gamma.linearize(color),
+        }
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// The pipeline of the display, as tracked by framebuffers.
+    pub(crate) fn selected_pipeline(&self) -> Selected {
+        self.pipeline.selected()
+    }
+
+    /// Replace the conversion of drawn colors (otherwise done by the
+    /// [gamma tables](Self::set_gamma)) and the scaling of stored colors by the
+    /// [white balance](Self::set_white_balance) with `pipeline`, e.g. a
+    /// `&'static` custom [`ColorPipeline`], or restore them with a [`Gamma`].
+    ///
+    /// The framebuffer stores converted values, so this only affects subsequent
+    /// draws, like [`set_gamma`](Self::set_gamma). Frames loaded from other buffers
+    /// (e.g. a [`FrameQueue`](crate::queue::FrameQueue)) are converted as well.
+    pub fn with_color_pipeline<P: DisplayPipeline>(
+        self,
+        pipeline: P,
+    ) -> Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, P> {
+        Hub75 {
+            frame: self.frame,
+            pipeline,
+            cursor: self.cursor,
+            blanked: self.blanked,
+            halves_swapped: self.halves_swapped,
+            min_frame_interval_us: self.min_frame_interval_us,
+            color_temperature: self.color_temperature,
+            white_balance: self.white_balance,
+            channel_scale: self.channel_scale,
+            dithering: self.dithering,
+            fade: self.fade,
+            row_brightness: self.row_brightness,
+            brightness_mask: self.brightness_mask,
+            overlay: self.overlay,
+            power_limit: self.power_limit,
+            power_sums: self.power_sums,
+            interlaced: self.interlaced,
+            odd_field: self.odd_field,
+            generation: self.generation,
+            dirty_rows: [self.dirty_stamp; SCAN],
+            dirty_stamp: self.dirty_stamp,
+            frame_count: self.frame_count,
+            vsync: self.vsync,
+            blend_mode: self.blend_mode,
+            latch_guard_us: self.latch_guard_us,
+            row_map: self.row_map,
+            scan_order: self.scan_order,
+            chip_mode: self.chip_mode,
+            ftc: self.ftc,
+            color_pins: self.color_pins,
+            row_pins: self.row_pins,
+            data_pins: self.data_pins,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::{sealed::Sealed, *};
+    use crate::{
+        buffer::DoubleBuffered,
+        queue::{DropPolicy, FrameQueue},
+        split::SharedFrame,
+        test_utils::*,
+        FrameBuffer,
+    };
+    use embedded_graphics::{draw_target::DrawTarget, geometry::Point, Drawable, Pixel};
+
+    /// Swap red and blue, and show half of the stored value.
+    struct Swapped;
+
+    /// Leave drawn colors as they are.
+    struct Unchanged;
+
+    impl ColorPipeline for Unchanged {
+        fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+            *color
+        }
+    }
+
+    impl ColorPipeline for Swapped {
+        fn correct(&self, color: &(u8, u8, u8)) -> (u8, u8, u8) {
+            (color.2, color.1, color.0)
+        }
+
+        fn scale(&self, color: &(u8, u8, u8), _: &(u8, u8, u8)) -> (u8, u8, u8) {
+            (color.0 / 2, color.1 / 2, color.2 / 2)
+        }
+    }
+
+    #[test]
+    fn default_expansion() {
+        assert_eq!(expand_rgb565(Rgb565::BLACK), (0, 0, 0));
+        assert_eq!(expand_rgb565(Rgb565::WHITE), (255, 255, 255));
+        assert_eq!(
+            Gamma::POWER.correct_rgb565(Rgb565::new(15, 31, 1)),
+            Gamma::POWER.correct(&(127, 127, 15))
+        );
+    }
+
+    #[test]
+    fn custom_pipeline() {
+        let log = Log::default();
+        let mut display = display::<8>(&log).with_color_pipeline(&Swapped);
+
+        Pixel(Point::zero(), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.frame.pixel(0, 0), Some(&(0, 0, 255)));
+        // halved by the scale stage, so the most significant plane is dark
+        assert_eq!(display.serialize_row(0, 6)[0], crate::sink::B1);
+        assert_eq!(display.serialize_row(0, 7)[0], 0);
+    }
+
+    #[test]
+    fn buffered_draws() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let color = Rgb565::new(15, 31, 1);
+        let expected = Gamma::LINEAR.correct_rgb565(color);
+
+        display.set_gamma(Gamma::LINEAR);
+
+        let mut back = FrameBuffer::new();
+        let mut buffered = DoubleBuffered::new(&mut display, &mut back);
+
+        Pixel(Point::zero(), color).draw(&mut buffered).unwrap();
+        buffered.swap();
+        buffered.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(buffered.display().frame.pixel(0, 0), Some(&expected));
+
+        let mut shared = SharedFrame::new();
+        let (mut drawer, mut outputter) = display.split(&mut shared);
+
+        Pixel(Point::new(1, 0), color).draw(&mut drawer).unwrap();
+        drawer.present();
+
+        assert!(outputter.take_presented());
+        assert_eq!(outputter.display().frame.pixel(1, 0), Some(&expected));
+
+        let mut display = display.with_color_pipeline(&Swapped);
+        let mut queue = FrameQueue::<64, 32, 1>::new();
+        let (mut producer, mut consumer) = queue.split();
+
+        // converted when loaded, then drawn through the display's pipeline
+        for _ in 0..2 {
+            producer
+                .push_with(|frame| frame.clear(Rgb565::RED).unwrap())
+                .unwrap();
+
+            assert!(consumer.pop_into(DropPolicy::Oldest, &mut display));
+            assert_eq!(display.frame.pixel(0, 0), Some(&(0, 0, 255)));
+        }
+    }
+
+    #[test]
+    fn distinct_pipelines() {
+        static SWAPPED: Swapped = Swapped;
+        static UNCHANGED: Unchanged = Unchanged;
+        static TABLE: Gamma = Gamma::LINEAR;
+        static OTHER_TABLE: Gamma = Gamma::CIE1931;
+
+        let (table, other_table): (&'static Gamma, &'static Gamma) = (&TABLE, &OTHER_TABLE);
+
+        let swapped = (&SWAPPED).selected();
+
+        // zero-sized pipelines of the same type behave the same, wherever they are
+        assert!(swapped.same(&(&Swapped).selected()));
+        // even if they share an address with another type
+        assert!(!swapped.same(&(&UNCHANGED).selected()));
+        assert!(!swapped.same(&Gamma::POWER.selected()));
+        // tables passed as a custom pipeline are the same by address
+        assert!(table.selected().same(&table.selected()));
+        assert!(!table.selected().same(&other_table.selected()));
+    }
+}
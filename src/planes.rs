@@ -0,0 +1,305 @@
+//! Displays storing their pixels as prepacked bit planes.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{
+    fmt, gamma::Gamma, pins::*, pipeline::ColorPipeline, sink, ConfigError, DelayProvider,
+    FrameTimeCompensation,
+};
+
+/// The pixel values of a `WIDTH` by `2 * SCAN` display, as packed rows (see
+/// [`sink`] for the format) of every bit plane and scan row.
+///
+/// Storage is allocated for the maximum of 8 bit planes, taking 8 bytes per pixel
+/// pair rather than the 6 of a [`FrameBuffer`](crate::FrameBuffer).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitPlanes<const WIDTH: usize, const SCAN: usize> {
+    data: [[[u8; WIDTH]; SCAN]; 8],
+}
+
+impl<const WIDTH: usize, const SCAN: usize> BitPlanes<WIDTH, SCAN> {
+    /// Create all black bit planes.
+    pub const fn new() -> Self {
+        Self {
+            data: [[[0; WIDTH]; SCAN]; 8],
+        }
+    }
+
+    /// The packed row `row` of the bit plane `mask`.
+    ///
+    /// *`row` must be less than `SCAN` and `mask` less than 8.*
+    pub fn row(&self, row: u8, mask: u8) -> &[u8; WIDTH] {
+        &self.data[mask as usize][row as usize]
+    }
+
+    /// Pack a (gamma corrected) color into every bit plane of the provided pixel,
+    /// if it is in bounds.
+    fn set<const BITS: u8>(&mut self, x: usize, y: usize, color: &(u8, u8, u8)) {
+        if x >= WIDTH || y >= 2 * SCAN {
+            return;
+        }
+
+        let (row, lower) = (y % SCAN, y >= SCAN);
+        let clear = if lower {
+            sink::R2 | sink::G2 | sink::B2
+        } else {
+            sink::R1 | sink::G1 | sink::B1
+        };
+
+        for mask in 0..BITS {
+            let bits = if lower {
+                sink::pack::<BITS>(&(0, 0, 0), color, mask)
+            } else {
+                sink::pack::<BITS>(color, &(0, 0, 0), mask)
+            };
+            let column = &mut self.data[mask as usize][row][x];
+
+            *column = (*column & !clear) | bits;
+        }
+    }
+}
+
+impl<const WIDTH: usize, const SCAN: usize> Default for BitPlanes<WIDTH, SCAN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A `WIDTH` by `HEIGHT` display with `SCAN` scan rows and 2 colors written at a
+/// time, packing drawn pixels into [`BitPlanes`].
+///
+/// Pixels are quantized and packed as they are drawn, so the output only reads
+/// prepacked rows and toggles the pins, with no per channel computation. This
+/// maximizes the refresh rate on slow MCUs, for displays which are drawn to far
+/// less often than they are output.
+///
+/// This variant only provides drawing and output; the configuration of
+/// [`Hub75`](crate::Hub75) (color temperature, blending, pacing, etc.) is not
+/// available.
+pub struct Hub75Planes<
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+> {
+    planes: BitPlanes<WIDTH, SCAN>,
+    latch_guard_us: u32,
+    ftc: FrameTimeCompensation<BITS>,
+    color_pins: ColorPins,
+    row_pins: RowPins,
+    data_pins: DataPins,
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Planes<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new display instance, with the same arguments as
+    /// [`Hub75::new`](crate::Hub75::new).
+    pub fn new(
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        const {
+            assert!(HEIGHT == 2 * SCAN, "HEIGHT must be twice SCAN");
+            assert!(BITS >= 1 && BITS <= 8, "BITS must be in 1..=8");
+        };
+
+        fmt::trace!(
+            "new Hub75Planes with {}x{} pixels, {} scan rows and {} bits",
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS
+        );
+
+        Self {
+            planes: BitPlanes::new(),
+            latch_guard_us: 1,
+            ftc: FrameTimeCompensation::from_ratio(on_ratio),
+            color_pins,
+            row_pins,
+            data_pins,
+        }
+    }
+
+    /// Output the bit planes to the display.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        for row in 0..SCAN as u8 {
+            for mask in self.ftc.masks() {
+                sink::shift_packed(
+                    &mut self.color_pins,
+                    &mut self.data_pins,
+                    delay,
+                    self.planes.row(row, mask),
+                )?;
+
+                // the previous row is shown until the first bit plane is shifted
+                if mask == self.ftc.masks().start {
+                    self.data_pins.wait_shown();
+                    self.row_pins.set_row(&row)?;
+                }
+
+                sink::latch_and_show(
+                    &mut self.data_pins,
+                    delay,
+                    self.latch_guard_us,
+                    self.ftc.duration(&mask),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the time (in microseconds) waited between disabling the output and
+    /// latching the next row, 1µs by default.
+    pub fn set_latch_guard_us(&mut self, guard: u32) {
+        self.latch_guard_us = guard;
+    }
+
+    /// Set the number of bit planes shown, trading color depth for refresh rate
+    /// (see [`Hub75::set_active_bits`](crate::Hub75::set_active_bits)).
+    ///
+    /// `bits` must be in `1..=BITS`.
+    pub fn set_active_bits(&mut self, bits: u8) -> Result<(), ConfigError> {
+        self.ftc.set_active_bits(bits)
+    }
+
+    /// The number of bit planes shown.
+    pub fn active_bits(&self) -> u8 {
+        self.ftc.active_bits
+    }
+
+    /// Set the minimum time (in microseconds) any bit plane is shown
+    /// (see [`Hub75::set_min_show_us`](crate::Hub75::set_min_show_us)).
+    pub fn set_min_show_us(&mut self, min: u32) {
+        self.ftc.min_show_us = min;
+    }
+
+    /// The bit planes of the display.
+    pub fn frame(&self) -> &BitPlanes<WIDTH, SCAN> {
+        &self.planes
+    }
+
+    /// Set the bit planes to all black.
+    pub fn wipe(&mut self) {
+        self.planes = BitPlanes::new();
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > Dimensions for Hub75Planes<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > DrawTarget for Hub75Planes<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x >= 0 && coord.y >= 0 {
+                self.planes.set::<BITS>(
+                    coord.x as usize,
+                    coord.y as usize,
+                    &Gamma::POWER.correct_rgb565(color),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal::mock::PinState::High, test_utils::*};
+    use embedded_graphics::{
+        pixelcolor::RgbColor,
+        prelude::*,
+        primitives::{PrimitiveStyle, Rectangle},
+    };
+
+    #[test]
+    fn output_matches_framebuffer() {
+        let reference = Log::default();
+        let log = Log::default();
+        let mut framebuffer = display::<4>(&reference);
+        let mut planes = Hub75Planes::<64, 32, 16, 4, _, _, _>::new(
+            color_pins(&log),
+            row_pins(&log),
+            data_pins(&log),
+            0.5,
+        );
+
+        for (color, offset) in [
+            (Rgb565::CYAN, 3),
+            (Rgb565::new(9, 40, 17), 20),
+            (Rgb565::BLACK, 5),
+        ] {
+            let rectangle = Rectangle::new(Point::new(offset, offset), Size::new(30, 9))
+                .into_styled(PrimitiveStyle::with_fill(color));
+
+            rectangle.draw(&mut framebuffer).unwrap();
+            rectangle.draw(&mut planes).unwrap();
+        }
+
+        framebuffer.output(&mut ProbeDelay(&reference)).unwrap();
+        planes.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert!(shifted_colors(&log)
+            .iter()
+            .any(|states| states.contains(&High)));
+        assert_eq!(*log.borrow(), *reference.borrow());
+    }
+}
@@ -0,0 +1,161 @@
+//! Power estimation and current limiting.
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameStorage, Hub75};
+
+/// A current budget, and the model estimating the current drawn by a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PowerLimit {
+    /// The average current (in mA) drawn by a red, green and blue sub-pixel at full
+    /// duty, i.e. showing full white at full brightness.
+    ///
+    /// This is best measured, e.g. as the current of an all-white frame divided by
+    /// the number of pixels.
+    pub ma_per_subpixel: (u16, u16, u16),
+    /// The current (in mA) the display may draw on average.
+    pub budget_ma: u32,
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Limit the estimated current of the display, scaling the brightness down
+    /// whenever a frame would exceed the budget (e.g. of the power supply).
+    ///
+    /// The estimate is updated at the start of every frame (and on
+    /// [`set_brightness`](Self::set_brightness)), so the limit holds from the first
+    /// frame showing a changed framebuffer or brightness on. The scaling is
+    /// independent of the [global brightness](Self::set_brightness), which is kept
+    /// as set.
+    ///
+    /// The estimate includes the color temperature and white balance, but not the
+    /// row brightness or brightness mask, so it errs on the high side.
+    pub fn set_power_limit(&mut self, limit: Option<PowerLimit>) {
+        self.power_limit = limit;
+        self.limit_power();
+    }
+
+    /// The estimated average current (in mA) drawn by the current frame, including
+    /// the power limit.
+    ///
+    /// Without a [power limit](Self::set_power_limit), this is always 0.
+    pub fn estimated_current_ma(&self) -> u32 {
+        let unlimited = self.unlimited_current_ma();
+
+        (unlimited * self.ftc.limit as u64 / 255) as u32
+    }
+
+    /// The estimated average current (in mA) drawn by the current frame at the
+    /// current brightness, without limiting.
+    fn unlimited_current_ma(&self) -> u64 {
+        let Some(PowerLimit {
+            ma_per_subpixel: (r, g, b),
+            ..
+        }) = self.power_limit
+        else {
+            return 0;
+        };
+
+        let (sums, scale) = (self.power_sums.1, self.channel_scale);
+        let channel = |sum: u32, ma: u16, scale: u8| sum as u64 * ma as u64 * (scale as u64 + 1);
+
+        // stored values and scales are both in 1/255ths (scales in 1/256ths)
+        (channel(sums[0], r, scale.0) + channel(sums[1], g, scale.1) + channel(sums[2], b, scale.2))
+            * self.ftc.brightness as u64
+            / (255 * 256 * 255)
+    }
+
+    /// Update the power limit for the current frame.
+    pub(crate) fn limit_power(&mut self) {
+        let Some(limit) = self.power_limit else {
+            self.ftc.limit = 255;
+
+            return;
+        };
+
+        if self.power_sums.0 != Some(self.generation) {
+            let mut sums = [0u32; 3];
+
+            for pixel in self.frame.pixels() {
+                sums[0] += pixel.0 as u32;
+                sums[1] += pixel.1 as u32;
+                sums[2] += pixel.2 as u32;
+            }
+
+            self.power_sums = (Some(self.generation), sums);
+        }
+
+        let current = self.unlimited_current_ma();
+
+        self.ftc.limit = if current > limit.budget_ma as u64 {
+            (limit.budget_ma as u64 * 255 / current) as u8
+        } else {
+            255
+        };
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn limit_white_frames() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+        let limit = PowerLimit {
+            ma_per_subpixel: (10, 10, 5),
+            budget_ma: 20_000,
+        };
+
+        display.set_power_limit(Some(limit));
+
+        assert_eq!(display.estimated_current_ma(), 0);
+
+        for (x, y) in (0..64).flat_map(|x| (0..32).map(move |y| (x, y))) {
+            display.set_pixel_raw(x, y, (255, 255, 255)).unwrap();
+        }
+
+        // the total delay of a frame, of which only the show durations change
+        let frame_us = |display: &mut ProbeDisplay<'_, 2>| {
+            log.borrow_mut().clear();
+            display.output(&mut ProbeDelay(&log)).unwrap();
+
+            log.borrow()
+                .iter()
+                .map(|event| match event {
+                    Event::Delay(us) => *us,
+                    _ => 0,
+                })
+                .sum::<u32>()
+        };
+
+        // the first frame of the new content is already limited
+        let first = frame_us(&mut display);
+
+        // 64 * 32 * 25mA = 51.2A, so scaled to 20A
+        assert_eq!(display.ftc.limit, 99);
+        assert_eq!(frame_us(&mut display), first);
+        assert!(display.estimated_current_ma() <= 20_000);
+        assert_eq!(display.brightness(), 255);
+
+        display.set_brightness(100);
+
+        // 51.2A * 100 / 255 = 20.08A
+        assert_eq!(display.ftc.limit, 254);
+
+        display.set_power_limit(None);
+
+        assert_eq!(display.ftc.limit, 255);
+    }
+}
@@ -0,0 +1,263 @@
+//! Displays with four parallel color groups (R1..R4), each driving a quarter.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    draw_target::DrawTarget, geometry::Dimensions, pixelcolor::Rgb565, primitives::Rectangle, Pixel,
+};
+
+use crate::{fmt, pins::*, sink, ConfigError, DelayProvider, FrameBuffer, FrameTimeCompensation};
+
+/// A `WIDTH` by `HEIGHT` display with `SCAN` scan rows and 4 colors written at a
+/// time.
+///
+/// Each scan row selects one row of every quarter, so `HEIGHT` must be `4 * SCAN`,
+/// and a row is shifted in a quarter of the pixels compared to a two group display
+/// of the same size.
+///
+/// This variant only provides drawing and output; the configuration of
+/// [`Hub75`](crate::Hub75) (color temperature, blending, pacing, etc.) is not
+/// available.
+pub struct Hub75Quad<
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsQuadColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+> {
+    frame: FrameBuffer<WIDTH, HEIGHT>,
+    latch_guard_us: u32,
+    ftc: FrameTimeCompensation<BITS>,
+    color_pins: ColorPins,
+    row_pins: RowPins,
+    data_pins: DataPins,
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Quad<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsQuadColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new display instance.
+    ///
+    /// `color_pins` are the four color groups from the top quarter down, and
+    /// `on_ratio` behaves as for [`Hub75::new`](crate::Hub75::new).
+    pub fn new(
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        const { assert!(HEIGHT == 4 * SCAN, "HEIGHT must be four times SCAN") };
+
+        fmt::trace!(
+            "new Hub75Quad with {}x{} pixels, {} scan rows and {} bits",
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS
+        );
+
+        Self {
+            frame: FrameBuffer::new(),
+            latch_guard_us: 1,
+            ftc: FrameTimeCompensation::from_ratio(on_ratio),
+            color_pins,
+            row_pins,
+            data_pins,
+        }
+    }
+
+    /// Output the framebuffer to the display.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        let data = &self.frame.data;
+
+        for row in 0..SCAN {
+            self.data_pins.wait_shown();
+            self.row_pins.set_row(&(row as u8))?;
+
+            let quarters = [row, row + SCAN, row + 2 * SCAN, row + 3 * SCAN].map(|y| &data[y]);
+
+            for mask in self.ftc.masks() {
+                for x in 0..WIDTH {
+                    self.color_pins
+                        .set_colors::<BITS>(&quarters.map(|quarter| quarter[x]), &mask)?;
+                    self.data_pins.shift(delay)?;
+                }
+
+                sink::latch_and_show(
+                    &mut self.data_pins,
+                    delay,
+                    self.latch_guard_us,
+                    self.ftc.duration(&mask),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the time (in microseconds) waited between disabling the output and
+    /// latching the next row, 1µs by default.
+    pub fn set_latch_guard_us(&mut self, guard: u32) {
+        self.latch_guard_us = guard;
+    }
+
+    /// Set the number of bit planes shown, trading color depth for refresh rate
+    /// (see [`Hub75::set_active_bits`](crate::Hub75::set_active_bits)).
+    ///
+    /// `bits` must be in `1..=BITS`.
+    pub fn set_active_bits(&mut self, bits: u8) -> Result<(), ConfigError> {
+        self.ftc.set_active_bits(bits)
+    }
+
+    /// The number of bit planes shown.
+    pub fn active_bits(&self) -> u8 {
+        self.ftc.active_bits
+    }
+
+    /// Set the minimum time (in microseconds) any bit plane is shown
+    /// (see [`Hub75::set_min_show_us`](crate::Hub75::set_min_show_us)).
+    pub fn set_min_show_us(&mut self, min: u32) {
+        self.ftc.min_show_us = min;
+    }
+
+    /// The framebuffer of the display.
+    pub fn frame(&self) -> &FrameBuffer<WIDTH, HEIGHT> {
+        &self.frame
+    }
+
+    /// Set the framebuffer to all black.
+    pub fn wipe(&mut self) {
+        self.frame = FrameBuffer::new();
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsQuadColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > Dimensions for Hub75Quad<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.frame.bounding_box()
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsQuadColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > DrawTarget for Hub75Quad<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.frame.draw_iter(pixels)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal::mock::PinState::*, test_utils::*};
+    use embedded_graphics::{
+        geometry::{Point, Size},
+        pixelcolor::RgbColor,
+        Drawable,
+    };
+
+    #[test]
+    fn output_quarters() {
+        let log = Log::default();
+        let (upper, lower) = color_pins(&log);
+        let (a, b, c, _) = row_pins(&log);
+        let mut display = Hub75Quad::<64, 32, 8, 1, _, _, _>::new(
+            (upper, lower, quarter_pins(&log), quarter_pins(&log)),
+            (a, b, c),
+            data_pins(&log),
+            0.5,
+        );
+
+        assert_eq!(display.bounding_box().size, Size::new(64, 32));
+
+        Pixel(Point::new(0, 8), Rgb565::BLUE)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(1, 31), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 64 * 8);
+        assert_eq!(shifted[0], [Low, Low, Low, Low, Low, High]);
+        assert_eq!(shifted[1], [Low; 6]);
+        assert_eq!(shifted[7 * 64], [Low; 6]);
+        assert_eq!(
+            log.borrow()
+                .iter()
+                .filter(|event| **event == Event::Pin(PinId::Quarter, High))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn shared_clock_stacked_chains() {
+        let log = Log::default();
+        let (upper, lower) = color_pins(&log);
+        let (a, b, c, _) = row_pins(&log);
+        let (clk, lat, oe) = data_pins(&log);
+        let other_oe = quarter_pins(&log).0;
+        let mut data_pins = SharedClockDataPins::new(clk, lat, [oe, other_oe]);
+
+        data_pins.set_enabled(1, false);
+
+        let mut display = Hub75Quad::<64, 32, 8, 1, _, _, _>::new(
+            (upper, lower, quarter_pins(&log), quarter_pins(&log)),
+            (a, b, c),
+            data_pins,
+            0.5,
+        );
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let events = log.borrow();
+        let count = |event| events.iter().filter(|e| **e == event).count();
+
+        // shifted once for both chains
+        assert_eq!(count(Event::Pin(PinId::Clk, High)), 64 * 8);
+        // the first chain is shown, the blanked one only disabled
+        assert_eq!(count(Event::Pin(PinId::Oe, Low)), 8);
+        assert_eq!(count(Event::Pin(PinId::Quarter, Low)), 64 * 8 * 6);
+    }
+}
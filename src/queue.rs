@@ -0,0 +1,276 @@
+//! A fixed capacity queue of frames from a rendering task to a refresh task.
+//!
+//! Unlike a [`TripleBuffer`](crate::buffer::TripleBuffer), which only ever keeps
+//! the newest frame, the queue keeps up to `N` frames in order, so bursts of
+//! rendering can be shown at the refresh rate. Frames are rendered in place into
+//! the queue, and the frame-drop policy is explicit on both sides:
+//!
+//! - a full queue rejects new frames with [`Full`] (dropping the newest), and
+//! - the consumer pops either the oldest frame or, with [`DropPolicy::Latest`],
+//!   the newest one, dropping the older ones (catching up with the renderer).
+//!
+//! The queue is single-producer single-consumer, and only needs atomic loads and
+//! stores, so it is available on all targets.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameBuffer, FrameStorage, Hub75};
+
+/// The error of pushing to a full [`FrameQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Full;
+
+/// Which queued frame the consumer pops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DropPolicy {
+    /// The oldest frame, showing every frame.
+    #[default]
+    Oldest,
+    /// The newest frame, dropping all older ones.
+    Latest,
+}
+
+/// A queue of up to `N` frames.
+pub struct FrameQueue<const WIDTH: usize, const HEIGHT: usize, const N: usize> {
+    frames: [UnsafeCell<FrameBuffer<WIDTH, HEIGHT>>; N],
+    /// The number of frames popped so far, modulo `2 * N`.
+    head: AtomicUsize,
+    /// The number of frames pushed so far, modulo `2 * N`.
+    tail: AtomicUsize,
+}
+
+// SAFETY: the slots between head and tail are only accessed by the consumer, and
+// the others only by the producer, with the counters handing slots over with
+// acquire/release ordering.
+unsafe impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> Sync
+    for FrameQueue<WIDTH, HEIGHT, N>
+{
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> FrameQueue<WIDTH, HEIGHT, N> {
+    /// Create an empty queue, which can be placed in a `static`.
+    pub const fn new() -> Self {
+        const {
+            assert!(N > 0, "the queue must hold at least one frame");
+        };
+
+        Self {
+            frames: [const { UnsafeCell::new(FrameBuffer::new()) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split into the producer and consumer halves.
+    pub fn split(
+        &mut self,
+    ) -> (
+        FrameProducer<'_, WIDTH, HEIGHT, N>,
+        FrameConsumer<'_, WIDTH, HEIGHT, N>,
+    ) {
+        let queue = &*self;
+
+        (FrameProducer { queue }, FrameConsumer { queue })
+    }
+
+    /// The number of queued frames.
+    fn len(&self) -> usize {
+        (self.tail.load(Ordering::Acquire) + 2 * N - self.head.load(Ordering::Acquire)) % (2 * N)
+    }
+
+    /// Advance a counter by `count` frames.
+    ///
+    /// Counters wrap at `2 * N` rather than at `usize::MAX`, so a full queue is
+    /// distinguishable from an empty one and the slot index stays continuous for
+    /// any `N`.
+    const fn advance(counter: usize, count: usize) -> usize {
+        (counter + count) % (2 * N)
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> Default
+    for FrameQueue<WIDTH, HEIGHT, N>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The rendering half of a [`FrameQueue`].
+pub struct FrameProducer<'a, const WIDTH: usize, const HEIGHT: usize, const N: usize> {
+    queue: &'a FrameQueue<WIDTH, HEIGHT, N>,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> FrameProducer<'_, WIDTH, HEIGHT, N> {
+    /// Render a frame with `render` in place and queue it, unless the queue is
+    /// full.
+    ///
+    /// The frame passed to `render` holds stale content, so it should be drawn
+    /// completely (e.g. starting with a [`clear`](embedded_graphics::draw_target::DrawTarget::clear)).
+    pub fn push_with(
+        &mut self,
+        render: impl FnOnce(&mut FrameBuffer<WIDTH, HEIGHT>),
+    ) -> Result<(), Full> {
+        if self.queue.len() >= N {
+            return Err(Full);
+        }
+
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+
+        // SAFETY: the slot at the tail is not queued, so the consumer does not access
+        // it
+        render(unsafe { &mut *self.queue.frames[tail % N].get() });
+        self.queue.tail.store(
+            FrameQueue::<WIDTH, HEIGHT, N>::advance(tail, 1),
+            Ordering::Release,
+        );
+
+        Ok(())
+    }
+
+    /// Whether the next push would fail.
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= N
+    }
+}
+
+/// The refresh half of a [`FrameQueue`].
+pub struct FrameConsumer<'a, const WIDTH: usize, const HEIGHT: usize, const N: usize> {
+    queue: &'a FrameQueue<WIDTH, HEIGHT, N>,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, const N: usize> FrameConsumer<'_, WIDTH, HEIGHT, N> {
+    /// The number of queued frames.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Whether no frames are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pop a frame according to `policy` into `display`, returning whether there
+    /// was one.
+    ///
+    /// Call this at frame boundaries (e.g. before [`Hub75::output`]) to avoid
+    /// tearing.
+    pub fn pop_into<
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    >(
+        &mut self,
+        policy: DropPolicy,
+        display: &mut Hub75<
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS,
+            ColorPins,
+            RowPins,
+            DataPins,
+            Frame,
+            Pipeline,
+        >,
+    ) -> bool {
+        let queued = self.len();
+
+        if queued == 0 {
+            return false;
+        }
+
+        let skipped = match policy {
+            DropPolicy::Oldest => 0,
+            DropPolicy::Latest => queued - 1,
+        };
+        let head = FrameQueue::<WIDTH, HEIGHT, N>::advance(
+            self.queue.head.load(Ordering::Relaxed),
+            skipped,
+        );
+
+        // SAFETY: the slot at the head is queued, so the producer does not access it
+        display.load_frame(unsafe { &mut *self.queue.frames[head % N].get() });
+        self.queue.head.store(
+            FrameQueue::<WIDTH, HEIGHT, N>::advance(head, 1),
+            Ordering::Release,
+        );
+
+        true
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn drop_policies() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut queue = FrameQueue::<64, 32, 2>::new();
+        let (mut producer, mut consumer) = queue.split();
+        let mut push = |color| producer.push_with(|frame| frame.clear(color).unwrap());
+
+        assert_eq!(push(Rgb565::RED), Ok(()));
+        assert_eq!(push(Rgb565::GREEN), Ok(()));
+        assert_eq!(push(Rgb565::BLUE), Err(Full));
+        assert_eq!(consumer.len(), 2);
+
+        assert!(consumer.pop_into(DropPolicy::Oldest, &mut display));
+        assert_eq!(display.frame.data[0][0], (255, 0, 0));
+
+        assert_eq!(push(Rgb565::BLUE), Ok(()));
+        assert!(consumer.pop_into(DropPolicy::Latest, &mut display));
+        assert_eq!(display.frame.data[0][0], (0, 0, 255));
+
+        assert!(consumer.is_empty());
+        assert!(!consumer.pop_into(DropPolicy::Oldest, &mut display));
+    }
+
+    #[test]
+    fn wraps_any_capacity() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut queue = FrameQueue::<64, 32, 3>::new();
+        let (mut producer, mut consumer) = queue.split();
+        let colors = [
+            (Rgb565::RED, (255, 0, 0)),
+            (Rgb565::GREEN, (0, 255, 0)),
+            (Rgb565::BLUE, (0, 0, 255)),
+            (Rgb565::WHITE, (255, 255, 255)),
+        ];
+
+        // cycle through every slot several times, past the counter wrap at 2N
+        for round in 0..5 {
+            for (color, _) in colors.iter().skip(round % 2).take(3) {
+                assert_eq!(
+                    producer.push_with(|frame| frame.clear(*color).unwrap()),
+                    Ok(())
+                );
+            }
+            assert!(producer.is_full());
+
+            for (_, stored) in colors.iter().skip(round % 2).take(3) {
+                assert!(consumer.pop_into(DropPolicy::Oldest, &mut display));
+                assert_eq!(display.frame.data[0][0], *stored);
+            }
+            assert!(consumer.is_empty());
+        }
+    }
+}
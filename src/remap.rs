@@ -0,0 +1,361 @@
+//! Remapping of logical display coordinates onto the shift geometry of panels
+//! whose wiring does not match their visual layout.
+//!
+//! The display driver is sized by the *electrical* geometry of a panel: the number
+//! of columns shifted per scan row and the number of scan rows. A [`PanelMapping`]
+//! locates every logical pixel in this geometry, and [`Remapped`] wraps such a
+//! display, exposing the *logical* geometry for drawing.
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// The half of a display driven by a group of color pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Half {
+    /// Driven by R1, G1, B1.
+    Upper,
+    /// Driven by R2, G2, B2.
+    Lower,
+}
+
+/// The location of a pixel in the shift geometry of a display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ShiftLocation {
+    /// The position in the shifted row, starting with the column shifted first.
+    pub column: u16,
+    /// The scan row.
+    pub row: u8,
+    /// The color pin group.
+    pub half: Half,
+}
+
+impl ShiftLocation {
+    /// The point of the location on a display with `scan` scan rows.
+    const fn to_point(self, scan: u32) -> Point {
+        let y = match self.half {
+            Half::Upper => self.row as u32,
+            Half::Lower => self.row as u32 + scan,
+        };
+
+        Point::new(self.column as i32, y as i32)
+    }
+}
+
+/// A mapping from logical coordinates to the shift geometry of the driving
+/// display.
+pub trait PanelMapping {
+    /// The logical size of the panel.
+    fn size(&self) -> Size;
+
+    /// Locate a logical point on the driving display, if it is in bounds.
+    fn map(&self, point: Point) -> Option<ShiftLocation>;
+}
+
+/// Whether a point lies within a size.
+fn contains(size: Size, point: Point) -> bool {
+    point.x >= 0 && point.y >= 0 && (point.x as u32) < size.width && (point.y as u32) < size.height
+}
+
+/// The mapping of a panel whose wiring matches its visual layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Identity {
+    size: Size,
+}
+
+impl Identity {
+    /// Describe a panel of the provided size.
+    pub const fn new(size: Size) -> Self {
+        Self { size }
+    }
+}
+
+impl PanelMapping for Identity {
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        if !contains(self.size, point) {
+            return None;
+        }
+
+        let scan = self.size.height / 2;
+        let (x, y) = (point.x as u32, point.y as u32);
+
+        Some(ShiftLocation {
+            column: x as u16,
+            row: (y % scan) as u8,
+            half: if y < scan { Half::Upper } else { Half::Lower },
+        })
+    }
+}
+
+/// A panel whose rows are folded into fewer scan rows, with the pixels of the
+/// folded rows interleaved in blocks of columns.
+///
+/// Each scan row selects `height / (2 * scan)` rows of each half. For every `block`
+/// columns, the panel shifts `block` pixels of each of these rows in turn, so the
+/// panel is driven as a display `height / (2 * scan)` times wider and with `2 *
+/// scan` rows (see [`electrical_size`](Self::electrical_size)).
+///
+/// With `reversed` set, the rows within a block group are shifted bottom to top,
+/// e.g. for a fold of two:
+///
+/// ```text
+/// shift position   0..8       8..16      16..24     24..32   ...
+/// logical row      y + scan   y          y + scan   y        ...
+/// logical column   0..8       0..8       8..16      8..16    ...
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Interleaved {
+    size: Size,
+    scan: u32,
+    block: u32,
+    reversed: bool,
+}
+
+impl Interleaved {
+    /// Describe a panel of the logical `size` with `scan` scan rows, interleaving
+    /// blocks of `block` columns.
+    ///
+    /// *`size.height` must be a multiple of `2 * scan`.*
+    pub const fn new(size: Size, scan: u32, block: u32, reversed: bool) -> Self {
+        assert!(size.height.is_multiple_of(2 * scan));
+
+        Self {
+            size,
+            scan,
+            block,
+            reversed,
+        }
+    }
+
+    /// The number of rows of each half selected per scan row.
+    const fn fold(&self) -> u32 {
+        self.size.height / (2 * self.scan)
+    }
+
+    /// The size of the display driving the panel.
+    pub const fn electrical_size(&self) -> Size {
+        Size::new(self.size.width * self.fold(), 2 * self.scan)
+    }
+}
+
+impl PanelMapping for Interleaved {
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        if !contains(self.size, point) {
+            return None;
+        }
+
+        let (x, y) = (point.x as u32, point.y as u32);
+        let (fold, block) = (self.fold(), self.block);
+        let (half, y) = (y / (self.size.height / 2), y % (self.size.height / 2));
+
+        let slot = if self.reversed {
+            fold - 1 - y / self.scan
+        } else {
+            y / self.scan
+        };
+
+        Some(ShiftLocation {
+            column: (x / block * block * fold + slot * block + x % block) as u16,
+            row: (y % self.scan) as u8,
+            half: if half == 0 { Half::Upper } else { Half::Lower },
+        })
+    }
+}
+
+/// A 1/8 scan "outdoor" 64x32 panel, driven as a 128x16 display with 8 scan rows.
+///
+/// Each scan row selects four rows, two of each half. For every 8 columns, the
+/// panel shifts 8 pixels of the lower of the two rows of a half, followed by 8
+/// pixels of the upper one.
+///
+/// This is the most common variant; panels with other orders can be described
+/// with [`Interleaved`] or implement [`PanelMapping`] themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FoldedEighthScan;
+
+impl FoldedEighthScan {
+    const LAYOUT: Interleaved = Interleaved::new(Size::new(64, 32), 8, 8, true);
+}
+
+impl PanelMapping for FoldedEighthScan {
+    fn size(&self) -> Size {
+        Self::LAYOUT.size()
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        Self::LAYOUT.map(point)
+    }
+}
+
+/// A 1/4 scan 32x16 panel (e.g. P10), driven as a 64x8 display with 4 scan rows.
+///
+/// Each scan row selects four rows, two of each half, interleaved in blocks of 8
+/// columns like [`FoldedEighthScan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QuarterScan;
+
+impl QuarterScan {
+    const LAYOUT: Interleaved = Interleaved::new(Size::new(32, 16), 4, 8, true);
+}
+
+impl PanelMapping for QuarterScan {
+    fn size(&self) -> Size {
+        Self::LAYOUT.size()
+    }
+
+    fn map(&self, point: Point) -> Option<ShiftLocation> {
+        Self::LAYOUT.map(point)
+    }
+}
+
+/// A draw target drawing through a [`PanelMapping`] into another draw target.
+///
+/// The target's bounding box is its shift geometry, with the upper and lower
+/// halves stacked, as is the case for the framebuffer and the display drivers.
+pub struct Remapped<'a, T, M> {
+    target: &'a mut T,
+    mapping: M,
+}
+
+impl<'a, T, M> Remapped<'a, T, M> {
+    pub fn new(target: &'a mut T, mapping: M) -> Self {
+        Self { target, mapping }
+    }
+}
+
+impl<T, M: PanelMapping> Dimensions for Remapped<'_, T, M> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), self.mapping.size())
+    }
+}
+
+impl<T: DrawTarget, M: PanelMapping> DrawTarget for Remapped<'_, T, M> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let mapping = &self.mapping;
+        let scan = self.target.bounding_box().size.height / 2;
+
+        self.target
+            .draw_iter(pixels.into_iter().filter_map(|Pixel(point, color)| {
+                Some(Pixel(mapping.map(point)?.to_point(scan), color))
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameBuffer;
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        Drawable,
+    };
+
+    #[test]
+    fn folded_eighth_scan_corners() {
+        let fold = Points(FoldedEighthScan, 8);
+
+        assert_eq!(fold.map(Point::new(0, 0)), Some(Point::new(8, 0)));
+        assert_eq!(fold.map(Point::new(0, 8)), Some(Point::new(0, 0)));
+        assert_eq!(fold.map(Point::new(9, 7)), Some(Point::new(25, 7)));
+        assert_eq!(fold.map(Point::new(0, 16)), Some(Point::new(8, 8)));
+        assert_eq!(fold.map(Point::new(63, 31)), Some(Point::new(119, 15)));
+        assert_eq!(fold.map(Point::new(64, 0)), None);
+        assert_eq!(fold.map(Point::new(0, -1)), None);
+    }
+
+    #[test]
+    fn quarter_scan_corners() {
+        let fold = Points(QuarterScan, 4);
+
+        assert_eq!(fold.map(Point::new(0, 0)), Some(Point::new(8, 0)));
+        assert_eq!(fold.map(Point::new(0, 4)), Some(Point::new(0, 0)));
+        assert_eq!(fold.map(Point::new(31, 15)), Some(Point::new(55, 7)));
+        assert_eq!(fold.map(Point::new(32, 0)), None);
+    }
+
+    /// A mapping to the points of a display with the provided scan rows.
+    struct Points<M>(M, u32);
+
+    impl<M: PanelMapping> Points<M> {
+        fn map(&self, point: Point) -> Option<Point> {
+            Some(self.0.map(point)?.to_point(self.1))
+        }
+    }
+
+    #[test]
+    fn identity() {
+        let identity = Identity::new(Size::new(64, 32));
+        let at = |column, row, half| Some(ShiftLocation { column, row, half });
+
+        assert_eq!(identity.map(Point::new(3, 0)), at(3, 0, Half::Upper));
+        assert_eq!(identity.map(Point::new(63, 15)), at(63, 15, Half::Upper));
+        assert_eq!(identity.map(Point::new(0, 16)), at(0, 0, Half::Lower));
+        assert_eq!(identity.map(Point::new(0, 32)), None);
+    }
+
+    /// Every logical pixel maps to a distinct pixel of the driving display.
+    fn assert_bijective(mapping: Interleaved) {
+        let size = mapping.size();
+        let electrical = mapping.electrical_size();
+        let mut hit = [[false; 256]; 32];
+
+        for y in 0..size.height as i32 {
+            for x in 0..size.width as i32 {
+                let Point { x, y } = Points(mapping, mapping.scan).map(Point::new(x, y)).unwrap();
+
+                assert!((x as u32) < electrical.width && (y as u32) < electrical.height);
+                assert!(!hit[y as usize][x as usize]);
+                hit[y as usize][x as usize] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn interleaved_is_bijective() {
+        assert_bijective(FoldedEighthScan::LAYOUT);
+        assert_bijective(QuarterScan::LAYOUT);
+        assert_bijective(Interleaved::new(Size::new(64, 32), 4, 4, false));
+        assert_bijective(Interleaved::new(Size::new(64, 32), 16, 8, false));
+    }
+
+    #[test]
+    fn remapped_draw() {
+        let mut frame = FrameBuffer::<128, 16>::new();
+        let mut remapped = Remapped::new(&mut frame, FoldedEighthScan);
+
+        assert_eq!(remapped.bounding_box().size, Size::new(64, 32));
+
+        Pixel(Point::new(0, 8), Rgb565::WHITE)
+            .draw(&mut remapped)
+            .unwrap();
+        Pixel(Point::new(64, 0), Rgb565::WHITE)
+            .draw(&mut remapped)
+            .unwrap();
+
+        assert_eq!(frame.pixel(0, 0), Some(&(255, 255, 255)));
+        assert_eq!(frame.pixels().filter(|c| **c != (0, 0, 0)).count(), 1);
+    }
+}
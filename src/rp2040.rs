@@ -0,0 +1,200 @@
+//! A reference [`RowSink`] and [`DmaBackend`] for the RP2040's PIO.
+//!
+//! With [`PioRowSink`], the PIO state machine receives, per row and bit plane:
+//!
+//! 1. one word holding the number of shifted columns minus one,
+//! 2. `WIDTH.div_ceil(4)` words holding the packed columns, four per word, least
+//!    significant byte first, the first word padded with leading black columns
+//!    (which are shifted out past the end of the chain),
+//! 3. one word holding the row address, shifted left by 6,
+//! 4. one word holding the show duration in state machine cycles, minus one.
+//!
+//! The `hub75_row` program of `pio/hub75.pio` shifts each column byte out with
+//! `out pins, 6` followed by `out null, 2`, toggling the clock via side-set, then
+//! selects the row (its address pins follow the color pins), pulses latch and
+//! holds output enable for the provided number of cycles.
+//!
+//! With [`PioDmaBackend`], the state machine only shifts: a DMA channel feeds it
+//! rendered rows (see [`stream`](crate::stream)), one sample per byte transfer.
+//! The `hub75_stream` program of `pio/hub75.pio` outputs each sample with
+//! `out pins, 8` to the consecutive R1, G1, B1, R2, G2, B2, CLK, and LAT pins,
+//! while row selection and output enable timing are left to the row and data pins
+//! of the display.
+//!
+//! The pin mapping and clock divider of the programs are application specific, so
+//! this module only provides the FIFO and buffer handling; the transmit FIFO of
+//! the state machine is abstracted by [`TxFifo`], and the DMA channel by
+//! [`PioTransfer`].
+
+use core::convert::Infallible;
+
+use crate::{dma::DmaBackend, sink::RowSink};
+
+/// The transmit FIFO of a PIO state machine.
+///
+/// For `rp2040-hal`, implement this for `Tx<SM>` by forwarding to its `write`
+/// method.
+pub trait TxFifo {
+    /// Push a word into the FIFO, returning `false` if it is full.
+    fn write(&mut self, word: u32) -> bool;
+}
+
+/// A DMA channel feeding the transmit FIFO of a PIO state machine.
+///
+/// For `rp2040-hal`, implement this by starting a single buffer transfer from the
+/// samples to the `Tx<SM>` of the state machine with 8 bit transfers, paced by its
+/// DREQ. Narrow writes are replicated across the FIFO word, of which the program
+/// (autopulling every 8 bits) outputs the least significant byte.
+pub trait PioTransfer {
+    /// Start transferring the samples.
+    ///
+    /// The samples are owned by the backend, which keeps them in place and
+    /// unmodified until [`is_idle`](Self::is_idle) returns `true`.
+    fn start(&mut self, samples: &[u8]);
+
+    /// Whether the last started transfer is complete, and the state machine
+    /// output every sample (e.g. the channel is done and `Tx::has_stalled`).
+    fn is_idle(&mut self) -> bool;
+}
+
+/// A row sink pushing packed rows into a PIO state machine.
+pub struct PioRowSink<Tx> {
+    tx: Tx,
+    cycles_per_us: u32,
+}
+
+impl<Tx: TxFifo> PioRowSink<Tx> {
+    /// Create a new PIO row sink, where the state machine executes
+    /// `cycles_per_us` instructions per microsecond.
+    pub fn new(tx: Tx, cycles_per_us: u32) -> Self {
+        Self { tx, cycles_per_us }
+    }
+
+    /// Release the underlying FIFO.
+    pub fn release(self) -> Tx {
+        self.tx
+    }
+
+    /// Push a word, blocking while the FIFO is full.
+    fn push(&mut self, word: u32) {
+        while !self.tx.write(word) {}
+    }
+}
+
+impl<Tx: TxFifo> RowSink for PioRowSink<Tx> {
+    type Error = Infallible;
+
+    fn send_row(
+        &mut self,
+        row: u8,
+        _mask: u8,
+        packed: &[u8],
+        show_ns: u32,
+    ) -> Result<(), Self::Error> {
+        let words = packed.len().div_ceil(4);
+        // the leading columns of the first word only pad it
+        let padding = words * 4 - packed.len();
+
+        self.push((words * 4).saturating_sub(1) as u32);
+
+        let mut word = [0; 4];
+
+        for (index, column) in packed.iter().enumerate() {
+            let index = padding + index;
+
+            word[index % 4] = *column;
+
+            if index % 4 == 3 {
+                self.push(u32::from_le_bytes(word));
+            }
+        }
+
+        let cycles = show_ns as u64 * self.cycles_per_us as u64 / 1000;
+
+        self.push((row as u32) << 6);
+        self.push((cycles as u32).saturating_sub(1));
+
+        Ok(())
+    }
+}
+
+/// A DMA backend feeding rendered rows into a PIO state machine, which only
+/// shifts and latches them.
+///
+/// `LEN` must be at least [`stream::row_len`](crate::stream::row_len) of the width
+/// of the display.
+pub struct PioDmaBackend<Transfer, const LEN: usize> {
+    transfer: Transfer,
+    buffer: [u8; LEN],
+}
+
+impl<Transfer: PioTransfer, const LEN: usize> PioDmaBackend<Transfer, LEN> {
+    pub fn new(transfer: Transfer) -> Self {
+        Self {
+            transfer,
+            buffer: [0; LEN],
+        }
+    }
+
+    /// Release the underlying transfer.
+    pub fn release(self) -> Transfer {
+        self.transfer
+    }
+}
+
+impl<Transfer: PioTransfer, const LEN: usize> DmaBackend<LEN> for PioDmaBackend<Transfer, LEN> {
+    type Error = Infallible;
+
+    fn buffer(&mut self) -> &mut [u8; LEN] {
+        &mut self.buffer
+    }
+
+    fn start(&mut self, len: usize) -> Result<(), Self::Error> {
+        self.transfer.start(&self.buffer[..len]);
+
+        Ok(())
+    }
+
+    fn is_complete(&mut self) -> bool {
+        self.transfer.is_idle()
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    struct Recorder(Vec<u32>);
+
+    impl TxFifo for Recorder {
+        fn write(&mut self, word: u32) -> bool {
+            self.0.push(word);
+
+            true
+        }
+    }
+
+    #[test]
+    fn row_sink_words() {
+        let mut sink = PioRowSink::new(Recorder(Vec::new()), 125);
+
+        sink.send_row(5, 0, &[1, 2, 3, 4, 5, 6], 1_500).unwrap();
+
+        assert_eq!(
+            sink.release().0,
+            [
+                7,
+                // padded with two leading black columns
+                u32::from_le_bytes([0, 0, 1, 2]),
+                u32::from_le_bytes([3, 4, 5, 6]),
+                5 << 6,
+                // 1.5us at 125 cycles per microsecond, minus one
+                186,
+            ]
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! Resumable scanning of the display, one (row, bit plane) pair at a time.
+
+use core::ops::Range;
+
+use crate::{
+    in_field, pins::*, pipeline::DisplayPipeline, sink, DelayProvider, Error, FrameStorage, Hub75,
+};
+
+/// The order in which the scan rows of a frame are output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScanOrder {
+    /// Every row after the previous one.
+    #[default]
+    Sequential,
+    /// The rows in bit-reversed order (e.g. 0, 8, 4, 12, 2, ... for 16 rows), so
+    /// consecutively output rows are spread across the display.
+    Interleaved,
+}
+
+/// The scan rows in the provided order.
+pub(crate) const fn scan_order<const SCAN: usize>(order: ScanOrder) -> [u8; SCAN] {
+    let mut rows = [0; SCAN];
+    let bits = SCAN.next_power_of_two().trailing_zeros();
+    let (mut index, mut row) = (0usize, 0);
+
+    while row < SCAN {
+        let next = match order {
+            ScanOrder::Sequential => index,
+            // rows past the end of a non power of two scan are skipped
+            ScanOrder::Interleaved if bits == 0 => index,
+            ScanOrder::Interleaved => index.reverse_bits() >> (usize::BITS - bits),
+        };
+
+        if next < SCAN {
+            rows[row] = next as u8;
+            row += 1;
+        }
+
+        index += 1;
+    }
+
+    rows
+}
+
+/// The progress of a resumable output call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Progress {
+    /// The frame was not completed, the next call resumes at the given scan row
+    /// (i.e. the row pair `next_row` and `next_row + HEIGHT / 2`) and bit plane.
+    Partial { next_row: u8, next_mask: u8 },
+    /// The last (row, bit plane) pair of the frame was shown, the next call starts
+    /// a new frame.
+    FrameComplete,
+}
+
+/// The position of the scan within a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cursor {
+    /// The index of the scan row in the scan order.
+    pub(crate) row: u8,
+    pub(crate) mask: u8,
+}
+
+impl Cursor {
+    pub(crate) const fn new() -> Self {
+        Self { row: 0, mask: 0 }
+    }
+
+    /// Advance to the next (row, bit plane) pair of the interlaced `field`, with
+    /// the rows in the scan `order`.
+    ///
+    /// Returns `true` if the cursor wrapped around to the start of the order.
+    pub(crate) fn advance(&mut self, masks: Range<u8>, order: &[u8], field: Option<bool>) -> bool {
+        self.mask += 1;
+
+        if self.mask < masks.end {
+            return false;
+        }
+
+        self.mask = masks.start;
+        self.row += 1;
+
+        if self.seek(order, field) {
+            return false;
+        }
+
+        self.row = 0;
+
+        true
+    }
+
+    /// Move to the first row of the interlaced `field` at or after the cursor, in
+    /// the scan `order`.
+    ///
+    /// Returns `false` if there is none.
+    pub(crate) fn seek(&mut self, order: &[u8], field: Option<bool>) -> bool {
+        let rest = order.get(self.row as usize..).unwrap_or_default();
+
+        match rest.iter().position(|row| in_field(*row, field)) {
+            Some(offset) => {
+                self.row += offset as u8;
+
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Output the framebuffer to the display, for at most (approximately)
+    /// `budget_us` microseconds.
+    ///
+    /// Rows are scanned until the accumulated show durations (plus an estimate of
+    /// the shifting overhead) would exceed the budget. The next call resumes
+    /// exactly where this one left off, so no (row, bit plane) pair is ever skipped
+    /// or shown twice.
+    ///
+    /// At least one (row, bit plane) pair is always output, so progress is made
+    /// even if the budget is too small to fit any.
+    ///
+    /// Like all resumable output methods, this scans the fields of an
+    /// [interlaced](Hub75::set_interlaced) display one after the other, and fails
+    /// with [`ConfigError::UnsupportedChipMode`](crate::ConfigError::UnsupportedChipMode)
+    /// for S-PWM driver chips.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output_bounded<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        budget_us: u32,
+    ) -> Result<Progress, Error<E>> {
+        self.check_pwm_mode()?;
+
+        if self.blanked {
+            return Ok(self.progress());
+        }
+
+        let mut spent = 0u32;
+        let mut first = true;
+
+        loop {
+            let mask = self.cursor.mask;
+            let cost = self.ftc.duration(&mask) + self.plane_overhead_us();
+
+            if !first && spent.saturating_add(cost) > budget_us {
+                return Ok(self.progress());
+            }
+
+            self.output_pair(delay, first)?;
+
+            spent = spent.saturating_add(cost);
+            first = false;
+
+            if let Progress::FrameComplete = self.advance() {
+                return Ok(Progress::FrameComplete);
+            }
+        }
+    }
+
+    /// Output the framebuffer to the display until (approximately) the `deadline`,
+    /// as measured by the microsecond clock `now`.
+    ///
+    /// Like [`output_bounded`](Self::output_bounded), but the remaining time is
+    /// measured rather than estimated, so time lost to interrupts or slow GPIO is
+    /// accounted for. Pairs are output while their estimated cost still fits before
+    /// the deadline. Timestamps wrap, so the deadline must be less than 2^31µs
+    /// ahead.
+    ///
+    /// At least one (row, bit plane) pair is always output, so progress is made
+    /// even if the deadline already passed.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output_with_deadline<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        mut now: impl FnMut() -> u32,
+        deadline: u32,
+    ) -> Result<Progress, Error<E>> {
+        self.check_pwm_mode()?;
+
+        if self.blanked {
+            return Ok(self.progress());
+        }
+
+        let mut first = true;
+
+        loop {
+            let mask = self.cursor.mask;
+            let cost = self.ftc.duration(&mask) + self.plane_overhead_us();
+            let remaining = deadline.wrapping_sub(now()) as i32;
+
+            if !first && (remaining < 0 || cost > remaining as u32) {
+                return Ok(self.progress());
+            }
+
+            self.output_pair(delay, first)?;
+            first = false;
+
+            if let Progress::FrameComplete = self.advance() {
+                return Ok(Progress::FrameComplete);
+            }
+        }
+    }
+
+    /// Output the next single (row, bit plane) pair of the framebuffer.
+    ///
+    /// This bounds the time spent per call to one show duration (plus shifting),
+    /// so refresh can be interleaved with other work at the finest granularity.
+    /// Calls resume where the last one (or [`output_bounded`](Self::output_bounded))
+    /// left off.
+    pub fn output_step<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+    ) -> Result<Progress, Error<E>> {
+        self.check_pwm_mode()?;
+
+        if self.blanked {
+            return Ok(self.progress());
+        }
+
+        self.output_pair(delay, true)?;
+
+        Ok(self.advance())
+    }
+
+    /// Output the remaining bit planes of the current row of the framebuffer.
+    ///
+    /// Like [`output_step`](Self::output_step), but the row is only selected once.
+    /// When resuming mid-row, only the remaining bit planes are output.
+    pub fn output_row<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+    ) -> Result<Progress, Error<E>> {
+        self.check_pwm_mode()?;
+
+        if self.blanked {
+            return Ok(self.progress());
+        }
+
+        let row = self.cursor.row;
+        let mut first = true;
+
+        loop {
+            self.output_pair(delay, first)?;
+            first = false;
+
+            let progress = self.advance();
+
+            if progress == Progress::FrameComplete || self.cursor.row != row {
+                return Ok(progress);
+            }
+        }
+    }
+
+    /// Blank the display and stop scanning, e.g. to cut power draw while idle.
+    ///
+    /// Output enable is parked deasserted, and every output method returns without
+    /// touching the pins until [`unblank`](Self::unblank) is called. The
+    /// framebuffer and scan position are retained, so scanning resumes exactly where
+    /// it left off.
+    pub fn blank(&mut self) -> Result<(), E> {
+        self.data_pins.disable_output()?;
+        self.blanked = true;
+
+        Ok(())
+    }
+
+    /// Resume scanning after [`blank`](Self::blank).
+    ///
+    /// The display stays dark until the next (row, bit plane) pair is output.
+    pub fn unblank(&mut self) {
+        self.blanked = false;
+    }
+
+    /// Whether the display is [blanked](Self::blank).
+    pub fn is_blanked(&self) -> bool {
+        self.blanked
+    }
+
+    /// Output the (row, bit plane) pair at the cursor, selecting the row if it
+    /// changed or may have (on the `first` pair of a call).
+    fn output_pair<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        first: bool,
+    ) -> Result<(), Error<E>> {
+        self.shift_pair(delay, first).map_err(Error::Pins)
+    }
+
+    /// Output the (row, bit plane) pair at the cursor, see
+    /// [`output_pair`](Self::output_pair).
+    fn shift_pair<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        first: bool,
+    ) -> Result<(), E> {
+        if self.at_frame_start() {
+            self.limit_power();
+        }
+
+        let Cursor { row, mask } = self.cursor;
+        let row = self.scan_order[row as usize];
+
+        self.shift_row(delay, row, mask)?;
+
+        if first || mask == self.ftc.masks().start {
+            self.data_pins.wait_shown();
+            self.row_pins.set_row(&self.row_map[row as usize])?;
+        }
+
+        let show_us = self.show_duration(row, &mask);
+
+        sink::latch_and_show(&mut self.data_pins, delay, self.latch_guard_us, show_us)
+    }
+
+    /// Advance the cursor past the pair just output, completing the field (see
+    /// [`Hub75::set_interlaced`]) after its last pair.
+    fn advance(&mut self) -> Progress {
+        let field = self.field();
+
+        if !self
+            .cursor
+            .advance(self.ftc.masks(), &self.scan_order, field)
+        {
+            return self.progress();
+        }
+
+        self.complete_field(field);
+        self.cursor.seek(&self.scan_order, self.field());
+
+        match field {
+            // the odd field follows
+            Some(false) => self.progress(),
+            _ => Progress::FrameComplete,
+        }
+    }
+
+    /// Whether the cursor is at the first (row, bit plane) pair of a frame.
+    pub(crate) fn at_frame_start(&self) -> bool {
+        let field = self.field();
+        let mut start = Cursor {
+            row: 0,
+            mask: self.ftc.masks().start,
+        };
+
+        field != Some(true) && start.seek(&self.scan_order, field) && start == self.cursor
+    }
+
+    /// The progress of the scan, resuming at the cursor.
+    fn progress(&self) -> Progress {
+        Progress::Partial {
+            next_row: self.scan_order[self.cursor.row as usize],
+            next_mask: self.cursor.mask,
+        }
+    }
+}
+
+/// The outcome of one [`IsrStepper::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tick {
+    /// The duration (in microseconds) the latched (row, bit plane) pair is to be
+    /// shown, i.e. when the next tick is due.
+    pub show_us: u32,
+    /// The latched pair was the last of the frame.
+    pub frame_complete: bool,
+}
+
+/// A refresh state machine advanced from a timer interrupt.
+///
+/// Every tick blanks the display, latches the (row, bit plane) pair shifted
+/// during the previous tick, shows it, and shifts the next pair while it is
+/// shown. The timer is then to be reprogrammed to fire again after
+/// [`Tick::show_us`], so the show durations are timed by the timer instead of a
+/// blocking delay and shifting is hidden in the show time:
+///
+/// ```ignore
+/// #[interrupt]
+/// fn TIMER() {
+///     let tick = STEPPER.tick(&mut DISPLAY, &mut NoDelay).unwrap();
+///
+///     timer.start(tick.show_us.micros());
+///
+///     if tick.frame_complete {
+///         // e.g. swap buffers or signal the application
+///     }
+/// }
+/// ```
+///
+/// *Other output methods overwrite the shifted pair, so call
+/// [`reset`](Self::reset) after using them.*
+#[derive(Debug, Default)]
+pub struct IsrStepper {
+    /// The pair at the display's cursor has been shifted in.
+    primed: bool,
+}
+
+impl IsrStepper {
+    pub const fn new() -> Self {
+        Self { primed: false }
+    }
+
+    /// Forget the shifted pair, so the next tick shifts it again before latching.
+    pub fn reset(&mut self) {
+        self.primed = false;
+    }
+
+    /// Advance the refresh by one (row, bit plane) pair of `display`.
+    ///
+    /// The display is left showing the pair, so the tick itself only takes as long
+    /// as shifting one row (plus the latch guard).
+    ///
+    /// While the display is [blanked](Hub75::blank), ticks neither latch nor
+    /// advance, and report the show duration of the pending pair to keep the timer
+    /// running.
+    ///
+    /// S-PWM driver chips are not supported, see [`Hub75::output_step`].
+    pub fn tick<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+        Delay,
+    >(
+        &mut self,
+        display: &mut Hub75<
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS,
+            ColorPins,
+            RowPins,
+            DataPins,
+            Frame,
+            Pipeline,
+        >,
+        delay: &mut Delay,
+    ) -> Result<Tick, Error<E>>
+    where
+        ColorPins: IsDualColorPins<Error = E>,
+        RowPins: IsRowPins<Error = E>,
+        DataPins: IsDataPins<Error = E>,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+        Delay: DelayProvider,
+    {
+        display.check_pwm_mode()?;
+
+        self.latch_next(display, delay).map_err(Error::Pins)
+    }
+
+    /// Latch and show the shifted pair, and shift the next one, see
+    /// [`tick`](Self::tick).
+    fn latch_next<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+        Delay,
+    >(
+        &mut self,
+        display: &mut Hub75<
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS,
+            ColorPins,
+            RowPins,
+            DataPins,
+            Frame,
+            Pipeline,
+        >,
+        delay: &mut Delay,
+    ) -> Result<Tick, E>
+    where
+        ColorPins: IsDualColorPins<Error = E>,
+        RowPins: IsRowPins<Error = E>,
+        DataPins: IsDataPins<Error = E>,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+        Delay: DelayProvider,
+    {
+        let Cursor { row, mask } = display.cursor;
+        let row = display.scan_order[row as usize];
+
+        if display.blanked {
+            return Ok(Tick {
+                show_us: display.show_duration(row, &mask),
+                frame_complete: false,
+            });
+        }
+
+        display.data_pins.disable_output()?;
+
+        if !self.primed {
+            display.shift_row(delay, row, mask)?;
+        }
+
+        if display.latch_guard_us > 0 {
+            delay.delay_us(display.latch_guard_us);
+        }
+
+        display.row_pins.set_row(&display.row_map[row as usize])?;
+        display.data_pins.latch(delay)?;
+        display.data_pins.enable_output()?;
+
+        let show_us = display.show_duration(row, &mask);
+        let frame_complete = display.advance() == Progress::FrameComplete;
+        let Cursor { row, mask } = display.cursor;
+
+        display.shift_row(delay, display.scan_order[row as usize], mask)?;
+        self.primed = true;
+
+        Ok(Tick {
+            show_us,
+            frame_complete,
+        })
+    }
+}
@@ -0,0 +1,330 @@
+//! A display shared between thread mode and an interrupt handler.
+//!
+//! [`SharedHub75`] is a try-lock around the display, placed in a `static`. The
+//! interrupt handler refreshes with [`try_output`](SharedHub75::try_output), which
+//! skips the refresh if thread mode is holding the lock, and thread mode draws in
+//! [`lock`](SharedHub75::lock), which waits for an ongoing refresh (only possible
+//! on multi-core targets, where the handler may run on another core).
+//!
+//! The shared display starts out empty, so it can be placed in a `static`, and the
+//! display is moved in once constructed at runtime:
+//!
+//! ```ignore
+//! static DISPLAY: SharedHub75<...> = SharedHub75::new();
+//!
+//! // at startup
+//! DISPLAY.init(Hub75::new(/* pins */)).ok();
+//!
+//! #[interrupt]
+//! fn TIMER() {
+//!     let _ = DISPLAY.try_output(&mut NoDelay);
+//! }
+//!
+//! // thread mode
+//! DISPLAY.lock(|display| Text::new("hi", Point::new(1, 8), style).draw(display));
+//! ```
+//!
+//! Refreshes skipped while drawing leave the display dark, so draws should be
+//! short, or draw into a [`buffer`](crate::buffer) and only copy under the lock.
+//!
+//! *Only available on targets with atomic compare-and-swap.*
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    fmt, gamma::Gamma, pins::*, pipeline::DisplayPipeline, DelayProvider, FrameBuffer,
+    FrameStorage, Hub75,
+};
+
+/// The error of accessing a [`SharedHub75`] while it is locked by another
+/// context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Locked;
+
+/// A display shared between contexts through a try-lock.
+///
+/// Until a display is moved in with [`init`](Self::init), the shared display is
+/// empty, and behaves as if locked by another context.
+pub struct SharedHub75<
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    #[allow(clippy::type_complexity)]
+    display: UnsafeCell<
+        Option<Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>>,
+    >,
+    locked: AtomicBool,
+}
+
+/// The held lock of a [`SharedHub75`], released when dropped (also when the
+/// locked closure panics).
+struct Guard<'a>(&'a AtomicBool);
+
+impl Guard<'_> {
+    /// Acquire `locked`, unless it is held already.
+    fn acquire(locked: &AtomicBool) -> Result<Guard<'_>, Locked> {
+        locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| Locked)?;
+
+        Ok(Guard(locked))
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+// SAFETY: the display is only accessed by the context holding the lock, which is
+// acquired with compare-and-swap.
+unsafe impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins + Send,
+        RowPins: IsRowPins + Send,
+        DataPins: IsDataPins + Send,
+        Frame: FrameStorage<WIDTH, HEIGHT> + Send,
+        Pipeline: DisplayPipeline + Send,
+    > Sync
+    for SharedHub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > SharedHub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Create an empty shared display, which can be placed in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            display: UnsafeCell::new(None),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a shared display holding `display`, e.g. one constructed in a const
+    /// context with [`Hub75::new_permille`].
+    pub const fn with_display(
+        display: Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    ) -> Self {
+        Self {
+            display: UnsafeCell::new(Some(display)),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// Move `display` into the empty shared display, returning it back if there
+    /// already is one (or another context holds the lock).
+    #[allow(clippy::result_large_err)]
+    pub fn init(
+        &self,
+        display: Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    ) -> Result<(), Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>>
+    {
+        let Ok(_guard) = Guard::acquire(&self.locked) else {
+            return Err(display);
+        };
+
+        // SAFETY: the lock is held until the guard is dropped
+        let slot = unsafe { &mut *self.display.get() };
+
+        if slot.is_some() {
+            return Err(display);
+        }
+
+        *slot = Some(display);
+
+        Ok(())
+    }
+
+    /// Run `f` with exclusive access to the display, unless another context holds
+    /// the lock (or the shared display is still empty).
+    ///
+    /// This never waits, so it is safe to call from interrupt handlers.
+    pub fn try_lock<R>(
+        &self,
+        f: impl FnOnce(
+            &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+        ) -> R,
+    ) -> Result<R, Locked> {
+        let _guard = Guard::acquire(&self.locked)?;
+
+        // SAFETY: the lock is held until the guard is dropped, after `f` returned
+        // or panicked
+        let display = unsafe { &mut *self.display.get() }.as_mut().ok_or(Locked)?;
+
+        Ok(f(display))
+    }
+
+    /// Run `f` with exclusive access to the display, waiting while another context
+    /// holds the lock.
+    ///
+    /// *Calling this from an interrupt handler which preempted a holder of the lock
+    /// deadlocks. Use [`try_lock`](Self::try_lock) there. Calling this before
+    /// [`init`](Self::init) panics.*
+    pub fn lock<R>(
+        &self,
+        f: impl FnOnce(
+            &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+        ) -> R,
+    ) -> R {
+        let _guard = loop {
+            if let Ok(guard) = Guard::acquire(&self.locked) {
+                break guard;
+            }
+
+            core::hint::spin_loop();
+        };
+
+        // SAFETY: the lock is held until the guard is dropped
+        let Some(display) = (unsafe { &mut *self.display.get() }) else {
+            fmt::panic!("the shared display is locked before init");
+        };
+
+        f(display)
+    }
+
+    /// Release the display, if there is one.
+    pub fn into_inner(
+        self,
+    ) -> Option<Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>>
+    {
+        self.display.into_inner()
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Default
+    for SharedHub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > SharedHub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Output a frame (see [`Hub75::output`]), unless another context holds the
+    /// lock (or the shared display is still empty), returning `None` then.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn try_output<Delay: DelayProvider>(&self, delay: &mut Delay) -> Result<Option<u32>, E> {
+        self.try_lock(|display| display.output(delay))
+            .ok()
+            .transpose()
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::test_utils::*;
+
+    /// The documented pattern: an empty shared display in a `static`.
+    static SHARED: SharedHub75<64, 32, 16, 1, NoPins, NoPins, NoPins> = SharedHub75::new();
+
+    #[test]
+    fn init_static() {
+        assert_eq!(SHARED.try_lock(|_| ()), Err(Locked));
+        assert!(SHARED.init(Hub75::new(NoPins, NoPins, NoPins, 0.5)).is_ok());
+        assert!(SHARED
+            .init(Hub75::new(NoPins, NoPins, NoPins, 0.5))
+            .is_err());
+        assert_eq!(SHARED.try_lock(|display| display.brightness()), Ok(255));
+    }
+
+    #[test]
+    fn refresh_skipped_while_locked() {
+        let log = Log::default();
+        let shared = SharedHub75::new();
+
+        assert_eq!(shared.try_output(&mut ProbeDelay(&log)), Ok(None));
+        assert!(shared.init(display::<1>(&log)).is_ok());
+        assert_eq!(shared.try_output(&mut ProbeDelay(&log)), Ok(Some(0)));
+        assert_eq!(latched_rows(&log).len(), 16);
+
+        shared.lock(|display| {
+            Pixel(Point::zero(), Rgb565::RED).draw(display).unwrap();
+
+            // an interrupt arriving mid-draw
+            assert_eq!(shared.try_output(&mut ProbeDelay(&log)), Ok(None));
+            assert_eq!(shared.try_lock(|_| ()), Err(Locked));
+        });
+
+        assert_eq!(latched_rows(&log).len(), 16);
+        assert_eq!(shared.try_output(&mut ProbeDelay(&log)), Ok(Some(0)));
+        assert_eq!(
+            shared.into_inner().map(|display| display.frame.data[0][0]),
+            Some((255, 0, 0))
+        );
+    }
+
+    #[test]
+    fn unlocked_after_panic() {
+        let log = Log::default();
+        let shared = SharedHub75::with_display(display::<1>(&log));
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            shared.lock(|_| panic!("draw failed"))
+        }));
+
+        assert!(panicked.is_err());
+        assert_eq!(shared.try_lock(|_| ()), Ok(()));
+    }
+}
@@ -0,0 +1,250 @@
+//! Displays with a single color group, whose lower half is appended to the shift
+//! chain of the upper half.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    draw_target::DrawTarget, geometry::Dimensions, pixelcolor::Rgb565, primitives::Rectangle, Pixel,
+};
+
+use crate::{fmt, pins::*, sink, ConfigError, DelayProvider, FrameBuffer, FrameTimeCompensation};
+
+/// A `WIDTH` by `HEIGHT` display with `SCAN` scan rows and 1 color written at a
+/// time.
+///
+/// Such panels lack the R2, G2, B2 inputs: each scan row selects one row of each
+/// half as usual, but the registers of the lower half are appended to those of the
+/// upper half. Every row is therefore shifted as `2 * WIDTH` columns, the lower
+/// row first (as it ends up farther down the chain), followed by the upper row.
+/// `HEIGHT` must be `2 * SCAN`.
+///
+/// This variant only provides drawing and output; the configuration of
+/// [`Hub75`](crate::Hub75) (color temperature, blending, pacing, etc.) is not
+/// available.
+pub struct Hub75Single<
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+> {
+    frame: FrameBuffer<WIDTH, HEIGHT>,
+    latch_guard_us: u32,
+    ftc: FrameTimeCompensation<BITS>,
+    color_pins: ColorPins,
+    row_pins: RowPins,
+    data_pins: DataPins,
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+    > Hub75Single<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+where
+    ColorPins: IsColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+{
+    /// Construct a new display instance.
+    ///
+    /// `color_pins` is the single color group, and `on_ratio` behaves as for
+    /// [`Hub75::new`](crate::Hub75::new).
+    pub fn new(
+        color_pins: ColorPins,
+        row_pins: RowPins,
+        data_pins: DataPins,
+        on_ratio: f64,
+    ) -> Self {
+        const { assert!(HEIGHT == 2 * SCAN, "HEIGHT must be twice SCAN") };
+
+        fmt::trace!(
+            "new Hub75Single with {}x{} pixels, {} scan rows and {} bits",
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS
+        );
+
+        Self {
+            frame: FrameBuffer::new(),
+            latch_guard_us: 1,
+            ftc: FrameTimeCompensation::from_ratio(on_ratio),
+            color_pins,
+            row_pins,
+            data_pins,
+        }
+    }
+
+    /// Output the framebuffer to the display.
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        let data = &self.frame.data;
+
+        for row in 0..SCAN {
+            self.data_pins.wait_shown();
+            self.row_pins.set_row(&(row as u8))?;
+
+            // the lower row is shifted first
+            let halves = [&data[row + SCAN], &data[row]];
+
+            for mask in self.ftc.masks() {
+                for color in halves.iter().copied().flatten() {
+                    self.color_pins.set_color::<BITS>(color, &mask)?;
+                    self.data_pins.shift(delay)?;
+                }
+
+                sink::latch_and_show(
+                    &mut self.data_pins,
+                    delay,
+                    self.latch_guard_us,
+                    self.ftc.duration(&mask),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the time (in microseconds) waited between disabling the output and
+    /// latching the next row, 1µs by default.
+    pub fn set_latch_guard_us(&mut self, guard: u32) {
+        self.latch_guard_us = guard;
+    }
+
+    /// Set the number of bit planes shown, trading color depth for refresh rate
+    /// (see [`Hub75::set_active_bits`](crate::Hub75::set_active_bits)).
+    ///
+    /// `bits` must be in `1..=BITS`.
+    pub fn set_active_bits(&mut self, bits: u8) -> Result<(), ConfigError> {
+        self.ftc.set_active_bits(bits)
+    }
+
+    /// The number of bit planes shown.
+    pub fn active_bits(&self) -> u8 {
+        self.ftc.active_bits
+    }
+
+    /// Set the minimum time (in microseconds) any bit plane is shown
+    /// (see [`Hub75::set_min_show_us`](crate::Hub75::set_min_show_us)).
+    pub fn set_min_show_us(&mut self, min: u32) {
+        self.ftc.min_show_us = min;
+    }
+
+    /// The framebuffer of the display.
+    pub fn frame(&self) -> &FrameBuffer<WIDTH, HEIGHT> {
+        &self.frame
+    }
+
+    /// Set the framebuffer to all black.
+    pub fn wipe(&mut self) {
+        self.frame = FrameBuffer::new();
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > Dimensions for Hub75Single<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.frame.bounding_box()
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+    > DrawTarget for Hub75Single<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins>
+{
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.frame.draw_iter(pixels)
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal::mock::PinState::*, test_utils::*};
+    use embedded_graphics::{
+        geometry::{Point, Size},
+        pixelcolor::RgbColor,
+        Drawable,
+    };
+
+    #[test]
+    fn output_concatenated_halves() {
+        let log = Log::default();
+        let (upper, _) = color_pins(&log);
+        let mut display =
+            Hub75Single::<32, 16, 8, 1, _, _, _>::new(upper, row_pins(&log), data_pins(&log), 0.5);
+
+        assert_eq!(display.bounding_box().size, Size::new(32, 16));
+
+        Pixel(Point::new(0, 0), Rgb565::RED)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(1, 8), Rgb565::BLUE)
+            .draw(&mut display)
+            .unwrap();
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        let shifted = shifted_colors(&log);
+
+        assert_eq!(shifted.len(), 2 * 32 * 8);
+        // lower row first
+        assert_eq!(shifted[1][..3], [Low, Low, High]);
+        assert_eq!(shifted[32][..3], [High, Low, Low]);
+        assert_eq!(
+            shifted
+                .iter()
+                .filter(|colors| colors.contains(&High))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn active_bits() {
+        let log = Log::default();
+        let (upper, _) = color_pins(&log);
+        let mut display =
+            Hub75Single::<32, 16, 8, 3, _, _, _>::new(upper, row_pins(&log), data_pins(&log), 0.5);
+
+        assert_eq!(
+            display.set_active_bits(4),
+            Err(ConfigError::ActiveBitsOutOfRange)
+        );
+        assert_eq!(display.set_active_bits(1), Ok(()));
+        assert_eq!(display.active_bits(), 1);
+
+        display.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert_eq!(shifted_colors(&log).len(), 2 * 32 * 8);
+    }
+}
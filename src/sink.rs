@@ -0,0 +1,155 @@
+//! Transports receiving packed rows, e.g. for hardware-assisted output.
+//!
+//! A packed row holds one byte per column, with the bits of the current bit plane
+//! of the upper and lower pixels (see [`R1`] through [`B2`]). This is the format
+//! produced by `serialize_row`.
+
+use crate::{pins::*, DelayProvider};
+
+/// The bit of a packed column holding the upper red channel.
+pub const R1: u8 = 1 << 0;
+/// The bit of a packed column holding the upper green channel.
+pub const G1: u8 = 1 << 1;
+/// The bit of a packed column holding the upper blue channel.
+pub const B1: u8 = 1 << 2;
+/// The bit of a packed column holding the lower red channel.
+pub const R2: u8 = 1 << 3;
+/// The bit of a packed column holding the lower green channel.
+pub const G2: u8 = 1 << 4;
+/// The bit of a packed column holding the lower blue channel.
+pub const B2: u8 = 1 << 5;
+
+/// Represents a transport which drives a display one packed row at a time.
+pub trait RowSink {
+    type Error;
+
+    /// Shift the packed bit plane `mask` of the row pair `row` into the display,
+    /// latch it, and show it for `show_ns` nanoseconds.
+    fn send_row(
+        &mut self,
+        row: u8,
+        mask: u8,
+        packed: &[u8],
+        show_ns: u32,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Pack the bits of a color pair for a single bit plane.
+pub(crate) fn pack<const BITS: u8>(upper: &(u8, u8, u8), lower: &(u8, u8, u8), mask: u8) -> u8 {
+    let bit = mask + 8 - BITS;
+    let set = |value: u8, flag: u8| if (value >> bit) & 0x1 == 1 { flag } else { 0 };
+
+    set(upper.0, R1)
+        | set(upper.1, G1)
+        | set(upper.2, B1)
+        | set(lower.0, R2)
+        | set(lower.1, G2)
+        | set(lower.2, B2)
+}
+
+/// Unpack the bits of a packed column into colors for a single (most
+/// significant) bit plane.
+pub(crate) fn unpack(packed: u8) -> ((u8, u8, u8), (u8, u8, u8)) {
+    let get = |flag: u8| if packed & flag == 0 { 0 } else { 0xff };
+
+    ((get(R1), get(G1), get(B1)), (get(R2), get(G2), get(B2)))
+}
+
+/// Shift a packed row into the display with the color and data pins.
+pub(crate) fn shift_packed<E, ColorPins, DataPins, Delay>(
+    color_pins: &mut ColorPins,
+    data_pins: &mut DataPins,
+    delay: &mut Delay,
+    packed: &[u8],
+) -> Result<(), E>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Delay: DelayProvider,
+{
+    for column in packed {
+        let (upper, lower) = unpack(*column);
+
+        color_pins.set_colors::<1>(&upper, &lower, &0)?;
+        data_pins.shift(delay)?;
+    }
+
+    Ok(())
+}
+
+/// Latch the shifted row and show it, after waiting `guard_us` since the output
+/// was last disabled.
+pub(crate) fn latch_and_show<DataPins, Delay>(
+    data_pins: &mut DataPins,
+    delay: &mut Delay,
+    guard_us: u32,
+    show_us: u32,
+) -> Result<(), DataPins::Error>
+where
+    DataPins: IsDataPins,
+    Delay: DelayProvider,
+{
+    if guard_us > 0 {
+        delay.delay_us(guard_us);
+    }
+
+    data_pins.latch(delay)?;
+    data_pins.show(delay, show_us)
+}
+
+/// The pin-based (bit-banged) transport.
+pub(crate) struct PinSink<'a, ColorPins, RowPins, DataPins, Delay> {
+    pub(crate) color_pins: &'a mut ColorPins,
+    pub(crate) row_pins: &'a mut RowPins,
+    pub(crate) data_pins: &'a mut DataPins,
+    pub(crate) delay: &'a mut Delay,
+    pub(crate) latch_guard_us: u32,
+    /// The currently selected row, to avoid redundant row selection.
+    pub(crate) row: &'a mut Option<u8>,
+}
+
+impl<E, ColorPins, RowPins, DataPins, Delay> RowSink
+    for PinSink<'_, ColorPins, RowPins, DataPins, Delay>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Delay: DelayProvider,
+{
+    type Error = E;
+
+    fn send_row(&mut self, row: u8, _mask: u8, packed: &[u8], show_ns: u32) -> Result<(), E> {
+        shift_packed(self.color_pins, self.data_pins, self.delay, packed)?;
+
+        if *self.row != Some(row) {
+            self.data_pins.wait_shown();
+            self.row_pins.set_row(&row)?;
+            *self.row = Some(row);
+        }
+
+        latch_and_show(
+            self.data_pins,
+            self.delay,
+            self.latch_guard_us,
+            show_ns / 1000,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_round_trip() {
+        let upper = (0b1000_0000, 0, 0b1100_0000);
+        let lower = (0, 0b0100_0000, 0b1000_0000);
+
+        assert_eq!(pack::<2>(&upper, &lower, 1), R1 | B1 | B2);
+        assert_eq!(pack::<2>(&upper, &lower, 0), B1 | G2);
+
+        let (upper, lower) = unpack(R1 | B1 | B2);
+
+        assert_eq!(pack::<1>(&upper, &lower, 0), R1 | B1 | B2);
+    }
+}
@@ -0,0 +1,142 @@
+//! A [`RowSink`] shifting a single color channel through an SPI peripheral.
+//!
+//! For single color panels (or chains serialized onto one data line), the SPI
+//! peripheral shifts the columns in hardware, with MOSI as the data line and SCK
+//! as the panel clock. Each column is one bit, most significant bit first, so a
+//! row takes `WIDTH / 8` bytes. Rows not a multiple of 8 columns wide are padded
+//! with leading zeroes, which are shifted past the end of the chain.
+//!
+//! The SPI peripheral must be configured for mode 0 (data sampled on the rising
+//! clock edge), at a clock rate the panel supports.
+
+use crate::{
+    hal::{OutputPin, SpiWrite},
+    pins::*,
+    sink::RowSink,
+    DelayProvider,
+};
+
+/// A row sink shifting the channel `flag` (see [`sink`](crate::sink)) of each
+/// packed column through an SPI bus.
+pub struct SpiRowSink<Spi, RowPins, Latch, Output, Delay> {
+    spi: Spi,
+    row_pins: RowPins,
+    latch: Latch,
+    output: Output,
+    delay: Delay,
+    flag: u8,
+    /// The currently selected row, to avoid redundant row selection.
+    row: Option<u8>,
+}
+
+impl<Spi, RowPins, Latch, Output, Delay> SpiRowSink<Spi, RowPins, Latch, Output, Delay> {
+    pub fn new(
+        spi: Spi,
+        row_pins: RowPins,
+        latch: Latch,
+        output: Output,
+        delay: Delay,
+        flag: u8,
+    ) -> Self {
+        Self {
+            spi,
+            row_pins,
+            latch,
+            output,
+            delay,
+            flag,
+            row: None,
+        }
+    }
+
+    /// Release the underlying peripheral, pins, and delay.
+    pub fn release(self) -> (Spi, RowPins, Latch, Output, Delay) {
+        (self.spi, self.row_pins, self.latch, self.output, self.delay)
+    }
+}
+
+impl<E, Spi, RowPins, Latch, Output, Delay> RowSink
+    for SpiRowSink<Spi, RowPins, Latch, Output, Delay>
+where
+    Spi: SpiWrite<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    Latch: OutputPin<Error = E>,
+    Output: OutputPin<Error = E>,
+    Delay: DelayProvider,
+{
+    type Error = E;
+
+    fn send_row(&mut self, row: u8, _mask: u8, packed: &[u8], show_ns: u32) -> Result<(), E> {
+        if self.row != Some(row) {
+            self.row_pins.set_row(&row)?;
+            self.row = Some(row);
+        }
+
+        // the leading padding of a partial byte, so the last column is the last bit
+        let mut bits = (8 - packed.len() % 8) % 8;
+        let mut byte = 0u8;
+
+        for column in packed {
+            byte = byte << 1 | (column & self.flag != 0) as u8;
+            bits += 1;
+
+            if bits == 8 {
+                self.spi.write(&[byte])?;
+                (bits, byte) = (0, 0);
+            }
+        }
+
+        self.latch.set_high()?;
+        self.delay.delay_us(1);
+        self.latch.set_low()?;
+
+        self.output.set_low()?;
+        self.delay.delay_us(show_ns / 1000);
+        self.output.set_high()
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use core::convert::Infallible;
+
+    use super::*;
+    use crate::{mock::Pin, sink};
+
+    struct Recorder(Vec<u8>);
+
+    impl SpiWrite for Recorder {
+        type Error = Infallible;
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+            self.0.extend_from_slice(bytes);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn padded_bits() {
+        let mut sink = SpiRowSink::new(
+            Recorder(Vec::new()),
+            NoPins,
+            Pin(|_| {}),
+            Pin(|_| {}),
+            crate::mock::Delay(|_| {}),
+            sink::R1,
+        );
+        let mut packed = [0; 12];
+
+        packed[0] = sink::R1;
+        packed[11] = sink::R1 | sink::G1;
+        packed[5] = sink::G1;
+
+        sink.send_row(0, 0, &packed, 1000).unwrap();
+
+        assert_eq!(sink.release().0 .0, [0b0000_1000, 0b0000_0001]);
+    }
+}
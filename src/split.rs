@@ -0,0 +1,285 @@
+//! Splitting a display into a drawing half and an output half, for drawing in one
+//! context while another (e.g. a timer interrupt) refreshes the display.
+//!
+//! The halves exchange frames through a [`SharedFrame`]: the [`Drawer`] draws into
+//! it, and [`presents`](Drawer::present) the finished frame, which the [`Outputter`]
+//! copies into the display at the start of its next frame. The drawing half is
+//! locked from presenting until the copy completes, so the halves never access the
+//! shared frame at the same time.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::Rgb565,
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{
+    gamma::Gamma, pins::*, pipeline::DisplayPipeline, DelayProvider, FrameBuffer, FrameStorage,
+    Hub75,
+};
+
+/// The frame drawn by a [`Drawer`], exchanged with its [`Outputter`].
+pub struct SharedFrame<const WIDTH: usize, const HEIGHT: usize> {
+    frame: UnsafeCell<FrameBuffer<WIDTH, HEIGHT>>,
+    /// The frame is presented, i.e. owned by the outputter until it is copied.
+    presented: AtomicBool,
+}
+
+// SAFETY: the frame is only accessed by the drawer while not presented, and by the
+// outputter while presented, with the flag handing it over with acquire/release
+// ordering.
+unsafe impl<const WIDTH: usize, const HEIGHT: usize> Sync for SharedFrame<WIDTH, HEIGHT> {}
+
+impl<const WIDTH: usize, const HEIGHT: usize> SharedFrame<WIDTH, HEIGHT> {
+    /// Create an all black shared frame, which can be placed in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            frame: UnsafeCell::new(FrameBuffer::new()),
+            presented: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for SharedFrame<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error of drawing while the last presented frame was not yet copied by the
+/// [`Outputter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Presenting;
+
+/// The drawing half of a split display.
+///
+/// Draws persist across presented frames, so each frame only needs to draw what
+/// changed.
+pub struct Drawer<'a, const WIDTH: usize, const HEIGHT: usize> {
+    shared: &'a SharedFrame<WIDTH, HEIGHT>,
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Drawer<'_, WIDTH, HEIGHT> {
+    /// Hand the drawn frame to the outputter, to be shown from its next frame on.
+    ///
+    /// Drawing fails with [`Presenting`] until the outputter copied it.
+    pub fn present(&mut self) {
+        self.shared.presented.store(true, Ordering::Release);
+    }
+
+    /// Whether the last presented frame was not yet copied by the outputter.
+    pub fn is_presenting(&self) -> bool {
+        self.shared.presented.load(Ordering::Acquire)
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Dimensions for Drawer<'_, WIDTH, HEIGHT> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Drawer<'_, WIDTH, HEIGHT> {
+    type Color = Rgb565;
+    type Error = Presenting;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.is_presenting() {
+            return Err(Presenting);
+        }
+
+        // SAFETY: the frame is not presented, and only this drawer presents it
+        let frame = unsafe { &mut *self.shared.frame.get() };
+        let Ok(()) = frame.draw_iter(pixels);
+
+        Ok(())
+    }
+}
+
+/// The output half of a split display.
+pub struct Outputter<
+    'a,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    display:
+        &'a mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    shared: &'a SharedFrame<WIDTH, HEIGHT>,
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    > Outputter<'_, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Copy the presented frame into the display, if there is one, returning
+    /// whether there was.
+    pub fn take_presented(&mut self) -> bool {
+        if !self.shared.presented.load(Ordering::Acquire) {
+            return false;
+        }
+
+        // SAFETY: the frame is presented, so the drawer does not access it until
+        // the flag is cleared
+        self.display
+            .load_frame(unsafe { &mut *self.shared.frame.get() });
+        self.shared.presented.store(false, Ordering::Release);
+
+        true
+    }
+
+    /// Take the presented frame (see [`take_presented`](Self::take_presented)) and
+    /// output the display (see [`Hub75::output`]).
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<u32, E> {
+        self.take_presented();
+        self.display.output(delay)
+    }
+
+    /// The display, e.g. for its settings or other output methods.
+    ///
+    /// *Draws to it are overwritten by the next presented frame.*
+    pub fn display(
+        &mut self,
+    ) -> &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        self.display
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Split the display into a drawing half and an output half, exchanging frames
+    /// through `shared`.
+    ///
+    /// A pending presented frame of `shared` is discarded.
+    #[allow(clippy::type_complexity)]
+    pub fn split<'a>(
+        &'a mut self,
+        shared: &'a mut SharedFrame<WIDTH, HEIGHT>,
+    ) -> (
+        Drawer<'a, WIDTH, HEIGHT>,
+        Outputter<'a, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    ) {
+        *shared.presented.get_mut() = false;
+        shared.frame.get_mut().convert(self.selected_pipeline());
+
+        let shared = &*shared;
+
+        (
+            Drawer { shared },
+            Outputter {
+                display: self,
+                shared,
+            },
+        )
+    }
+
+    /// Copy a framebuffer into the display, marking the changed rows dirty.
+    ///
+    /// The framebuffer is converted to the display's pipeline first, so its
+    /// subsequent draws match those to the display.
+    pub(crate) fn load_frame(&mut self, frame: &mut FrameBuffer<WIDTH, HEIGHT>) {
+        frame.convert(self.selected_pipeline());
+
+        let mut changed = false;
+
+        for (y, (row, new)) in self.frame.data.iter_mut().zip(&frame.data).enumerate() {
+            if row != new {
+                *row = *new;
+                self.dirty_rows[y % SCAN] = self.dirty_stamp;
+                changed = true;
+            }
+        }
+
+        self.mark_rows_changed(changed);
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use embedded_graphics::{pixelcolor::RgbColor, Drawable};
+
+    use super::*;
+    use crate::{hal::PinState, test_utils::*};
+
+    #[test]
+    fn present_hand_over() {
+        let log = Log::default();
+        let mut display = display::<1>(&log);
+        let mut shared = SharedFrame::new();
+        let (mut drawer, mut outputter) = display.split(&mut shared);
+        let lit = |log: &Log| {
+            shifted_colors(log)
+                .iter()
+                .any(|column| column[0] == PinState::High)
+        };
+
+        Pixel(Point::new(0, 0), Rgb565::RED)
+            .draw(&mut drawer)
+            .unwrap();
+        outputter.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert!(!lit(&log));
+
+        drawer.present();
+
+        assert_eq!(
+            Pixel(Point::new(1, 0), Rgb565::RED).draw(&mut drawer),
+            Err(Presenting)
+        );
+
+        outputter.output(&mut ProbeDelay(&log)).unwrap();
+
+        assert!(lit(&log));
+        assert!(!drawer.is_presenting());
+        assert!(!outputter.take_presented());
+        assert_eq!(
+            Pixel(Point::new(1, 0), Rgb565::RED).draw(&mut drawer),
+            Ok(())
+        );
+        assert_eq!(outputter.display().frame.data[0][0], (255, 0, 0));
+    }
+}
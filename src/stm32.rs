@@ -0,0 +1,165 @@
+//! A reference [`DmaBackend`] and output enable for STM32 parts, feeding GPIO BSRR
+//! words by DMA paced by a timer, with the output enable driven by a timer channel.
+//!
+//! Rendered rows (see [`stream`](crate::stream)) are converted into one BSRR word
+//! per sample, setting and resetting eight consecutive pins of one port: R1, G1,
+//! B1, R2, G2, B2, CLK, and LAT, from `first_pin` up. The words are written to the
+//! BSRR register by DMA on every update of a timer, at the desired shift rate.
+//!
+//! The peripheral setup (timer, DMA stream, pin modes) is application specific, so
+//! this module only provides the buffer handling and pulse logic; the peripherals
+//! are abstracted by [`BsrrTransfer`] and [`OnePulseTimer`].
+
+use core::convert::Infallible;
+
+use crate::{dma::DmaBackend, pins::IsDataPins, DelayProvider};
+
+/// A timer paced DMA stream writing words to a GPIO BSRR register.
+pub trait BsrrTransfer {
+    /// Start writing the words.
+    ///
+    /// The words are owned by the backend, which keeps them in place and
+    /// unmodified until [`is_idle`](Self::is_idle) returns `true`.
+    fn start(&mut self, words: &[u32]);
+
+    /// Whether the last started stream is complete.
+    fn is_idle(&mut self) -> bool;
+}
+
+/// The BSRR word setting the pins of a sample and resetting the others, for pins
+/// from `first_pin` up.
+pub const fn bsrr_word(sample: u8, first_pin: u8) -> u32 {
+    let set = (sample as u32) << first_pin;
+    let reset = (!sample as u32 & 0xff) << first_pin;
+
+    set | reset << 16
+}
+
+/// A DMA backend writing rendered rows to a GPIO port as BSRR words.
+///
+/// `LEN` must be at least [`stream::row_len`](crate::stream::row_len) of the width
+/// of the display.
+pub struct BsrrDmaBackend<Transfer, const LEN: usize> {
+    transfer: Transfer,
+    first_pin: u8,
+    samples: [u8; LEN],
+    words: [u32; LEN],
+}
+
+impl<Transfer: BsrrTransfer, const LEN: usize> BsrrDmaBackend<Transfer, LEN> {
+    /// *`first_pin` must be at most 8.*
+    pub fn new(transfer: Transfer, first_pin: u8) -> Self {
+        assert!(first_pin <= 8);
+
+        Self {
+            transfer,
+            first_pin,
+            samples: [0; LEN],
+            words: [0; LEN],
+        }
+    }
+
+    /// Release the underlying transfer.
+    pub fn release(self) -> Transfer {
+        self.transfer
+    }
+}
+
+impl<Transfer: BsrrTransfer, const LEN: usize> DmaBackend<LEN> for BsrrDmaBackend<Transfer, LEN> {
+    type Error = Infallible;
+
+    fn buffer(&mut self) -> &mut [u8; LEN] {
+        &mut self.samples
+    }
+
+    fn start(&mut self, len: usize) -> Result<(), Self::Error> {
+        for (word, sample) in self.words.iter_mut().zip(&self.samples[..len]) {
+            *word = bsrr_word(*sample, self.first_pin);
+        }
+
+        self.transfer.start(&self.words[..len]);
+
+        Ok(())
+    }
+
+    fn is_complete(&mut self) -> bool {
+        self.transfer.is_idle()
+    }
+}
+
+/// A timer channel driving the output enable pin in one-pulse mode.
+pub trait OnePulseTimer {
+    /// Start a single pulse asserting the output enable for `us` microseconds.
+    fn start_pulse(&mut self, us: u32);
+
+    /// Whether the last started pulse is complete.
+    fn is_pulse_done(&mut self) -> bool;
+
+    /// Force the output enable asserted or deasserted, outside of pulses.
+    fn force(&mut self, asserted: bool);
+}
+
+/// Data pins driving only the output enable, with a timer channel, for displays
+/// whose clock and latch are driven by a [`BsrrDmaBackend`].
+///
+/// `show` times the pulse in hardware, so its duration is exact regardless of
+/// interrupts. `shift` and `latch` do nothing.
+pub struct TimerOutputEnable<Timer>(pub Timer);
+
+impl<Timer: OnePulseTimer> IsDataPins for TimerOutputEnable<Timer> {
+    type Error = Infallible;
+
+    fn shift<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, _delay: &mut Delay) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn shift_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn latch_delay_us(&self) -> u32 {
+        0
+    }
+
+    fn enable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.force(true);
+
+        Ok(())
+    }
+
+    fn disable_output(&mut self) -> Result<(), Self::Error> {
+        self.0.force(false);
+
+        Ok(())
+    }
+
+    fn show<Delay: DelayProvider>(
+        &mut self,
+        _delay: &mut Delay,
+        duration: u32,
+    ) -> Result<(), Self::Error> {
+        self.0.start_pulse(duration);
+
+        while !self.0.is_pulse_done() {}
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{CLK, LAT};
+
+    #[test]
+    fn bsrr_words() {
+        assert_eq!(bsrr_word(0, 0), 0x00ff_0000);
+        assert_eq!(bsrr_word(0xff, 8), 0x0000_ff00);
+        assert_eq!(bsrr_word(CLK | 0b1, 4), 0x0be0_0410);
+        assert_eq!(bsrr_word(LAT, 0), 0x007f_0080);
+    }
+}
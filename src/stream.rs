@@ -0,0 +1,255 @@
+//! Rendering of raw bitstreams, for output by user-driven DMA.
+//!
+//! A bitstream holds one byte per sample of eight parallel pins: the color bits of
+//! a packed column (see [`sink`](crate::sink) for the format), [`CLK`], and [`LAT`]. Every
+//! column takes two samples, with the clock low and then high, and every row ends
+//! with two samples pulsing the latch.
+//!
+//! Row selection and the output enable are left to the caller, e.g. driven by a
+//! timer between the transfers of rows.
+
+use crate::{pins::*, pipeline::DisplayPipeline, FrameStorage, Hub75, OutOfBounds};
+
+/// The bit of a sample holding the clock.
+pub const CLK: u8 = 1 << 6;
+/// The bit of a sample holding the latch.
+pub const LAT: u8 = 1 << 7;
+
+/// The number of samples of a rendered row of a `width` columns wide display.
+pub const fn row_len(width: usize) -> usize {
+    2 * width + 2
+}
+
+/// The rows of a display a consumer has rendered, see
+/// [`is_row_dirty`](Hub75::is_row_dirty).
+///
+/// Every consumer keeping rendered rows around keeps its own, so consumers do not
+/// clean each other's dirty rows. Every row is dirty to the default, which has
+/// rendered nothing yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderedRows(pub(crate) u32);
+
+/// The samples pulsing the latch, ending a rendered row.
+pub(crate) const LATCH: [u8; 2] = [LAT, 0];
+
+/// Render a packed row into `buffer`, returning the number of samples written.
+pub fn render_packed(packed: &[u8], buffer: &mut [u8]) -> Result<usize, OutOfBounds> {
+    let len = row_len(packed.len());
+    let buffer = buffer.get_mut(..len).ok_or(OutOfBounds)?;
+
+    render_shift(packed, buffer);
+    buffer[len - 2..].copy_from_slice(&LATCH);
+
+    Ok(len)
+}
+
+/// Render the samples shifting (but not latching) a packed row into `buffer`, as
+/// far as it holds them.
+pub(crate) fn render_shift(packed: &[u8], buffer: &mut [u8]) {
+    for (samples, column) in buffer.chunks_exact_mut(2).zip(packed) {
+        samples[0] = *column;
+        samples[1] = *column | CLK;
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Render the provided bit plane of a row pair into `buffer`, returning the
+    /// number of samples written ([`row_len`] of `WIDTH`).
+    ///
+    /// *`row` must be less than `SCAN` and `mask` less than `BITS`.*
+    pub fn render_row(&self, row: u8, mask: u8, buffer: &mut [u8]) -> Result<usize, OutOfBounds> {
+        render_packed(&self.serialize_row(row, mask), buffer)
+    }
+
+    /// Render every shown bit plane of every row pair into `buffer`, in output
+    /// order (every bit plane of the first row, from the least significant, then
+    /// the next row), returning the number of samples written.
+    ///
+    /// Each row must be selected (at the address of its
+    /// [row map](Self::set_row_map) entry) while it is shifted, and each bit plane
+    /// shown for [`plane_duration_us`](Self::plane_duration_us) after it is
+    /// latched.
+    pub fn render_frame(&self, buffer: &mut [u8]) -> Result<usize, OutOfBounds> {
+        let mut written = 0;
+
+        for row in 0..SCAN as u8 {
+            for mask in self.ftc.masks() {
+                let rest = buffer.get_mut(written..).ok_or(OutOfBounds)?;
+
+                written += self.render_row(row, mask, rest)?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Re-render only the rows [dirty](Self::is_row_dirty) to `rendered` into
+    /// `buffer`, which holds the frame rendered along with it, and mark every row
+    /// clean for it. Returns the number of samples of the whole frame.
+    ///
+    /// Static content then costs no rendering at all, and partial updates only
+    /// the rows they touch. Each buffer is kept up to date with its own
+    /// `rendered`, starting out [default](RenderedRows::default).
+    pub fn render_dirty(
+        &mut self,
+        buffer: &mut [u8],
+        rendered: &mut RenderedRows,
+    ) -> Result<usize, OutOfBounds> {
+        let row_samples = row_len(WIDTH) * self.ftc.masks().len();
+        let buffer = buffer.get_mut(..row_samples * SCAN).ok_or(OutOfBounds)?;
+
+        for (row, samples) in buffer.chunks_exact_mut(row_samples).enumerate() {
+            if !self.is_row_dirty(row as u8, rendered) {
+                continue;
+            }
+
+            for (mask, plane) in self
+                .ftc
+                .masks()
+                .zip(samples.chunks_exact_mut(row_len(WIDTH)))
+            {
+                self.render_row(row as u8, mask, plane)?;
+            }
+        }
+
+        self.mark_rendered(rendered);
+
+        Ok(buffer.len())
+    }
+
+    /// The time (in microseconds) the bit plane `mask` is shown for, or 0 if it is
+    /// not shown (see [`set_active_bits`](Self::set_active_bits)).
+    ///
+    /// This excludes the [row brightness](Self::set_row_brightness), see
+    /// [`row_plane_duration_us`](Self::row_plane_duration_us).
+    pub fn plane_duration_us(&self, mask: u8) -> u32 {
+        if self.ftc.masks().contains(&mask) {
+            self.ftc.duration(&mask)
+        } else {
+            0
+        }
+    }
+
+    /// The time (in microseconds) the bit plane `mask` of scan row `row` is shown
+    /// for, or 0 if it is not shown.
+    ///
+    /// *`row` must be less than `SCAN`.*
+    pub fn row_plane_duration_us(&self, row: u8, mask: u8) -> u32 {
+        if self.ftc.masks().contains(&mask) {
+            self.show_duration(row, &mask)
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{sink, test_utils::*};
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    #[test]
+    fn render_row_framing() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+
+        Pixel(Point::new(1, 16), Rgb565::BLUE)
+            .draw(&mut display)
+            .unwrap();
+
+        let mut buffer = [0xaa; row_len(64) + 1];
+
+        assert_eq!(display.render_row(0, 1, &mut buffer), Ok(row_len(64)));
+        assert_eq!(buffer[..6], [0, CLK, sink::B2, sink::B2 | CLK, 0, CLK]);
+        assert_eq!(buffer[row_len(64) - 2..], [LAT, 0, 0xaa]);
+
+        assert_eq!(
+            display.render_row(0, 1, &mut buffer[..row_len(64) - 1]),
+            Err(OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn render_frame_order() {
+        let log = Log::default();
+        let mut display = display::<3>(&log);
+        let mut buffer = [0; 16 * 2 * row_len(64)];
+
+        display.set_active_bits(2).unwrap();
+        display.set_pixel_raw(0, 5, (0xff, 0, 0)).unwrap();
+
+        assert_eq!(display.render_frame(&mut buffer), Ok(buffer.len()));
+        assert_eq!(display.plane_duration_us(0), 0);
+        assert!(display.plane_duration_us(2) > display.plane_duration_us(1));
+
+        let row = |row: usize, plane: usize| (row * 2 + plane) * row_len(64);
+
+        assert_eq!(buffer[row(5, 0)], sink::R1);
+        assert_eq!(buffer[row(5, 1)], sink::R1);
+        assert_eq!(buffer[row(4, 1)], 0);
+        assert_eq!(
+            display.render_frame(&mut buffer[..row(15, 1)]),
+            Err(OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn render_dirty_rows() {
+        let log = Log::default();
+        let mut display = display::<2>(&log);
+        let mut partial = [0; 16 * 2 * row_len(64)];
+        let mut full = [0; 16 * 2 * row_len(64)];
+        let mut rendered = RenderedRows::default();
+        let mut other = RenderedRows::default();
+
+        assert!((0..16).all(|row| display.is_row_dirty(row, &rendered)));
+        assert_eq!(
+            display.render_dirty(&mut partial, &mut rendered),
+            Ok(partial.len())
+        );
+        assert!((0..16).all(|row| !display.is_row_dirty(row, &rendered)));
+        // another consumer still has every row to render
+        assert!((0..16).all(|row| display.is_row_dirty(row, &other)));
+
+        display.mark_rendered(&mut other);
+
+        Pixel(Point::new(3, 20), Rgb565::WHITE)
+            .draw(&mut display)
+            .unwrap();
+        // unchanged pixels leave their rows clean
+        Pixel(Point::new(3, 2), Rgb565::BLACK)
+            .draw(&mut display)
+            .unwrap();
+
+        assert!((0..16)
+            .filter(|row| display.is_row_dirty(*row, &rendered))
+            .eq([4]));
+
+        // stale samples of clean rows are kept
+        partial[0] = 0xaa;
+        display.render_dirty(&mut partial, &mut rendered).unwrap();
+        display.render_frame(&mut full).unwrap();
+        partial[0] = 0;
+
+        assert_eq!(partial, full);
+        // rendering for one consumer leaves the other's dirty rows
+        assert!((0..16)
+            .filter(|row| display.is_row_dirty(*row, &other))
+            .eq([4]));
+    }
+}
@@ -0,0 +1,691 @@
+//! Driving several independent panels from a single scan loop.
+
+use core::ops::Range;
+
+use crate::{
+    field_rows, fmt, pins::*, pipeline::DisplayPipeline, sink, ConfigError, DelayProvider, Error,
+    FrameStorage, Hub75,
+};
+
+/// Displays refreshed in lockstep, scanned as configured for the first one.
+trait Lockstep<const SCAN: usize> {
+    type Error;
+
+    /// Fail unless every display can be driven by the shared scan.
+    fn check_pwm_mode(&self) -> Result<(), ConfigError>;
+
+    /// Whether every display is [blanked](Hub75::blank).
+    fn all_blanked(&self) -> bool;
+
+    /// The field of the first display, see [`Hub75::field`].
+    fn field(&self) -> Option<bool>;
+
+    /// The scan order of the first display.
+    fn scan_order(&self) -> [u8; SCAN];
+
+    /// The shown bit planes of the first display.
+    fn masks(&self) -> Range<u8>;
+
+    /// The latch guard of the first display.
+    fn latch_guard_us(&self) -> u32;
+
+    /// The show duration of the first display, see [`Hub75::show_duration`].
+    fn show_duration(&self, row: u8, mask: u8) -> u32;
+
+    /// The idle time of the first display, see [`Hub75::idle_us`].
+    fn idle_us(&self, field: Option<bool>) -> u32;
+
+    /// Select the scan row `row` of every display.
+    fn set_row(&mut self, row: u8) -> Result<(), Self::Error>;
+
+    /// Shift the bit plane `mask` of the scan row `row` into every display.
+    fn shift_row<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        row: u8,
+        mask: u8,
+    ) -> Result<(), Self::Error>;
+
+    /// Latch every display.
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), Self::Error>;
+
+    /// Enable the output of every display which is not blanked.
+    fn enable_output(&mut self) -> Result<(), Self::Error>;
+
+    /// Disable the output of every display.
+    fn disable_output(&mut self) -> Result<(), Self::Error>;
+
+    /// Begin `field` on every display, see [`Hub75::begin_field`].
+    fn begin_field(&mut self, field: Option<bool>);
+
+    /// Complete `field` on every display, see [`Hub75::complete_field`].
+    fn complete_field(&mut self, field: Option<bool>);
+}
+
+/// Output a frame (or field) of every display of `group`, with the per-frame
+/// handling of [`Hub75::output`].
+fn output<const SCAN: usize, Group: Lockstep<SCAN>, Delay: DelayProvider>(
+    group: &mut Group,
+    delay: &mut Delay,
+) -> Result<u32, Error<Group::Error>> {
+    group.check_pwm_mode()?;
+
+    scan(group, delay).map_err(Error::Pins)
+}
+
+/// Output a frame (or field) of every display of `group`, see [`output`].
+fn scan<const SCAN: usize, Group: Lockstep<SCAN>, Delay: DelayProvider>(
+    group: &mut Group,
+    delay: &mut Delay,
+) -> Result<u32, Group::Error> {
+    if group.all_blanked() {
+        return Ok(0);
+    }
+
+    let field = group.field();
+
+    group.begin_field(field);
+
+    for row in field_rows(group.scan_order(), field) {
+        group.set_row(row)?;
+
+        for mask in group.masks() {
+            group.shift_row(delay, row, mask)?;
+
+            let guard = group.latch_guard_us();
+
+            if guard > 0 {
+                delay.delay_us(guard);
+            }
+
+            group.latch(delay)?;
+            group.enable_output()?;
+            delay.delay_us(group.show_duration(row, mask));
+            group.disable_output()?;
+        }
+    }
+
+    let idle = group.idle_us(field);
+
+    group.complete_field(field);
+
+    if idle > 0 {
+        delay.delay_us(idle);
+    }
+
+    Ok(idle)
+}
+
+/// Two independent (not chained) displays refreshed in lockstep.
+///
+/// Calling `output` on each display in turn halves the refresh rate of both and
+/// makes them beat against each other. Instead, this combinator interleaves the
+/// scans: for every row and bit plane, both displays are shifted and latched, and
+/// then shown simultaneously.
+///
+/// Both displays must have the same color depth, which is enforced by the
+/// available constructors.
+pub struct SyncedPanels<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> SyncedPanels<A, B> {
+    /// Access the first display, e.g. to draw to it.
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.first
+    }
+
+    /// Access the second display, e.g. to draw to it.
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.second
+    }
+
+    /// Release the underlying displays.
+    pub fn release(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPinsA,
+        RowPinsA,
+        DataPinsA,
+        FrameA,
+        PipelineA,
+        ColorPinsB,
+        RowPinsB,
+        DataPinsB,
+        FrameB,
+        PipelineB,
+    >
+    SyncedPanels<
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPinsA, RowPinsA, DataPinsA, FrameA, PipelineA>,
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPinsB, RowPinsB, DataPinsB, FrameB, PipelineB>,
+    >
+where
+    ColorPinsA: IsDualColorPins<Error = E>,
+    RowPinsA: IsRowPins<Error = E>,
+    DataPinsA: IsDataPins<Error = E>,
+    FrameA: FrameStorage<WIDTH, HEIGHT>,
+    PipelineA: DisplayPipeline,
+    ColorPinsB: IsDualColorPins<Error = E>,
+    RowPinsB: IsRowPins<Error = E>,
+    DataPinsB: IsDataPins<Error = E>,
+    FrameB: FrameStorage<WIDTH, HEIGHT>,
+    PipelineB: DisplayPipeline,
+{
+    /// Combine two displays of matching size and color depth.
+    ///
+    /// The frame time compensation (and active bit planes and latch guard) of the
+    /// *first* display is used for both, so the displays should be configured identically.
+    pub fn new(
+        first: Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPinsA, RowPinsA, DataPinsA, FrameA, PipelineA>,
+        second: Hub75<
+            WIDTH,
+            HEIGHT,
+            SCAN,
+            BITS,
+            ColorPinsB,
+            RowPinsB,
+            DataPinsB,
+            FrameB,
+            PipelineB,
+        >,
+    ) -> Self {
+        fmt::trace!("new SyncedPanels with {} bits", BITS);
+
+        Self { first, second }
+    }
+
+    /// Output both framebuffers to their displays.
+    ///
+    /// Like [`Hub75::output`], the remaining time of the minimum frame interval is
+    /// idled and returned, and only every other row is output while interlaced,
+    /// both as configured for the first display. A [blanked](Hub75::blank)
+    /// display stays dark, and nothing is output while both are.
+    ///
+    /// S-PWM driver chips are not supported, and fail with
+    /// [`ConfigError::UnsupportedChipMode`].
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<u32, Error<E>> {
+        output(self, delay)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPinsA,
+        RowPinsA,
+        DataPinsA,
+        FrameA,
+        PipelineA,
+        ColorPinsB,
+        RowPinsB,
+        DataPinsB,
+        FrameB,
+        PipelineB,
+    > Lockstep<SCAN>
+    for SyncedPanels<
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPinsA, RowPinsA, DataPinsA, FrameA, PipelineA>,
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPinsB, RowPinsB, DataPinsB, FrameB, PipelineB>,
+    >
+where
+    ColorPinsA: IsDualColorPins<Error = E>,
+    RowPinsA: IsRowPins<Error = E>,
+    DataPinsA: IsDataPins<Error = E>,
+    FrameA: FrameStorage<WIDTH, HEIGHT>,
+    PipelineA: DisplayPipeline,
+    ColorPinsB: IsDualColorPins<Error = E>,
+    RowPinsB: IsRowPins<Error = E>,
+    DataPinsB: IsDataPins<Error = E>,
+    FrameB: FrameStorage<WIDTH, HEIGHT>,
+    PipelineB: DisplayPipeline,
+{
+    type Error = E;
+
+    fn check_pwm_mode(&self) -> Result<(), ConfigError> {
+        self.first.check_pwm_mode()?;
+        self.second.check_pwm_mode()
+    }
+
+    fn all_blanked(&self) -> bool {
+        self.first.blanked && self.second.blanked
+    }
+
+    fn field(&self) -> Option<bool> {
+        self.first.field()
+    }
+
+    fn scan_order(&self) -> [u8; SCAN] {
+        self.first.scan_order
+    }
+
+    fn masks(&self) -> Range<u8> {
+        self.first.ftc.masks()
+    }
+
+    fn latch_guard_us(&self) -> u32 {
+        self.first.latch_guard_us
+    }
+
+    fn show_duration(&self, row: u8, mask: u8) -> u32 {
+        self.first.show_duration(row, &mask)
+    }
+
+    fn idle_us(&self, field: Option<bool>) -> u32 {
+        self.first.idle_us(field)
+    }
+
+    fn set_row(&mut self, row: u8) -> Result<(), E> {
+        self.first
+            .row_pins
+            .set_row(&self.first.row_map[row as usize])?;
+        self.second
+            .row_pins
+            .set_row(&self.second.row_map[row as usize])
+    }
+
+    fn shift_row<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        row: u8,
+        mask: u8,
+    ) -> Result<(), E> {
+        self.first.shift_row(delay, row, mask)?;
+        self.second.shift_row(delay, row, mask)
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        self.first.data_pins.latch(delay)?;
+        self.second.data_pins.latch(delay)
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        if !self.first.blanked {
+            self.first.data_pins.enable_output()?;
+        }
+
+        if !self.second.blanked {
+            self.second.data_pins.enable_output()?;
+        }
+
+        Ok(())
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        self.first.data_pins.disable_output()?;
+        self.second.data_pins.disable_output()
+    }
+
+    fn begin_field(&mut self, field: Option<bool>) {
+        self.first.begin_field(field);
+        self.second.begin_field(field);
+    }
+
+    fn complete_field(&mut self, field: Option<bool>) {
+        self.first.complete_field(field);
+        self.second.complete_field(field);
+    }
+}
+
+/// Any number of identically typed independent (not chained) displays, e.g. chains
+/// on different pins, refreshed in lockstep.
+///
+/// Unlike [`SyncedPanels`], the displays are also shifted in lockstep: every
+/// column is presented on all color pins before all displays are clocked, so the
+/// time to shift a row does not grow with the number of displays (beyond the pin
+/// toggling itself).
+pub struct SyncedArray<D, const N: usize> {
+    displays: [D; N],
+}
+
+impl<D, const N: usize> SyncedArray<D, N> {
+    /// Access the displays, e.g. to draw to them.
+    pub fn displays_mut(&mut self) -> &mut [D; N] {
+        &mut self.displays
+    }
+
+    /// Release the underlying displays.
+    pub fn release(self) -> [D; N] {
+        self.displays
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+        const N: usize,
+    >
+    SyncedArray<Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>, N>
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    /// Combine displays of matching size and color depth.
+    ///
+    /// The frame time compensation (and active bit planes and latch guard) of the
+    /// *first* display is used for all, so the displays should be configured
+    /// identically.
+    ///
+    /// *`N` must not be zero.*
+    pub fn new(
+        displays: [Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>;
+            N],
+    ) -> Self {
+        const { assert!(N > 0, "at least one display is required") };
+
+        fmt::trace!("new SyncedArray of {} displays with {} bits", N, BITS);
+
+        Self { displays }
+    }
+
+    /// Output all framebuffers to their displays.
+    ///
+    /// The frame is handled like by [`SyncedPanels::output`], as configured for
+    /// the first display.
+    ///
+    /// S-PWM driver chips are not supported, and fail with
+    /// [`ConfigError::UnsupportedChipMode`].
+    ///
+    /// *This function is time-sensitive and should be called as often as possible.*
+    pub fn output<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<u32, Error<E>> {
+        output(self, delay)
+    }
+}
+
+impl<
+        E,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+        const N: usize,
+    > Lockstep<SCAN>
+    for SyncedArray<
+        Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+        N,
+    >
+where
+    ColorPins: IsDualColorPins<Error = E>,
+    RowPins: IsRowPins<Error = E>,
+    DataPins: IsDataPins<Error = E>,
+    Frame: FrameStorage<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline,
+{
+    type Error = E;
+
+    fn check_pwm_mode(&self) -> Result<(), ConfigError> {
+        self.displays
+            .iter()
+            .try_for_each(|display| display.check_pwm_mode())
+    }
+
+    fn all_blanked(&self) -> bool {
+        self.displays.iter().all(|display| display.blanked)
+    }
+
+    fn field(&self) -> Option<bool> {
+        self.displays[0].field()
+    }
+
+    fn scan_order(&self) -> [u8; SCAN] {
+        self.displays[0].scan_order
+    }
+
+    fn masks(&self) -> Range<u8> {
+        self.displays[0].ftc.masks()
+    }
+
+    fn latch_guard_us(&self) -> u32 {
+        self.displays[0].latch_guard_us
+    }
+
+    fn show_duration(&self, row: u8, mask: u8) -> u32 {
+        self.displays[0].show_duration(row, &mask)
+    }
+
+    fn idle_us(&self, field: Option<bool>) -> u32 {
+        self.displays[0].idle_us(field)
+    }
+
+    fn set_row(&mut self, row: u8) -> Result<(), E> {
+        for display in &mut self.displays {
+            display.row_pins.set_row(&display.row_map[row as usize])?;
+        }
+
+        Ok(())
+    }
+
+    fn shift_row<Delay: DelayProvider>(
+        &mut self,
+        delay: &mut Delay,
+        row: u8,
+        mask: u8,
+    ) -> Result<(), E> {
+        let packed = self
+            .displays
+            .each_ref()
+            .map(|display| display.serialize_row(row, mask));
+
+        for column in 0..WIDTH {
+            for (display, packed) in self.displays.iter_mut().zip(&packed) {
+                let (upper, lower) = sink::unpack(packed[column]);
+
+                display.color_pins.set_colors::<1>(&upper, &lower, &0)?;
+            }
+
+            for display in &mut self.displays {
+                display.data_pins.shift(delay)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn latch<Delay: DelayProvider>(&mut self, delay: &mut Delay) -> Result<(), E> {
+        for display in &mut self.displays {
+            display.data_pins.latch(delay)?;
+        }
+
+        Ok(())
+    }
+
+    fn enable_output(&mut self) -> Result<(), E> {
+        for display in &mut self.displays {
+            if !display.blanked {
+                display.data_pins.enable_output()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disable_output(&mut self) -> Result<(), E> {
+        for display in &mut self.displays {
+            display.data_pins.disable_output()?;
+        }
+
+        Ok(())
+    }
+
+    fn begin_field(&mut self, field: Option<bool>) {
+        for display in &mut self.displays {
+            display.begin_field(field);
+        }
+    }
+
+    fn complete_field(&mut self, field: Option<bool>) {
+        for display in &mut self.displays {
+            display.complete_field(field);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{hal::mock::PinState::*, test_utils::*};
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::*,
+    };
+
+    #[test]
+    fn array_lockstep() {
+        let logs = [Log::default(), Log::default(), Log::default()];
+        let mut array = SyncedArray::new(logs.each_ref().map(display::<2>));
+
+        for (display, color) in
+            array
+                .displays_mut()
+                .iter_mut()
+                .zip([Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE])
+        {
+            Pixel(Point::new(1, 16), color).draw(display).unwrap();
+        }
+
+        array.output(&mut ProbeDelay(&logs[0])).unwrap();
+
+        for (log, lower) in logs
+            .iter()
+            .zip([[High, Low, Low], [Low, High, Low], [Low, Low, High]])
+        {
+            let shifted = shifted_colors(log);
+
+            assert_eq!(shifted.len(), 64 * 16 * 2);
+            assert_eq!(shifted[1][3..], lower);
+            assert_eq!(latched_rows(log).len(), 16 * 2);
+        }
+    }
+
+    #[test]
+    fn panels_lockstep() {
+        let log = Log::default();
+        let mut panels = SyncedPanels::new(display::<2>(&log), display::<2>(&log));
+
+        panels.output(&mut ProbeDelay(&log)).unwrap();
+
+        let log = log.borrow();
+        let (lat, oe) = (
+            |state| Event::Pin(PinId::Lat, state),
+            |state| Event::Pin(PinId::Oe, state),
+        );
+        let data: std::vec::Vec<_> = log
+            .iter()
+            .copied()
+            .filter(|event| matches!(event, Event::Pin(PinId::Lat | PinId::Oe, _)))
+            .collect();
+
+        // both panels are latched, then shown together, for every row and bit plane
+        assert_eq!(data.len(), 16 * 2 * 8);
+        assert!(data.chunks(8).all(|window| window
+            == [
+                lat(High),
+                lat(Low),
+                lat(High),
+                lat(Low),
+                oe(Low),
+                oe(Low),
+                oe(High),
+                oe(High)
+            ]));
+
+        // with nothing in between the two output enables
+        let adjacent = |state| {
+            log.windows(2)
+                .filter(|pair| *pair == [oe(state), oe(state)])
+                .count()
+        };
+
+        assert_eq!(adjacent(Low), 16 * 2);
+        assert_eq!(adjacent(High), 16 * 2);
+    }
+
+    #[test]
+    fn panels_frame_checks() {
+        let (first, second) = (Log::default(), Log::default());
+        let mut panels = SyncedPanels::new(display::<2>(&first), display::<2>(&second));
+
+        panels.first_mut().set_interlaced(true);
+        panels.second_mut().blank().unwrap();
+
+        let interval = panels.first_mut().frame_time_us();
+
+        panels.first_mut().set_min_frame_interval_us(interval);
+        second.borrow_mut().clear();
+
+        // the even field, and the remaining half of the interval
+        assert_eq!(
+            panels.output(&mut ProbeDelay(&first)).unwrap(),
+            interval - interval / 2
+        );
+        assert_eq!(
+            latched_rows(&first),
+            (0..16)
+                .step_by(2)
+                .flat_map(|row| [row, row])
+                .collect::<std::vec::Vec<_>>()
+        );
+        assert_eq!(latched_rows(&second), latched_rows(&first));
+        assert!(!second.borrow().contains(&Event::Pin(PinId::Oe, Low)));
+
+        panels.first_mut().blank().unwrap();
+        first.borrow_mut().clear();
+
+        assert_eq!(panels.output(&mut ProbeDelay(&first)).unwrap(), 0);
+        assert!(first.borrow().is_empty());
+    }
+
+    #[test]
+    fn array_frame_checks() {
+        let logs = [Log::default(), Log::default()];
+        let mut array = SyncedArray::new(logs.each_ref().map(display::<2>));
+
+        array.displays_mut()[0].set_interlaced(true);
+        array.output(&mut ProbeDelay(&logs[0])).unwrap();
+        array.output(&mut ProbeDelay(&logs[0])).unwrap();
+
+        // the even field, then the odd one, completing a frame
+        for log in &logs {
+            let rows = latched_rows(log);
+
+            assert_eq!(rows.len(), 16 * 2);
+            assert!(rows[..16].iter().all(|row| row % 2 == 0));
+            assert!(rows[16..].iter().all(|row| row % 2 == 1));
+        }
+
+        assert!(array
+            .displays_mut()
+            .iter()
+            .all(|display| display.frame_count() == 1));
+    }
+}
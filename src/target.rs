@@ -0,0 +1,494 @@
+//! Draw targets for color types other than [`Rgb565`].
+//!
+//! The framebuffer stores 8 bits per channel, so drawing through [`Rgb565`]
+//! throws away precision at the API boundary. These targets draw into the same
+//! framebuffer without quantizing first, and let assets and fonts be drawn in
+//! whatever color type they use. Monochrome content ([`Gray8`] or
+//! [`BinaryColor`]) is drawn through a [`TintTarget`], mapping it to a tint.
+//!
+//! [`Gray8`]: embedded_graphics::pixelcolor::Gray8
+//! [`BinaryColor`]: embedded_graphics::pixelcolor::BinaryColor
+//!
+//! [`Rgb565`]: embedded_graphics::pixelcolor::Rgb565
+
+use core::{convert::Infallible, marker::PhantomData};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::{BinaryColor, Gray8, GrayColor, PixelColor, Rgb888, RgbColor},
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{
+    gamma::Gamma,
+    pins::*,
+    pipeline::{ColorPipeline, DisplayPipeline},
+    FrameBuffer, FrameStorage, Hub75,
+};
+
+/// Convert a drawn color to a stored (gamma corrected) value.
+fn correct<C: Into<Rgb888>>(pipeline: &impl ColorPipeline, color: C) -> (u8, u8, u8) {
+    let color = color.into();
+
+    pipeline.correct(&(color.r(), color.g(), color.b()))
+}
+
+/// A display drawn to with colors of type `C`, see [`Hub75::colored`].
+pub struct ColorTarget<
+    'a,
+    C,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    display:
+        &'a mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>,
+    color: PhantomData<C>,
+}
+
+/// A display drawn to with [`Rgb888`] colors, see [`Hub75::rgb888`].
+pub type Rgb888Target<
+    'a,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline = Gamma,
+> = ColorTarget<
+    'a,
+    Rgb888,
+    WIDTH,
+    HEIGHT,
+    SCAN,
+    BITS,
+    ColorPins,
+    RowPins,
+    DataPins,
+    Frame,
+    Pipeline,
+>;
+
+impl<
+        'a,
+        C,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > ColorTarget<'a, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// The display, e.g. for its settings or to output it.
+    pub fn display(
+        &mut self,
+    ) -> &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        self.display
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Draw to the display with [`Rgb888`] colors, keeping all 8 bits of each
+    /// channel.
+    ///
+    /// Drawing is otherwise identical to drawing to the display itself (gamma
+    /// correction, blending and dirty tracking all apply).
+    pub fn rgb888(
+        &mut self,
+    ) -> Rgb888Target<'_, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+    {
+        self.colored()
+    }
+
+    /// Draw to the display with colors of any type convertible to [`Rgb888`] (e.g.
+    /// [`Bgr565`] or [`Rgb555`]), converted per pixel.
+    ///
+    /// Drawing is otherwise identical to drawing to the display itself (gamma
+    /// correction, blending and dirty tracking all apply).
+    ///
+    /// [`Bgr565`]: embedded_graphics::pixelcolor::Bgr565
+    /// [`Rgb555`]: embedded_graphics::pixelcolor::Rgb555
+    pub fn colored<C: PixelColor + Into<Rgb888>>(
+        &mut self,
+    ) -> ColorTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+    {
+        ColorTarget {
+            display: self,
+            color: PhantomData,
+        }
+    }
+}
+
+impl<
+        C: PixelColor + Into<Rgb888>,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Dimensions
+    for ColorTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+    }
+}
+
+impl<
+        C: PixelColor + Into<Rgb888>,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > DrawTarget
+    for ColorTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    type Color = C;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let pipeline = self.display.pipeline;
+
+        self.display.draw_corrected(
+            pixels
+                .into_iter()
+                .map(|Pixel(coord, color)| (coord, correct(&pipeline, color))),
+        );
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let color = correct(&self.display.pipeline, color);
+
+        self.display.fill_solid_corrected(area, color);
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let pipeline = self.display.pipeline;
+
+        self.display.fill_contiguous_corrected(
+            area,
+            colors.into_iter().map(|color| correct(&pipeline, color)),
+        );
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid(&self.bounding_box(), color)
+    }
+}
+
+/// A monochrome color, drawn through a [`TintTarget`].
+pub trait Monochrome: PixelColor {
+    /// The intensity of the color, where 255 shows the full tint.
+    fn intensity(&self) -> u8;
+}
+
+impl Monochrome for Gray8 {
+    fn intensity(&self) -> u8 {
+        self.luma()
+    }
+}
+
+impl Monochrome for BinaryColor {
+    fn intensity(&self) -> u8 {
+        match self {
+            BinaryColor::Off => 0,
+            BinaryColor::On => 255,
+        }
+    }
+}
+
+/// Scale a tint by the intensity of a monochrome color.
+fn tint<C: Monochrome>(tint: Rgb888, color: C) -> Rgb888 {
+    let scale = |channel: u8| ((channel as u16 * color.intensity() as u16 + 127) / 255) as u8;
+
+    Rgb888::new(scale(tint.r()), scale(tint.g()), scale(tint.b()))
+}
+
+/// A display drawn to with monochrome colors of type `C`, mapped to a tint, see
+/// [`Hub75::tinted`].
+pub struct TintTarget<
+    'a,
+    C,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCAN: usize,
+    const BITS: u8,
+    ColorPins: IsDualColorPins,
+    RowPins: IsRowPins,
+    DataPins: IsDataPins,
+    Frame: FrameStorage<WIDTH, HEIGHT> = FrameBuffer<WIDTH, HEIGHT>,
+    Pipeline: DisplayPipeline = Gamma,
+> {
+    target: ColorTarget<
+        'a,
+        Rgb888,
+        WIDTH,
+        HEIGHT,
+        SCAN,
+        BITS,
+        ColorPins,
+        RowPins,
+        DataPins,
+        Frame,
+        Pipeline,
+    >,
+    tint: Rgb888,
+    color: PhantomData<C>,
+}
+
+impl<
+        'a,
+        C,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > TintTarget<'a, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Change the tint of subsequent draws.
+    pub fn set_tint(&mut self, tint: Rgb888) {
+        self.tint = tint;
+    }
+
+    /// The display, e.g. for its settings or to output it.
+    pub fn display(
+        &mut self,
+    ) -> &mut Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline> {
+        self.target.display()
+    }
+}
+
+impl<
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Hub75<WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    /// Draw monochrome content ([`Gray8`] or [`BinaryColor`], e.g. text or 1-bit
+    /// assets) to the display, mapping full intensity to `tint` and zero to black.
+    ///
+    /// Drawing is otherwise identical to drawing to the display itself. Under an
+    /// additive [blend mode](Self::set_blend_mode), black (e.g.
+    /// [`BinaryColor::Off`]) leaves the content below it untouched.
+    pub fn tinted<C: Monochrome>(
+        &mut self,
+        tint: Rgb888,
+    ) -> TintTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+    {
+        TintTarget {
+            target: self.rgb888(),
+            tint,
+            color: PhantomData,
+        }
+    }
+}
+
+impl<
+        C: Monochrome,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > Dimensions
+    for TintTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<
+        C: Monochrome,
+        const WIDTH: usize,
+        const HEIGHT: usize,
+        const SCAN: usize,
+        const BITS: u8,
+        ColorPins: IsDualColorPins,
+        RowPins: IsRowPins,
+        DataPins: IsDataPins,
+        Frame: FrameStorage<WIDTH, HEIGHT>,
+        Pipeline: DisplayPipeline,
+    > DrawTarget
+    for TintTarget<'_, C, WIDTH, HEIGHT, SCAN, BITS, ColorPins, RowPins, DataPins, Frame, Pipeline>
+{
+    type Color = C;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let tint_color = self.tint;
+
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(coord, color)| Pixel(coord, tint(tint_color, color))),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.fill_solid(area, tint(self.tint, color))
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let tint_color = self.tint;
+
+        self.target.fill_contiguous(
+            area,
+            colors.into_iter().map(|color| tint(tint_color, color)),
+        )
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.clear(tint(self.tint, color))
+    }
+}
+
+#[cfg(all(test, feature = "hal-mock"))]
+mod tests {
+    use super::*;
+    use crate::{gamma::Gamma, test_utils::*, GAMMA8};
+    use embedded_graphics::{
+        pixelcolor::{Bgr565, Rgb555},
+        Drawable,
+    };
+
+    #[test]
+    fn full_precision() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+
+        Pixel(Point::new(1, 2), Rgb888::new(201, 7, 130))
+            .draw(&mut display.rgb888())
+            .unwrap();
+        display
+            .rgb888()
+            .fill_solid(
+                &Rectangle::new(Point::new(4, 0), Size::new(2, 1)),
+                Rgb888::new(3, 255, 0),
+            )
+            .unwrap();
+
+        assert_eq!(
+            display.frame.pixel(1, 2),
+            Some(&(GAMMA8[201], GAMMA8[7], GAMMA8[130]))
+        );
+        assert!(display.frame.data[0][4..6]
+            .iter()
+            .all(|pixel| *pixel == (GAMMA8[3], 255, 0)));
+        // not representable in Rgb565
+        assert_ne!(GAMMA8[201], GAMMA8[(201 / 8 + 1) * 8 - 1]);
+    }
+
+    #[test]
+    fn converted_colors() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let color = Bgr565::new(31, 10, 4);
+        let expected = Rgb888::from(color);
+
+        Pixel(Point::new(0, 0), color)
+            .draw(&mut display.colored())
+            .unwrap();
+        display.colored().clear(Rgb555::new(0, 31, 0)).unwrap();
+        Pixel(Point::new(3, 3), color)
+            .draw(&mut display.colored())
+            .unwrap();
+
+        assert_eq!(display.frame.pixel(0, 0), Some(&(0, 255, 0)));
+        assert_eq!(
+            display.frame.pixel(3, 3),
+            Some(&Gamma::POWER.correct(&(expected.r(), expected.g(), expected.b())))
+        );
+        assert_eq!(expected.r(), 255);
+    }
+
+    #[test]
+    fn monochrome_tint() {
+        let log = Log::default();
+        let mut display = display::<8>(&log);
+        let amber = Rgb888::new(255, 192, 0);
+
+        display.tinted(amber).clear(Gray8::new(128)).unwrap();
+        Pixel(Point::new(1, 0), BinaryColor::On)
+            .draw(&mut display.tinted(amber))
+            .unwrap();
+        Pixel(Point::new(2, 0), BinaryColor::Off)
+            .draw(&mut display.tinted(amber))
+            .unwrap();
+
+        assert_eq!(
+            display.frame.pixel(0, 0),
+            Some(&(GAMMA8[128], GAMMA8[96], 0))
+        );
+        assert_eq!(display.frame.pixel(1, 0), Some(&(255, GAMMA8[192], 0)));
+        assert_eq!(display.frame.pixel(2, 0), Some(&(0, 0, 0)));
+    }
+}
@@ -0,0 +1,187 @@
+//! Recording pins for exercising the output logic on the host.
+
+extern crate std;
+
+use core::{cell::RefCell, convert::Infallible};
+use std::vec::Vec;
+
+use crate::{
+    hal::mock::{DelayUs, OutputPin, PinState},
+    Hub75_64_32_2,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinId {
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E,
+    Clk,
+    Lat,
+    Oe,
+    Gclk,
+    /// A color pin of the third or fourth group of a four group display.
+    Quarter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Pin(PinId, PinState),
+    Delay(u32),
+}
+
+pub type Log = RefCell<Vec<Event>>;
+
+/// A pin recording its state changes into a shared log.
+pub struct Probe<'a> {
+    id: PinId,
+    log: &'a Log,
+}
+
+impl OutputPin for Probe<'_> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.log
+            .borrow_mut()
+            .push(Event::Pin(self.id, PinState::Low));
+
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.log
+            .borrow_mut()
+            .push(Event::Pin(self.id, PinState::High));
+
+        Ok(())
+    }
+}
+
+/// A delay recording its durations into a shared log.
+pub struct ProbeDelay<'a>(pub &'a Log);
+
+impl DelayUs for ProbeDelay<'_> {
+    fn delay_us(&mut self, us: u32) {
+        self.0.borrow_mut().push(Event::Delay(us));
+    }
+}
+
+pub type ProbeColorPins<'a> = (
+    (Probe<'a>, Probe<'a>, Probe<'a>),
+    (Probe<'a>, Probe<'a>, Probe<'a>),
+);
+pub type ProbeRowPins<'a> = (Probe<'a>, Probe<'a>, Probe<'a>, Probe<'a>);
+pub type ProbeDataPins<'a> = (Probe<'a>, Probe<'a>, Probe<'a>);
+
+pub type ProbeDisplay<'a, const BITS: u8> =
+    Hub75_64_32_2<BITS, ProbeColorPins<'a>, ProbeRowPins<'a>, ProbeDataPins<'a>>;
+
+pub fn color_pins(log: &Log) -> ProbeColorPins<'_> {
+    let probe = |id| Probe { id, log };
+
+    (
+        (probe(PinId::R1), probe(PinId::G1), probe(PinId::B1)),
+        (probe(PinId::R2), probe(PinId::G2), probe(PinId::B2)),
+    )
+}
+
+pub fn row_pins(log: &Log) -> ProbeRowPins<'_> {
+    let probe = |id| Probe { id, log };
+
+    (
+        probe(PinId::A),
+        probe(PinId::B),
+        probe(PinId::C),
+        probe(PinId::D),
+    )
+}
+
+pub fn data_pins(log: &Log) -> ProbeDataPins<'_> {
+    let probe = |id| Probe { id, log };
+
+    (probe(PinId::Clk), probe(PinId::Lat), probe(PinId::Oe))
+}
+
+/// The clock, latch, output enable, and grayscale clock pins of S-PWM panels.
+pub fn gclk_data_pins(log: &Log) -> (Probe<'_>, Probe<'_>, Probe<'_>, Probe<'_>) {
+    let (clk, latch, output) = data_pins(log);
+
+    (
+        clk,
+        latch,
+        output,
+        Probe {
+            id: PinId::Gclk,
+            log,
+        },
+    )
+}
+
+/// The A-E row pins of a 64x64 display.
+pub fn row_pins_e(log: &Log) -> (Probe<'_>, Probe<'_>, Probe<'_>, Probe<'_>, Probe<'_>) {
+    let (a, b, c, d) = row_pins(log);
+
+    (a, b, c, d, Probe { id: PinId::E, log })
+}
+
+/// The color pins of the third or fourth group of a four group display.
+pub fn quarter_pins(log: &Log) -> (Probe<'_>, Probe<'_>, Probe<'_>) {
+    let probe = || Probe {
+        id: PinId::Quarter,
+        log,
+    };
+
+    (probe(), probe(), probe())
+}
+
+/// A display whose pins all record into `log`.
+pub fn display<const BITS: u8>(log: &Log) -> ProbeDisplay<'_, BITS> {
+    Hub75_64_32_2::new(color_pins(log), row_pins(log), data_pins(log), 0.5)
+}
+
+/// The states of the R1, G1, B1, R2, G2, B2 pins at every rising clock edge.
+pub fn shifted_colors(log: &Log) -> Vec<[PinState; 6]> {
+    let mut states = [PinState::Low; 6];
+    let mut shifted = Vec::new();
+
+    for event in log.borrow().iter() {
+        match event {
+            Event::Pin(PinId::Clk, PinState::High) => shifted.push(states),
+            Event::Pin(id, state) if (*id as usize) < 6 => states[*id as usize] = *state,
+            _ => {}
+        }
+    }
+
+    shifted
+}
+
+/// The address selected by the row pins at every rising latch edge.
+pub fn latched_rows(log: &Log) -> Vec<u8> {
+    let mut address = 0u8;
+    let mut latched = Vec::new();
+
+    for event in log.borrow().iter() {
+        match event {
+            Event::Pin(PinId::Lat, PinState::High) => latched.push(address),
+            Event::Pin(id @ (PinId::A | PinId::B | PinId::C | PinId::D | PinId::E), state) => {
+                let bit = 1 << (*id as u8 - PinId::A as u8);
+
+                match state {
+                    PinState::High => address |= bit,
+                    PinState::Low => address &= !bit,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    latched
+}